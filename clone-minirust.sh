@@ -1,3 +1,7 @@
 #!/bin/bash
 
 git clone https://github.com/RalfJung/minirust
+
+# `minimize` inspects the machine through accessors which are not part of the spec.
+cp minimize/minirust-ext/lang/inspect.md minirust/spec/lang/
+cp minimize/minirust-ext/mem/inspect.md minirust/spec/mem/
@@ -36,7 +36,7 @@ fn impl_for_struct(s: &ItemStruct) -> Item {
     };
 
     let name = &s.ident;
-    let g = generics_base(&s.generics);
+    let g = generics_base(&s.generics, gccompat_bound());
     let tg = generics_trim(&g);
 
     let ts = quote! {
@@ -84,7 +84,7 @@ fn impl_for_enum(e: &ItemEnum) -> Item {
     }).collect();
 
     let enum_ident = &e.ident;
-    let g = generics_base(&e.generics);
+    let g = generics_base(&e.generics, gccompat_bound());
     let tg = generics_trim(&g);
 
     let ts = quote! {
@@ -101,20 +101,139 @@ fn impl_for_enum(e: &ItemEnum) -> Item {
     syn::parse2(ts).unwrap()
 }
 
+#[proc_macro_derive(Encode)]
+pub fn encode_derive(input: TokenStream1) -> TokenStream1 {
+    let i: syn::Item = parse(input).unwrap();
+    match &i {
+        Item::Struct(s) => encode_for_struct(s).to_token_stream().into(),
+        Item::Enum(e) => encode_for_enum(e).to_token_stream().into(),
+        _ => panic!("#[derive(Encode)] applied to invalid item!"),
+    }
+}
+
+/// Generates `impl Encode for _`-Item for a struct.
+/// The fields are encoded one after another.
+fn encode_for_struct(s: &ItemStruct) -> Item {
+    let members: Vec<Member> = s.fields.iter().enumerate().map(|(i, f)| match &f.ident {
+        Some(ident) => Member::Named(ident.clone()),
+        None => Member::Unnamed(i.into()),
+    }).collect();
+
+    let name = &s.ident;
+    let g = generics_base(&s.generics, encode_bound());
+    let tg = generics_trim(&g);
+    let decoded = decode_fields(quote! { Self }, &s.fields);
+
+    let ts = quote! {
+        impl #g libspecr::Encode for #name #tg {
+            #[allow(unused_variables)]
+            fn encode(&self, out: &mut Vec<u8>) {
+                #(
+                    libspecr::Encode::encode(&self.#members, out);
+                )*
+            }
+
+            #[allow(unused_variables)]
+            fn decode(bytes: &mut &[u8]) -> Option<Self> {
+                Some(#decoded)
+            }
+        }
+    };
+    syn::parse2(ts).unwrap()
+}
+
+/// Generates `impl Encode for _`-Item for an enum.
+/// The index of the variant is encoded as `u32`, followed by its fields.
+fn encode_for_enum(e: &ItemEnum) -> Item {
+    let enum_ident = &e.ident;
+    let g = generics_base(&e.generics, encode_bound());
+    let tg = generics_trim(&g);
+
+    let mut encode_arms: Vec<TokenStream2> = Vec::new();
+    let mut decode_arms: Vec<TokenStream2> = Vec::new();
+    for (i, v) in e.variants.iter().enumerate() {
+        let ident = &v.ident;
+        let tag = i as u32;
+        let names: Vec<Ident> = match &v.fields {
+            Fields::Named(n) => n.named.iter().map(|x| x.ident.clone().unwrap()).collect(),
+            _ => (0..v.fields.len()).map(|i| format_ident!("a{}", i)).collect(),
+        };
+        let pat = match &v.fields {
+            Fields::Named(_) => quote! { Self::#ident { #( #names ),* } },
+            Fields::Unnamed(_) => quote! { Self::#ident(#( #names ),*) },
+            Fields::Unit => quote! { Self::#ident },
+        };
+        encode_arms.push(quote! {
+            #pat => {
+                libspecr::Encode::encode(&#tag, out);
+                #( libspecr::Encode::encode(#names, out); )*
+            }
+        });
+
+        let decoded = decode_fields(quote! { Self::#ident }, &v.fields);
+        decode_arms.push(quote! { #tag => Some(#decoded), });
+    }
+
+    // an empty enum has no values to encode.
+    let encode_body = match encode_arms.is_empty() {
+        true => quote! { match *self {} },
+        false => quote! { match self { #( #encode_arms )* } },
+    };
+
+    let ts = quote! {
+        impl #g libspecr::Encode for #enum_ident #tg {
+            #[allow(unused_variables)]
+            fn encode(&self, out: &mut Vec<u8>) {
+                #encode_body
+            }
+
+            #[allow(unused_variables)]
+            fn decode(bytes: &mut &[u8]) -> Option<Self> {
+                let tag: u32 = libspecr::Encode::decode(bytes)?;
+                match tag {
+                    #( #decode_arms )*
+                    _ => None,
+                }
+            }
+        }
+    };
+    syn::parse2(ts).unwrap()
+}
+
+// constructs `ctor` with all `fields` decoded from `bytes`.
+fn decode_fields(ctor: TokenStream2, fields: &Fields) -> TokenStream2 {
+    let decode = quote! { libspecr::Encode::decode(bytes)? };
+    match fields {
+        Fields::Named(n) => {
+            let names = n.named.iter().map(|x| x.ident.as_ref().unwrap());
+            quote! { #ctor { #( #names: #decode ),* } }
+        },
+        Fields::Unnamed(u) => {
+            let decodes = u.unnamed.iter().map(|_| &decode);
+            quote! { #ctor(#( #decodes ),*) }
+        },
+        Fields::Unit => ctor,
+    }
+}
+
 fn gccompat_bound() -> TypeParamBound {
     parse2(quote! { libspecr::hidden::GcCompat }).unwrap()
 }
 
-// removes defaults from generics, and adds GcCompat to the bounds.
+fn encode_bound() -> TypeParamBound {
+    parse2(quote! { libspecr::Encode }).unwrap()
+}
+
+// removes defaults from generics, and adds `bound` to the bounds.
 // <T : Clone = ()> -> <T : Clone + GcCompat>
-fn generics_base(g: &Generics) -> Generics {
+fn generics_base(g: &Generics, bound: TypeParamBound) -> Generics {
     let mut g = g.clone();
     g.params = g.params.iter().map(|p| {
         match p {
             GenericParam::Type(t) => {
                 let mut t = t.clone();
                 t.default = None;
-                t.bounds.push(gccompat_bound());
+                t.bounds.push(bound.clone());
 
                 GenericParam::Type(t)
             },
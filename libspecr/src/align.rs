@@ -2,7 +2,7 @@ use crate::*;
 
 /// This type is basically a copy of the `Align` type in the Rust compiler.
 /// See [Align](https://doc.rust-lang.org/nightly/nightly-rustc/rustc_target/abi/struct.Align.html).
-#[derive(Clone, Copy, PartialOrd, Ord, PartialEq, Eq, Debug, Hash, GcCompat)]
+#[derive(Clone, Copy, PartialOrd, Ord, PartialEq, Eq, Debug, Hash, GcCompat, Encode)]
 pub struct Align { raw: Int }
 
 impl Align {
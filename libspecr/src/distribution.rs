@@ -1,6 +1,6 @@
 use crate::*;
 
-use rand::rngs::ThreadRng;
+use rand::RngCore;
 use num_bigint::RandBigInt;
 use num_integer::Integer;
 use num_traits::Zero;
@@ -8,7 +8,15 @@ use num_traits::Zero;
 /// A probability distribution over values of type `T`.
 pub trait Distribution<T> {
     /// samples a value from the distribution.
-    fn sample(&self, rng: &mut ThreadRng) -> T;
+    fn sample(&self, rng: &mut dyn RngCore) -> T;
+
+    /// enumerates all values of the distribution, in a deterministic order.
+    /// This is used to explore the choices of `pick` exhaustively.
+    ///
+    /// Distributions which don't override this cannot be used in exhaustive mode, the default panics.
+    fn enumerate(&self) -> Box<dyn Iterator<Item=T> + '_> where T: 'static {
+        panic!("this distribution cannot be enumerated, so `pick` cannot explore it exhaustively; implement `Distribution::enumerate` for it")
+    }
 }
 
 /// Uniformly samples a random non-negative `Int` ...
@@ -22,7 +30,7 @@ pub struct IntDistribution {
 }
 
 impl Distribution<Int> for IntDistribution {
-    fn sample(&self, rng: &mut ThreadRng) -> Int {
+    fn sample(&self, rng: &mut dyn RngCore) -> Int {
         let start = self.start.ext();
         let end = self.end.ext();
         let divisor = self.divisor.ext();
@@ -40,6 +48,16 @@ impl Distribution<Int> for IntDistribution {
 
         Int::wrap(out)
     }
+
+    fn enumerate(&self) -> Box<dyn Iterator<Item=Int> + '_> {
+        assert!(self.start >= Int::ZERO);
+        assert!(self.divisor > Int::ZERO);
+
+        let start = self.start.div_ceil(self.divisor);
+        let end = self.end.div_ceil(self.divisor);
+
+        Box::new((start..end).map(|x| x * self.divisor))
+    }
 }
 
 #[test]
@@ -59,3 +77,31 @@ fn test_int_distr() {
         }
     }
 }
+
+#[test]
+fn test_distr_object_safe() {
+    let distr: &dyn Distribution<Int> = &IntDistribution { start: Int::ZERO, end: Int::from(2), divisor: Int::ONE };
+    assert!(distr.sample(&mut rand::thread_rng()) < Int::from(2));
+}
+
+#[test]
+#[should_panic(expected = "this distribution cannot be enumerated")]
+fn test_enumerate_default() {
+    struct Zero;
+    impl Distribution<Int> for Zero {
+        fn sample(&self, _rng: &mut dyn RngCore) -> Int { Int::ZERO }
+    }
+
+    let _ = Zero.enumerate();
+}
+
+#[test]
+fn test_int_distr_enumerate() {
+    let distr = IntDistribution {
+        start: Int::from(2),
+        end: Int::from(13),
+        divisor: Int::from(4),
+    };
+    let values: Vec<Int> = distr.enumerate().collect();
+    assert_eq!(values, [Int::from(4), Int::from(8), Int::from(12)]);
+}
@@ -0,0 +1,350 @@
+use crate::*;
+
+use std::convert::Infallible;
+
+/// Types which can be serialized to bytes and deserialized again, like to snapshot machine states.
+///
+/// For structs & enums, this can be derived using `#[derive(Encode)]`.
+/// The encoding is not self-describing, so values need to be decoded with the type they were encoded with.
+pub trait Encode: Sized {
+    /// Appends the encoding of `self` to `out`.
+    fn encode(&self, out: &mut Vec<u8>);
+
+    /// Decodes a value from the start of `bytes`, and advances `bytes` past it.
+    /// Returns `None` if `bytes` doesn't start with a valid encoding.
+    fn decode(bytes: &mut &[u8]) -> Option<Self>;
+
+    /// Returns the encoding of `self`.
+    fn to_bytes(&self) -> Vec<u8> {
+        let mut out = Vec::new();
+        self.encode(&mut out);
+
+        out
+    }
+
+    /// Decodes a value from `bytes`, which need to contain exactly one encoded value.
+    fn from_bytes(mut bytes: &[u8]) -> Option<Self> {
+        let val = Self::decode(&mut bytes)?;
+
+        bytes.is_empty().then_some(val)
+    }
+}
+
+// removes the first `n` bytes from `bytes` and returns them.
+fn take<'a>(bytes: &mut &'a [u8], n: usize) -> Option<&'a [u8]> {
+    if bytes.len() < n { return None; }
+    let (head, tail) = bytes.split_at(n);
+    *bytes = tail;
+
+    Some(head)
+}
+
+// impls for primitive integers, encoded in little endian:
+
+macro_rules! int_encode {
+    ( $( $t:ty ),* ) => {
+        $(
+            impl Encode for $t {
+                fn encode(&self, out: &mut Vec<u8>) {
+                    out.extend(self.to_le_bytes());
+                }
+
+                fn decode(bytes: &mut &[u8]) -> Option<Self> {
+                    let b = take(bytes, std::mem::size_of::<$t>())?;
+                    Some(<$t>::from_le_bytes(b.try_into().unwrap()))
+                }
+            }
+        )*
+    };
+}
+
+int_encode!(u8, i8, u16, i16, u32, i32, u64, i64, u128, i128);
+
+// `usize` and `isize` are encoded with 64 bits, independent of the platform.
+impl Encode for usize {
+    fn encode(&self, out: &mut Vec<u8>) {
+        (*self as u64).encode(out);
+    }
+
+    fn decode(bytes: &mut &[u8]) -> Option<Self> {
+        u64::decode(bytes)?.try_into().ok()
+    }
+}
+
+impl Encode for isize {
+    fn encode(&self, out: &mut Vec<u8>) {
+        (*self as i64).encode(out);
+    }
+
+    fn decode(bytes: &mut &[u8]) -> Option<Self> {
+        i64::decode(bytes)?.try_into().ok()
+    }
+}
+
+impl Encode for bool {
+    fn encode(&self, out: &mut Vec<u8>) {
+        (*self as u8).encode(out);
+    }
+
+    fn decode(bytes: &mut &[u8]) -> Option<Self> {
+        match u8::decode(bytes)? {
+            0 => Some(false),
+            1 => Some(true),
+            _ => None,
+        }
+    }
+}
+
+impl Encode for char {
+    fn encode(&self, out: &mut Vec<u8>) {
+        (*self as u32).encode(out);
+    }
+
+    fn decode(bytes: &mut &[u8]) -> Option<Self> {
+        char::from_u32(u32::decode(bytes)?)
+    }
+}
+
+impl Encode for () {
+    fn encode(&self, _out: &mut Vec<u8>) {}
+
+    fn decode(_bytes: &mut &[u8]) -> Option<Self> {
+        Some(())
+    }
+}
+
+impl Encode for Infallible {
+    fn encode(&self, _out: &mut Vec<u8>) {
+        match *self {}
+    }
+
+    fn decode(_bytes: &mut &[u8]) -> Option<Self> {
+        None
+    }
+}
+
+// impls for std types:
+
+// sequences are encoded as their length followed by their elements.
+fn encode_seq<T: Encode>(len: usize, elems: impl Iterator<Item=T>, out: &mut Vec<u8>) {
+    len.encode(out);
+    for x in elems {
+        x.encode(out);
+    }
+}
+
+fn decode_seq<T: Encode, C: FromIterator<T>>(bytes: &mut &[u8]) -> Option<C> {
+    let len = usize::decode(bytes)?;
+    (0..len).map(|_| T::decode(bytes)).collect()
+}
+
+impl Encode for std::string::String {
+    fn encode(&self, out: &mut Vec<u8>) {
+        encode_seq(self.len(), self.bytes(), out);
+    }
+
+    fn decode(bytes: &mut &[u8]) -> Option<Self> {
+        std::string::String::from_utf8(decode_seq(bytes)?).ok()
+    }
+}
+
+impl<T: Encode + Clone> Encode for Vec<T> {
+    fn encode(&self, out: &mut Vec<u8>) {
+        encode_seq(self.len(), self.iter().cloned(), out);
+    }
+
+    fn decode(bytes: &mut &[u8]) -> Option<Self> {
+        decode_seq(bytes)
+    }
+}
+
+impl<T: Encode> Encode for Box<T> {
+    fn encode(&self, out: &mut Vec<u8>) {
+        (**self).encode(out);
+    }
+
+    fn decode(bytes: &mut &[u8]) -> Option<Self> {
+        T::decode(bytes).map(Box::new)
+    }
+}
+
+impl<T: Encode> Encode for Option<T> {
+    fn encode(&self, out: &mut Vec<u8>) {
+        match self {
+            None => 0u8.encode(out),
+            Some(x) => {
+                1u8.encode(out);
+                x.encode(out);
+            },
+        }
+    }
+
+    fn decode(bytes: &mut &[u8]) -> Option<Self> {
+        match u8::decode(bytes)? {
+            0 => Some(None),
+            1 => Some(Some(T::decode(bytes)?)),
+            _ => None,
+        }
+    }
+}
+
+impl<T: Encode, E: Encode> Encode for Result<T, E> {
+    fn encode(&self, out: &mut Vec<u8>) {
+        match self {
+            Ok(x) => {
+                0u8.encode(out);
+                x.encode(out);
+            },
+            Err(e) => {
+                1u8.encode(out);
+                e.encode(out);
+            },
+        }
+    }
+
+    fn decode(bytes: &mut &[u8]) -> Option<Self> {
+        match u8::decode(bytes)? {
+            0 => Some(Ok(T::decode(bytes)?)),
+            1 => Some(Err(E::decode(bytes)?)),
+            _ => None,
+        }
+    }
+}
+
+macro_rules! tuple_encode {
+    ( $( ( $( $t:ident ),* ) ),* ) => {
+        $(
+            #[allow(non_snake_case)]
+            impl<$( $t: Encode ),*> Encode for ( $( $t, )* ) {
+                fn encode(&self, out: &mut Vec<u8>) {
+                    let ( $( $t, )* ) = self;
+                    $( $t.encode(out); )*
+                }
+
+                fn decode(bytes: &mut &[u8]) -> Option<Self> {
+                    Some(( $( $t::decode(bytes)?, )* ))
+                }
+            }
+        )*
+    };
+}
+
+tuple_encode!((A), (A, B), (A, B, C), (A, B, C, D), (A, B, C, D, E));
+
+// arrays have a fixed length, so it is not encoded.
+impl<T: Encode, const N: usize> Encode for [T; N] {
+    fn encode(&self, out: &mut Vec<u8>) {
+        for x in self {
+            x.encode(out);
+        }
+    }
+
+    fn decode(bytes: &mut &[u8]) -> Option<Self> {
+        let elems: Vec<T> = (0..N).map(|_| T::decode(bytes)).collect::<Option<_>>()?;
+        elems.try_into().ok()
+    }
+}
+
+// impls for the types of libspecr:
+
+// The value is encoded, so that values shared by multiple `GcCow`s are encoded multiple times.
+impl<T: Encode + GcCompat + Clone> Encode for GcCow<T> {
+    fn encode(&self, out: &mut Vec<u8>) {
+        self.extract().encode(out);
+    }
+
+    fn decode(bytes: &mut &[u8]) -> Option<Self> {
+        T::decode(bytes).map(GcCow::new)
+    }
+}
+
+// `Int`s are encoded as the length of their two's complement in bytes, followed by it in little endian.
+impl Encode for Int {
+    fn encode(&self, out: &mut Vec<u8>) {
+        let b = self.ext().to_signed_bytes_le();
+        encode_seq(b.len(), b.into_iter(), out);
+    }
+
+    fn decode(bytes: &mut &[u8]) -> Option<Self> {
+        let len = usize::decode(bytes)?;
+        let b = take(bytes, len)?;
+        Some(Int::wrap(ExtInt::from_signed_bytes_le(b)))
+    }
+}
+
+impl Encode for String {
+    fn encode(&self, out: &mut Vec<u8>) {
+        self.get_internal().encode(out);
+    }
+
+    fn decode(bytes: &mut &[u8]) -> Option<Self> {
+        std::string::String::decode(bytes).map(String::from_internal)
+    }
+}
+
+impl Encode for Name {
+    fn encode(&self, out: &mut Vec<u8>) {
+        self.0.encode(out);
+    }
+
+    fn decode(bytes: &mut &[u8]) -> Option<Self> {
+        u32::decode(bytes).map(Name)
+    }
+}
+
+impl<T: Obj + Encode> Encode for List<T> {
+    fn encode(&self, out: &mut Vec<u8>) {
+        encode_seq(self.len().try_to_usize().unwrap(), self.iter(), out);
+    }
+
+    fn decode(bytes: &mut &[u8]) -> Option<Self> {
+        decode_seq(bytes)
+    }
+}
+
+// The elements of `Set`s and `Map`s are encoded in the order of iteration, which may differ for equal values.
+impl<T: Obj + Encode> Encode for Set<T> {
+    fn encode(&self, out: &mut Vec<u8>) {
+        encode_seq(self.len().try_to_usize().unwrap(), self.iter(), out);
+    }
+
+    fn decode(bytes: &mut &[u8]) -> Option<Self> {
+        decode_seq(bytes)
+    }
+}
+
+impl<K: Obj + Encode, V: Obj + Encode> Encode for Map<K, V> {
+    fn encode(&self, out: &mut Vec<u8>) {
+        encode_seq(self.len().try_to_usize().unwrap(), self.iter(), out);
+    }
+
+    fn decode(bytes: &mut &[u8]) -> Option<Self> {
+        decode_seq(bytes)
+    }
+}
+
+#[test]
+fn encode_roundtrip_test() {
+    fn roundtrip<T: Encode + PartialEq + Debug>(x: T) {
+        let bytes = x.to_bytes();
+        assert_eq!(T::from_bytes(&bytes), Some(x));
+    }
+
+    roundtrip(42u8);
+    roundtrip(-7i64);
+    roundtrip(true);
+    roundtrip((1u16, 'x', ()));
+    roundtrip(Some(Ok::<_, bool>([1i32, -2, 3])));
+    roundtrip(Int::from(-1000));
+    roundtrip(Int::from(u128::MAX) * Int::from(u128::MAX));
+    roundtrip(list![Int::from(1), Int::from(2)]);
+    roundtrip([(Int::from(1), Size::from_bytes_const(3)), (Int::from(2), Size::ZERO)].into_iter().collect::<Map<_, _>>());
+    roundtrip([Signed, Unsigned].into_iter().collect::<Set<_>>());
+    roundtrip(String::from_internal("abc".to_string()));
+    roundtrip(Name::from_internal(3));
+
+    // trailing bytes, wrong tags and missing bytes are rejected.
+    assert_eq!(u8::from_bytes(&[1, 2]), None);
+    assert_eq!(bool::from_bytes(&[2]), None);
+    assert_eq!(Int::from_bytes(&[5, 0, 0, 0, 0, 0, 0, 0, 1]), None);
+}
@@ -2,7 +2,7 @@ use crate::*;
 
 use crate::{Size, Signedness};
 
-#[derive(Clone, Copy, PartialEq, Eq, Debug, Hash)]
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Hash, Encode)]
 /// Either `LittleEndian` or `BigEndian`.
 pub enum Endianness {
     #[allow(missing_docs)]
@@ -2,6 +2,7 @@ use crate::*;
 use crate::gc::*;
 
 use std::marker::PhantomData;
+use std::ops::{Deref, DerefMut};
 
 /// A gargabe-collected pointer type implementing Copy.
 pub struct GcCow<T> {
@@ -52,6 +53,13 @@ impl<T: GcCompat> GcCow<T> {
         })
     }
 
+    /// Gives mutable access to the inner value.
+    /// Like `mutate`, this is copy-on-write: the mutated value is written back when the returned guard is dropped.
+    pub fn mutate_guard(&mut self) -> GcCowMut<'_, T> where T: Clone {
+        let val = Some(self.extract());
+        GcCowMut { cow: self, val }
+    }
+
     // this does the copy-on-write
     pub(crate) fn mutate<O>(&mut self, f: impl FnOnce(&mut T) -> O) -> O where T: Clone {
         let mut val = self.extract();
@@ -86,3 +94,44 @@ impl<T: GcCompat> GcCow<T> {
         out
     }
 }
+
+/// A mutable borrow of the value behind a `GcCow`, see `GcCow::mutate_guard`.
+pub struct GcCowMut<'a, T: GcCompat + Clone> {
+    cow: &'a mut GcCow<T>,
+    // only `None` while dropping.
+    val: Option<T>,
+}
+
+impl<T: GcCompat + Clone> Deref for GcCowMut<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        self.val.as_ref().unwrap()
+    }
+}
+
+impl<T: GcCompat + Clone> DerefMut for GcCowMut<'_, T> {
+    fn deref_mut(&mut self) -> &mut T {
+        self.val.as_mut().unwrap()
+    }
+}
+
+impl<T: GcCompat + Clone> Drop for GcCowMut<'_, T> {
+    fn drop(&mut self) {
+        let val = self.val.take().unwrap();
+        *self.cow = GcCow::new(val);
+    }
+}
+
+#[test]
+fn test_mutate_guard() {
+    let mut x = GcCow::new(List::<Int>::new());
+    let y = x;
+    {
+        let mut guard = x.mutate_guard();
+        guard.push(Int::from(1));
+    }
+
+    assert_eq!(x.extract().len(), Int::from(1));
+    assert_eq!(y.extract().len(), Int::from(0));
+}
@@ -27,10 +27,31 @@ fn with_gc_mut<O>(f: impl FnOnce(&mut GcState) -> O) -> O {
 
 /// clears every object not recursively reachable from `root`.
 pub fn mark_and_sweep(root: &impl GcCompat) {
-    with_gc_mut(|st| st.mark_and_sweep(root) );
+    with_gc_mut(|st| st.mark_and_sweep(&[root]) );
+}
+
+/// clears every object not recursively reachable from any of `roots`.
+/// This is called by the safepoints generated for `#[specr::gc_root]`.
+pub fn mark_and_sweep_roots(roots: &[&dyn GcCompat]) {
+    with_gc_mut(|st| st.mark_and_sweep(roots) );
 }
 
 /// clears all objects from the garbage collector.
 pub fn clear() {
     with_gc_mut(|st| st.clear() );
 }
+
+#[test]
+fn test_mark_and_sweep_roots() {
+    let a = GcCow::new(1u32);
+    let b = GcCow::new(2u32);
+
+    // allocate enough garbage, so that `mark_and_sweep` doesn't skip the cleanup.
+    for i in 0..200_000u64 {
+        GcCow::new(i);
+    }
+
+    mark_and_sweep_roots(&[&a, &b]);
+    assert_eq!(with_gc(|st| st.len()), 2);
+    assert_eq!(a.extract() + b.extract(), 3);
+}
@@ -80,17 +80,19 @@ impl GcState {
         self.data.iter().map(|x| mem::size_of_val(&*x)).sum::<usize>()
     }
 
-    pub fn mark_and_sweep(&mut self, root: &impl GcCompat) {
+    pub fn mark_and_sweep(&mut self, roots: &[&dyn GcCompat]) {
         // don't cleanup, if you didn't allocate at least LEEWAY_MEMORY bytes since the last cleanup.
         if self.current_memory < self.last_memory + LEEWAY_MEMORY {
             return;
         }
 
-        // objects which are found to be directly reachable from `root`.
+        // objects which are found to be directly reachable from `roots`.
         let mut open = HashSet::new();
-        root.points_to(&mut open);
+        for root in roots {
+            root.points_to(&mut open);
+        }
 
-        // objects which are found to be reachable from `root`, whose children have already been added.
+        // objects which are found to be reachable from `roots`, whose children have already been added.
         let mut done = HashSet::new();
 
         while let Some(i) = open.iter().next().cloned() {
@@ -106,7 +108,7 @@ impl GcState {
                 }
             }
         }
-        // seen now contains the `usize` which are reachable from root.
+        // seen now contains the `usize` which are reachable from roots.
         let seen = done;
 
         // clear all unreachable objects.
@@ -24,6 +24,8 @@ use im::Vector as IMVector;
 
 extern crate gccompat_derive;
 use gccompat_derive::GcCompat;
+/// Derives [`Encode`] for a struct or enum.
+pub use gccompat_derive::Encode;
 
 mod int;
 pub use int::*;
@@ -73,6 +75,9 @@ mod mutability;
 mod signedness;
 use signedness::*;
 
+mod encode;
+pub use encode::*;
+
 mod gc;
 use gc::*;
 
@@ -82,7 +87,7 @@ use obj::*;
 #[doc(hidden)]
 pub mod hidden {
     pub use crate::obj::*;
-    pub use crate::gc::{GcCow, GcCompat, mark_and_sweep, clear};
+    pub use crate::gc::{GcCow, GcCowMut, GcCompat, mark_and_sweep, mark_and_sweep_roots, clear};
 }
 
 pub use crate::nondet::*;
@@ -2,7 +2,7 @@ use crate::*;
 
 #[derive(Clone, Copy, PartialEq, Eq, Debug, Hash)]
 /// Either `Mutable` or `Immutable`.
-#[derive(GcCompat)]
+#[derive(GcCompat, Encode)]
 pub enum Mutability {
     #[allow(missing_docs)]
     Mutable,
@@ -1,23 +1,114 @@
 use crate::*;
 
+use std::cell::RefCell;
 use std::convert::Infallible;
 use std::ops::*;
 
-#[derive(Copy, Clone, GcCompat)]
+use rand::SeedableRng;
+use rand::rngs::StdRng;
+
+#[derive(Copy, Clone, GcCompat, Encode)]
 /// Non-determinism primitive. See [Non-determinism](https://github.com/RalfJung/minirust/blob/master/README.md#non-determinism).
 pub struct Nondet<T>(pub(crate) T);
 
 /// The `pick` function from the minirust spec.  See [Non-determinism](https://github.com/RalfJung/minirust/blob/master/README.md#non-determinism).
+///
+/// How the value is chosen is controlled by [`set_nondet_mode`].
 pub fn pick<T: Obj>(distr: impl Distribution<T>, f: impl Fn(T) -> bool) -> crate::Nondet<T> {
-    let mut rng = rand::thread_rng();
-    for _ in 0..50 {
-        let s = distr.sample(&mut rng);
-        if f(s) {
-            return Nondet(s);
+    NONDET_STATE.with(|st| {
+        match &mut *st.borrow_mut() {
+            NondetState::Random(rng) => {
+                for _ in 0..PICK_ATTEMPTS {
+                    let s = distr.sample(rng);
+                    if f(s) {
+                        return Nondet(s);
+                    }
+                }
+            },
+            NondetState::Exhaustive { prefix, bound, trace } => {
+                let alternatives: Vec<T> = distr.enumerate()
+                                                .take(PICK_ATTEMPTS * *bound)
+                                                .filter(|x| f(*x))
+                                                .take(*bound)
+                                                .collect();
+                if !alternatives.is_empty() {
+                    let taken = prefix.get(trace.len()).copied().unwrap_or(0);
+                    let choice = Choice { taken, alternatives: alternatives.len() };
+                    trace.push(choice);
+                    return Nondet(alternatives[taken]);
+                }
+            },
         }
-    }
 
-    panic!("Timeout! `pick` could not find a valid value.");
+        panic!("Timeout! `pick` could not find a valid value.");
+    })
+}
+
+// How many values `pick` considers per alternative, before giving up.
+const PICK_ATTEMPTS: usize = 50;
+
+/// Configures how `pick` resolves non-determinism.
+pub enum NondetMode {
+    /// Sample values using an RNG seeded with entropy. This is the default.
+    Random,
+    /// Sample values using an RNG seeded with the given seed.
+    /// This makes the choices reproducible.
+    Seeded(u64),
+    /// Each `pick` chooses among at most `bound` valid values of its distribution.
+    /// The `i`-th `pick` takes the alternative with index `prefix[i]`, or the first alternative if `prefix` is too short.
+    ///
+    /// The choices taken can be obtained using [`take_choice_trace`].
+    Exhaustive {
+        /// The indices of alternatives to take.
+        prefix: Vec<usize>,
+        /// The maximal number of alternatives per `pick`.
+        bound: usize,
+    },
+}
+
+/// A choice made by `pick` in [`NondetMode::Exhaustive`].
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Choice {
+    /// The index of the taken alternative.
+    pub taken: usize,
+    /// The number of alternatives that were available.
+    pub alternatives: usize,
+}
+
+enum NondetState {
+    Random(Box<StdRng>),
+    Exhaustive {
+        prefix: Vec<usize>,
+        bound: usize,
+        trace: Vec<Choice>,
+    },
+}
+
+// Note that each thread resolves its non-determinism independently, just like each thread has its own GC_STATE.
+thread_local! {
+    static NONDET_STATE: RefCell<NondetState> = RefCell::new(NondetState::Random(Box::new(StdRng::from_entropy())));
+}
+
+/// Sets how all following calls to `pick` on this thread choose their values.
+pub fn set_nondet_mode(mode: NondetMode) {
+    let state = match mode {
+        NondetMode::Random => NondetState::Random(Box::new(StdRng::from_entropy())),
+        NondetMode::Seeded(seed) => NondetState::Random(Box::new(StdRng::seed_from_u64(seed))),
+        NondetMode::Exhaustive { prefix, bound } => {
+            assert!(bound > 0, "`bound` has to be positive!");
+            NondetState::Exhaustive { prefix, bound, trace: Vec::new() }
+        },
+    };
+    NONDET_STATE.with(|st| *st.borrow_mut() = state);
+}
+
+/// Returns the choices taken by `pick` since the last call to [`set_nondet_mode`] or `take_choice_trace`.
+/// This is always empty outside of [`NondetMode::Exhaustive`].
+pub fn take_choice_trace() -> Vec<Choice> {
+    NONDET_STATE.with(|st| match &mut *st.borrow_mut() {
+        NondetState::Random(_) => Vec::new(),
+        NondetState::Exhaustive { trace, .. } => std::mem::take(trace),
+    })
 }
 
 /// The `predict` function from the minirust spec. See [Non-determinism](https://github.com/RalfJung/minirust/blob/master/README.md#non-determinism).
@@ -41,3 +132,23 @@ impl<T> FromResidual<Infallible> for Nondet<T> {
         match residual {}
     }
 }
+
+#[test]
+fn test_pick_modes() {
+    let distr = || IntDistribution { start: Int::ZERO, end: Int::from(100), divisor: Int::ONE };
+    let even = |x: Int| x % 2 == 0;
+
+    set_nondet_mode(NondetMode::Seeded(42));
+    let a: Vec<Int> = (0..10).map(|_| pick(distr(), even).0).collect();
+    set_nondet_mode(NondetMode::Seeded(42));
+    let b: Vec<Int> = (0..10).map(|_| pick(distr(), even).0).collect();
+    assert_eq!(a, b);
+
+    set_nondet_mode(NondetMode::Exhaustive { prefix: vec![2], bound: 3 });
+    assert_eq!(pick(distr(), even).0, Int::from(4));
+    assert_eq!(pick(distr(), even).0, Int::ZERO);
+    let trace = take_choice_trace();
+    assert_eq!(trace, [Choice { taken: 2, alternatives: 3 }, Choice { taken: 0, alternatives: 3 }]);
+
+    set_nondet_mode(NondetMode::Random);
+}
@@ -1,6 +1,6 @@
 use crate::*;
 
-#[derive(Clone, Copy, PartialEq, Eq, Debug, Hash, GcCompat)]
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Hash, GcCompat, Encode)]
 /// Expresses whether an integer has a sign or not
 pub enum Signedness {
     #[allow(missing_docs)]
@@ -9,7 +9,7 @@ use std::ops::{Add, Mul};
 ///
 /// Note that the `Size` type has no upper-bound.
 /// Users needs check whether a given `Size` is too large for their Machine themselves.
-#[derive(Clone, Copy, PartialOrd, Ord, PartialEq, Eq, Debug, Hash, GcCompat)]
+#[derive(Clone, Copy, PartialOrd, Ord, PartialEq, Eq, Debug, Hash, GcCompat, Encode)]
 pub struct Size { raw: Int }
 
 impl Size {
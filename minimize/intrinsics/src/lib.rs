@@ -2,10 +2,18 @@
 //! The bodies of these functions are mostly used through `tests/rust.sh`.
 
 #![feature(allocator_api)]
+#![feature(core_intrinsics)]
 
 use std::fmt::Display;
 use std::alloc::{System, Layout, Allocator};
 use std::ptr::NonNull;
+use std::sync::Mutex;
+use std::thread::JoinHandle;
+use std::intrinsics as core_intr;
+
+// The join handles of all spawned threads.
+// The thread with id `i` is stored at index `i-1`, as id 0 is the main thread.
+static THREADS: Mutex<Vec<Option<JoinHandle<()>>>> = Mutex::new(Vec::new());
 
 pub fn print(t: impl Display) {
     println!("{t}");
@@ -29,3 +37,35 @@ pub unsafe fn deallocate(ptr: *mut u8, size: usize, align: usize) {
     let layout = Layout::from_size_align(size, align).unwrap();
     unsafe { System.deallocate(ptr, layout); }
 }
+
+pub fn spawn(f: fn(*const ()), data_ptr: *const ()) -> u32 {
+    // raw pointers are not `Send`, so we smuggle it as an integer.
+    let data_ptr = data_ptr as usize;
+    let handle = std::thread::spawn(move || f(data_ptr as *const ()));
+
+    let mut threads = THREADS.lock().unwrap();
+    threads.push(Some(handle));
+    threads.len() as u32
+}
+
+pub fn join(thread_id: u32) {
+    let handle = THREADS.lock().unwrap()[thread_id as usize - 1].take();
+    handle.expect("thread joined twice!").join().unwrap();
+}
+
+pub unsafe fn atomic_store<T: Copy>(ptr: *mut T, val: T) {
+    unsafe { core_intr::atomic_store_seqcst(ptr, val) }
+}
+
+pub unsafe fn atomic_load<T: Copy>(ptr: *mut T) -> T {
+    unsafe { core_intr::atomic_load_seqcst(ptr) }
+}
+
+/// Returns the value before the operation, just like `AtomicU32::compare_exchange`.
+pub unsafe fn compare_exchange<T: Copy>(ptr: *mut T, current: T, next: T) -> T {
+    unsafe { core_intr::atomic_cxchg_seqcst_seqcst(ptr, current, next).0 }
+}
+
+pub fn fence() {
+    unsafe { core_intr::atomic_fence_seqcst() }
+}
@@ -4,6 +4,7 @@ use crate::*;
 // Those can be found by grepping "IGNORED".
 
 pub fn translate_bb<'cx, 'tcx>(
+    bb_name: BbName,
     bb: &rs::BasicBlockData<'tcx>,
     fcx: &mut FnCtxt<'cx, 'tcx>,
 ) -> BasicBlock {
@@ -11,12 +12,19 @@ pub fn translate_bb<'cx, 'tcx>(
     for stmt in bb.statements.iter() {
         // unsupported statements will be IGNORED.
         if let Some(x) = translate_stmt(stmt, fcx) {
+            let idx = statements.len().try_to_usize().unwrap();
+            fcx.record_span(bb_name, idx, stmt.source_info.span);
             statements.push(x);
         }
     }
+
+    let terminator = bb.terminator();
+    let idx = statements.len().try_to_usize().unwrap();
+    fcx.record_span(bb_name, idx, terminator.source_info.span);
+
     BasicBlock {
         statements,
-        terminator: translate_terminator(bb.terminator(), fcx),
+        terminator: translate_terminator(terminator, fcx),
     }
 }
 
@@ -101,6 +109,17 @@ fn translate_call<'cx, 'tcx>(
             "exit" => Intrinsic::Exit,
             "allocate" => Intrinsic::Allocate,
             "deallocate" => Intrinsic::Deallocate,
+            "spawn" => Intrinsic::Spawn,
+            "join" => Intrinsic::Join,
+            "atomic_store" => Intrinsic::AtomicStore,
+            "atomic_load" => Intrinsic::AtomicLoad,
+            "compare_exchange" => Intrinsic::AtomicCompareExchange,
+            // all atomic operations in MiniRust are sequentially consistent,
+            // hence fences have no effect and are IGNORED.
+            "fence" => {
+                let target = target.expect("`fence` has to return!");
+                return Terminator::Goto(fcx.bb_name_map[&target]);
+            }
             name => panic!("unsupported intrinsic `{}`", name),
         };
         Terminator::CallIntrinsic {
@@ -113,16 +132,26 @@ fn translate_call<'cx, 'tcx>(
         let (ret_abi, arg_abis) = calc_abis(*f, substs_ref, fcx.cx.tcx);
         let args: List<_> = args.iter().map(|op| translate_operand(op, fcx)).collect();
 
-        if !fcx.cx.fn_name_map.contains_key(&key) {
-            let fn_name = fcx.cx.fn_name_map.len();
-            let fn_name = FnName(Name::from_internal(fn_name as _));
-            fcx.cx.fn_name_map.insert(key, fn_name);
-        }
         Terminator::Call {
-            callee: build::fn_ptr(fcx.cx.fn_name_map[&key].0.get_internal()),
+            callee: build::fn_ptr(fn_name_of(key, fcx).0.get_internal()),
             arguments: args.zip(arg_abis),
             ret: Some((translate_place(&destination, fcx), ret_abi)),
             next_block: target.as_ref().map(|t| fcx.bb_name_map[t]),
         }
     }
 }
+
+/// returns the `FnName` of the function `key`.
+/// If this function is not yet known, a fresh `FnName` is assigned to it,
+/// and it will be translated later on.
+pub fn fn_name_of<'cx, 'tcx>(
+    key: (rs::DefId, rs::SubstsRef<'tcx>),
+    fcx: &mut FnCtxt<'cx, 'tcx>,
+) -> FnName {
+    if !fcx.cx.fn_name_map.contains_key(&key) {
+        let fn_name = fcx.cx.fn_name_map.len();
+        let fn_name = FnName(Name::from_internal(fn_name as _));
+        fcx.cx.fn_name_map.insert(key, fn_name);
+    }
+    fcx.cx.fn_name_map[&key]
+}
@@ -0,0 +1,302 @@
+// This module implements `minimize debug <file>`, an interactive debugger for the translated MiniRust program.
+
+use crate::*;
+use exec::{fmt_block, fmt_pos, FnNames};
+use std::io::{BufRead, Write};
+
+const HELP: &str = "\
+commands:
+  s, step [n]          execute the next n statements or terminators (default 1)
+  c, continue          run until a breakpoint is hit or the program terminates
+  b, break <loc>       add a breakpoint, <loc> is `<line>`, `<file>:<line>` or `f<n>:bb<n>`
+  d, delete <n>        remove the n-th breakpoint
+  breakpoints          list all breakpoints
+  w, where             show the current position and the call stack
+  l, locals            show the locals of the current function
+  m, memory            show the memory allocations
+  g, globals [n]       show all globals, or only the global g<n>
+  q, quit              stop debugging";
+
+enum Breakpoint {
+    /// A line in a Rust source file, the file is matched by suffix.
+    Line { file: Option<String>, line: usize },
+    /// The beginning of a MiniRust basic block.
+    Block(FnName, BbName),
+}
+
+impl Breakpoint {
+    fn parse(s: &str) -> Option<Breakpoint> {
+        if let Ok(line) = s.parse() {
+            return Some(Breakpoint::Line { file: None, line });
+        }
+
+        let (l, r) = s.rsplit_once(':')?;
+        if let Ok(line) = r.parse() {
+            let file = Some(l.to_string());
+            return Some(Breakpoint::Line { file, line });
+        }
+
+        let f = l.strip_prefix('f')?.parse().ok()?;
+        let bb = r.strip_prefix("bb")?.parse().ok()?;
+        Some(Breakpoint::Block(
+            FnName(Name::from_internal(f)),
+            BbName(Name::from_internal(bb)),
+        ))
+    }
+
+    fn hit(&self, pos: StmtIdx, loc: Option<&SourceLoc>) -> bool {
+        match self {
+            Breakpoint::Line { file, line } => {
+                let Some(loc) = loc else { return false };
+                let file_matches = file.as_ref().map_or(true, |f| loc.file.ends_with(&**f));
+                file_matches && loc.line == *line
+            }
+            Breakpoint::Block(f, bb) => pos == (*f, *bb, 0),
+        }
+    }
+}
+
+impl std::fmt::Display for Breakpoint {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Breakpoint::Line { file: Some(file), line } => write!(f, "{file}:{line}"),
+            Breakpoint::Line { file: None, line } => write!(f, "line {line}"),
+            Breakpoint::Block(fn_name, bb_name) => write!(f, "{}", fmt_block(*fn_name, *bb_name)),
+        }
+    }
+}
+
+struct Debugger {
+    prog: Program,
+    span_table: SpanTable,
+    fn_names: FnNames,
+    machine: Machine<BasicMemory>,
+    breakpoints: Vec<Breakpoint>,
+}
+
+/// Runs the debugger, which reads its commands from stdin.
+/// If `commands` is a file, the commands are read from it instead and echoed, which is used by the tests.
+pub fn debug(prog: Program, span_table: SpanTable, commands: Option<String>) {
+    let machine = match Machine::<BasicMemory>::new(prog).get_internal() {
+        Ok(machine) => machine,
+        Err(outcome) => {
+            eprintln!("program terminated during initialization.");
+            schedule::report(Outcome::Terminated(outcome));
+            return;
+        }
+    };
+
+    let mut dbg = Debugger {
+        prog,
+        span_table,
+        fn_names: FnNames::new(prog),
+        machine,
+        breakpoints: Vec::new(),
+    };
+
+    println!("Type `help` for a list of commands.");
+    dbg.print_position();
+
+    let echo = commands.is_some();
+    let mut input: Box<dyn BufRead> = match &commands {
+        Some(path) => {
+            let file = std::fs::File::open(path).unwrap_or_else(|e| panic!("could not read `{path}`: {e}"));
+            Box::new(std::io::BufReader::new(file))
+        }
+        None => Box::new(std::io::stdin().lock()),
+    };
+    loop {
+        print!("(mdb) ");
+        std::io::stdout().flush().unwrap();
+
+        let mut line = String::new();
+        if input.read_line(&mut line).unwrap() == 0 {
+            break; // EOF
+        }
+        if echo {
+            println!("{}", line.trim_end());
+        }
+
+        let words: Vec<&str> = line.split_whitespace().collect();
+        let Some(&cmd) = words.first() else { continue };
+        let arg = words.get(1).copied();
+
+        let running = match cmd {
+            "s" | "step" => {
+                let n = arg.and_then(|x| x.parse().ok()).unwrap_or(1);
+                dbg.step(n)
+            }
+            "c" | "continue" => dbg.cont(),
+            "b" | "break" => {
+                match arg.and_then(Breakpoint::parse) {
+                    Some(bp) => {
+                        println!("breakpoint {} at {bp}", dbg.breakpoints.len());
+                        dbg.breakpoints.push(bp);
+                    }
+                    None => println!("expected `<line>`, `<file>:<line>` or `f<n>:bb<n>`"),
+                }
+                true
+            }
+            "d" | "delete" => {
+                match arg.and_then(|x| x.parse::<usize>().ok()) {
+                    Some(i) if i < dbg.breakpoints.len() => {
+                        dbg.breakpoints.remove(i);
+                    }
+                    _ => println!("no such breakpoint"),
+                }
+                true
+            }
+            "breakpoints" => {
+                for (i, bp) in dbg.breakpoints.iter().enumerate() {
+                    println!("{i}: {bp}");
+                }
+                true
+            }
+            "w" | "where" => {
+                dbg.print_backtrace();
+                true
+            }
+            "l" | "locals" => {
+                dbg.print_locals();
+                true
+            }
+            "m" | "memory" => {
+                println!("{:#?}", dbg.machine.mem());
+                true
+            }
+            "g" | "globals" => {
+                dbg.print_globals(arg);
+                true
+            }
+            "h" | "help" => {
+                println!("{HELP}");
+                true
+            }
+            "q" | "quit" => break,
+            _ => {
+                println!("unknown command `{cmd}`, type `help` for a list of commands.");
+                true
+            }
+        };
+
+        if !running {
+            break;
+        }
+    }
+}
+
+impl Debugger {
+    /// executes `n` steps, returns `false` if the program terminated.
+    fn step(&mut self, n: usize) -> bool {
+        for _ in 0..n {
+            if !self.step_once() {
+                return false;
+            }
+        }
+        self.print_position();
+        true
+    }
+
+    /// executes steps until a breakpoint is hit, returns `false` if the program terminated.
+    fn cont(&mut self) -> bool {
+        loop {
+            let old_loc = self.position().and_then(|p| self.span_table.get(&p).cloned());
+            if !self.step_once() {
+                return false;
+            }
+
+            let Some(pos) = self.position() else { continue };
+            let loc = self.span_table.get(&pos);
+
+            // a line breakpoint is only hit when entering that line.
+            let new_line = loc.is_some() && loc != old_loc.as_ref();
+            let hit = self.breakpoints.iter().position(|bp| match bp {
+                Breakpoint::Line { .. } => new_line && bp.hit(pos, loc),
+                Breakpoint::Block(..) => bp.hit(pos, loc),
+            });
+
+            if let Some(i) = hit {
+                println!("hit breakpoint {i}: {}", self.breakpoints[i]);
+                self.print_position();
+                return true;
+            }
+        }
+    }
+
+    fn step_once(&mut self) -> bool {
+        match exec::step(&mut self.machine) {
+            Ok(()) => true,
+            Err(outcome) => {
+                println!("program terminated.");
+                schedule::report(Outcome::Terminated(outcome));
+                false
+            }
+        }
+    }
+
+    /// the statement which will be executed next.
+    fn position(&self) -> Option<StmtIdx> {
+        self.fn_names.position(&self.machine)
+    }
+
+    fn print_position(&self) {
+        let Some(pos) = self.position() else {
+            println!("no active function.");
+            return;
+        };
+        println!("at {}", fmt_pos(pos, &self.span_table));
+        println!("  {}", exec::fmt_stmt(pos, self.prog));
+    }
+
+    fn print_backtrace(&self) {
+        let positions = self.fn_names.stack_positions(&self.machine);
+        for (depth, pos) in positions.into_iter().rev().enumerate() {
+            println!("#{depth} {}", fmt_pos(pos, &self.span_table));
+        }
+    }
+
+    fn print_locals(&self) {
+        let Some(frame) = self.machine.stack().last() else {
+            println!("no active function.");
+            return;
+        };
+        let mut locals: Vec<LocalName> = frame.locals().iter().map(|(local, _)| local).collect();
+        locals.sort_by_key(|local| local.0.get_internal());
+        for local in locals {
+            let value = match self.machine.local_value(local) {
+                Some(value) => fmt_value(value),
+                None => String::from("<uninit>"),
+            };
+            println!("_{} = {value}", local.0.get_internal());
+        }
+    }
+
+    fn print_globals(&self, arg: Option<&str>) {
+        let filter = arg.and_then(|x| x.strip_prefix('g').unwrap_or(x).parse::<u32>().ok());
+        let mut globals: Vec<_> = self.prog.globals.iter().collect();
+        globals.sort_by_key(|(name, _)| name.0.get_internal());
+        for (name, global) in globals {
+            let idx = name.0.get_internal();
+            if filter.map_or(true, |f| f == idx) {
+                let bytes: Vec<String> = global
+                    .bytes
+                    .iter()
+                    .map(|b| b.map_or(String::from("__"), |b| format!("{b:02x}")))
+                    .collect();
+                println!("g{idx}: [{}] align {}", bytes.join(" "), global.align.bytes());
+                for (offset, relocation) in global.relocations.iter() {
+                    println!("  at {}: {relocation:?}", offset.bytes());
+                }
+            }
+        }
+    }
+}
+
+// integers and booleans are printed as in Rust, everything else with its debug output.
+fn fmt_value(value: Value<BasicMemory>) -> String {
+    match value {
+        Value::Int(i) => i.to_string(),
+        Value::Bool(b) => b.to_string(),
+        _ => format!("{value:?}"),
+    }
+}
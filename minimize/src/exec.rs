@@ -0,0 +1,233 @@
+// This module executes MiniRust programs step by step,
+// which allows us to observe the machine in between the steps.
+// The machine state is read using the accessors from `minirust-ext`, which `clone-minirust.sh` adds to the spec.
+
+use crate::*;
+use std::time::{Duration, Instant};
+
+/// Observes the execution of a program, see `run`.
+pub trait Observer {
+    /// called before each step of the machine.
+    fn before_step(&mut self, machine: &Machine<BasicMemory>, prog: Program);
+}
+
+impl Observer for () {
+    fn before_step(&mut self, _machine: &Machine<BasicMemory>, _prog: Program) {}
+}
+
+impl<O: Observer> Observer for Option<O> {
+    fn before_step(&mut self, machine: &Machine<BasicMemory>, prog: Program) {
+        if let Some(o) = self {
+            o.before_step(machine, prog);
+        }
+    }
+}
+
+impl<A: Observer, B: Observer> Observer for (A, B) {
+    fn before_step(&mut self, machine: &Machine<BasicMemory>, prog: Program) {
+        self.0.before_step(machine, prog);
+        self.1.before_step(machine, prog);
+    }
+}
+
+/// How the execution of a program ended.
+pub enum Outcome {
+    /// The MiniRust machine terminated.
+    Terminated(TerminationInfo),
+    /// The program exceeded one of its `Limits`, the string explains which one.
+    ResourceExhausted(String),
+}
+
+/// Limits the resources a program may use, so that non-terminating
+/// or allocation-heavy programs cannot hang `minimize`.
+/// All limits are off by default.
+#[derive(Copy, Clone)]
+pub struct Limits {
+    /// The maximal number of executed statements and terminators.
+    pub max_steps: Option<u64>,
+    /// The maximal number of stack frames of a thread.
+    pub max_stack_depth: Option<usize>,
+    /// The maximal number of bytes allocated at the same time.
+    /// This is only checked every `CHECK_INTERVAL` steps.
+    pub max_memory: Option<u64>,
+    /// The maximal wall time of a single run.
+    /// This is only checked every `CHECK_INTERVAL` steps.
+    pub timeout: Option<Duration>,
+}
+
+// memory and wall time are comparatively expensive to check, so we only do it every few steps.
+const CHECK_INTERVAL: u64 = 1024;
+
+impl Limits {
+    /// Reads the limits from the command-line flags
+    /// `--max-steps N`, `--max-stack-depth N`, `--max-memory BYTES` and `--timeout SECONDS`.
+    /// Limits without a flag, or with `0`, are off. If a flag is given multiple times, the last one counts.
+    ///
+    /// `--max-memory` and `--timeout` are only checked every 1024 steps (see `CHECK_INTERVAL`),
+    /// so a run may exceed them by up to 1024 steps before it is stopped.
+    pub fn from_args(args: &[String]) -> Limits {
+        let limit = |flag| match flag_value(args, flag) {
+            None | Some(0) => None,
+            x => x,
+        };
+
+        Limits {
+            max_steps: limit("--max-steps"),
+            max_stack_depth: limit("--max-stack-depth").map(|x| x as usize),
+            max_memory: limit("--max-memory"),
+            timeout: limit("--timeout").map(Duration::from_secs),
+        }
+    }
+
+    // returns `Some(_)` explaining which limit was exceeded, if any.
+    fn check(&self, machine: &Machine<BasicMemory>, steps: u64, start: Instant) -> Option<String> {
+        if let Some(max) = self.max_steps {
+            if steps >= max {
+                return Some(format!("step limit of {max} reached"));
+            }
+        }
+
+        if let Some(max) = self.max_stack_depth {
+            if machine.stack().len() > Int::from(max) {
+                return Some(format!("stack depth limit of {max} reached"));
+            }
+        }
+
+        if steps % CHECK_INTERVAL != 0 {
+            return None;
+        }
+
+        if let Some(max) = self.max_memory {
+            let allocated = allocated_bytes(machine);
+            if allocated > max {
+                return Some(format!("memory limit of {max} bytes reached ({allocated} bytes allocated)"));
+            }
+        }
+
+        if let Some(max) = self.timeout {
+            if start.elapsed() > max {
+                return Some(format!("time limit of {} seconds reached", max.as_secs()));
+            }
+        }
+
+        None
+    }
+}
+
+/// Runs the program until it terminates or exceeds its limits.
+pub fn run(prog: Program, limits: Limits, observer: &mut impl Observer) -> Outcome {
+    let mut machine = match Machine::<BasicMemory>::new(prog).get_internal() {
+        Ok(machine) => machine,
+        Err(outcome) => return Outcome::Terminated(outcome),
+    };
+
+    let start = Instant::now();
+    let mut steps: u64 = 0;
+    loop {
+        if let Some(msg) = limits.check(&machine, steps, start) {
+            return Outcome::ResourceExhausted(msg);
+        }
+
+        observer.before_step(&machine, prog);
+        if let Err(outcome) = step(&mut machine) {
+            return Outcome::Terminated(outcome);
+        }
+        steps += 1;
+    }
+}
+
+// the number of bytes in live allocations.
+fn allocated_bytes(machine: &Machine<BasicMemory>) -> u64 {
+    machine
+        .mem()
+        .allocations()
+        .iter()
+        .filter(|a| a.live())
+        .map(|a| a.size().bytes().try_to_usize().unwrap() as u64)
+        .sum()
+}
+
+/// Executes a single statement or terminator.
+pub fn step(machine: &mut Machine<BasicMemory>) -> Result<(), TerminationInfo> {
+    machine.step().get_internal()?;
+
+    // drop everything that is no longer reachable from the machine.
+    mark_and_sweep(machine);
+
+    Ok(())
+}
+
+/// Finds the names of the functions on the stack.
+/// StackFrames only know their `Function`, and structurally identical functions are equal,
+/// so the name of a frame is taken from the call in its caller whenever possible.
+pub struct FnNames {
+    prog: Program,
+    // used for frames whose caller is unknown, like the first frame of a thread.
+    by_fn: HashMap<Function, FnName>,
+}
+
+impl FnNames {
+    pub fn new(prog: Program) -> FnNames {
+        let by_fn = prog.functions.iter().map(|(name, f)| (f, name)).collect();
+
+        FnNames { prog, by_fn }
+    }
+
+    /// The statements which will be executed next by the frames of the active thread.
+    /// The outermost frame comes first.
+    pub fn stack_positions(&self, machine: &Machine<BasicMemory>) -> Vec<StmtIdx> {
+        let mut positions: Vec<StmtIdx> = Vec::new();
+        for frame in machine.stack().iter() {
+            let called = positions.last().and_then(|&caller| self.callee(caller));
+            let Some(fn_name) = called.or_else(|| self.by_fn.get(&frame.func()).copied()) else { continue };
+            let stmt = frame.next_stmt().try_to_usize().unwrap();
+            positions.push((fn_name, frame.next_block(), stmt));
+        }
+
+        positions
+    }
+
+    /// The statement which will be executed next.
+    pub fn position(&self, machine: &Machine<BasicMemory>) -> Option<StmtIdx> {
+        self.stack_positions(machine).pop()
+    }
+
+    // while a function is called, its caller stays at the `Call` terminator.
+    // Returns the called function, if it is known without evaluating the callee.
+    fn callee(&self, caller: StmtIdx) -> Option<FnName> {
+        let (fn_name, bb_name, _) = caller;
+        let bb = self.prog.functions.index_at(fn_name).blocks.index_at(bb_name);
+        match bb.terminator {
+            Terminator::Call { callee: ValueExpr::Constant(Constant::FnPointer(name), _), .. } => Some(name),
+            _ => None,
+        }
+    }
+}
+
+/// Returns the debug output of the statement or terminator at `pos`.
+pub fn fmt_stmt(pos: StmtIdx, prog: Program) -> String {
+    let (fn_name, bb_name, idx) = pos;
+    let bb = prog.functions.index_at(fn_name).blocks.index_at(bb_name);
+    match bb.statements.get(Int::from(idx)) {
+        Some(stmt) => format!("{stmt:?}"),
+        None => format!("{:?}", bb.terminator),
+    }
+}
+
+/// Formats `pos` like `f1:bb2[3] (file.rs:12)`.
+pub fn fmt_pos(pos: StmtIdx, span_table: &SpanTable) -> String {
+    let (fn_name, bb_name, idx) = pos;
+    let block = fmt_block(fn_name, bb_name);
+    match span_table.get(&pos) {
+        Some(loc) => format!("{block}[{idx}] ({}:{})", loc.file, loc.line),
+        None => format!("{block}[{idx}]"),
+    }
+}
+
+pub fn fmt_fn(fn_name: FnName) -> String {
+    format!("f{}", fn_name.0.get_internal())
+}
+
+pub fn fmt_block(fn_name: FnName, bb_name: BbName) -> String {
+    format!("{}:bb{}", fmt_fn(fn_name), bb_name.0.get_internal())
+}
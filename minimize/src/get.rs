@@ -4,7 +4,7 @@ use crate::*;
 use rustc_driver::{Callbacks, Compilation, RunCompiler};
 use rustc_interface::{interface::Compiler, Queries};
 
-pub fn get_mini(file: String, callback: impl FnOnce(Program) + Send + Copy) {
+pub fn get_mini(file: String, callback: impl FnOnce(Program, SpanTable) + Send + Copy) {
     if !Path::new(&file).exists() {
         eprintln!("You need to define some `file.rs` in order to run `minimize`.");
         std::process::exit(1);
@@ -34,19 +34,19 @@ pub fn get_mini(file: String, callback: impl FnOnce(Program) + Send + Copy) {
     RunCompiler::new(&args, &mut Cb { callback }).run().unwrap();
 }
 
-struct Cb<F: FnOnce(Program) + Send + Copy> {
+struct Cb<F: FnOnce(Program, SpanTable) + Send + Copy> {
     callback: F,
 }
 
-impl<F: FnOnce(Program) + Send + Copy> Callbacks for Cb<F> {
+impl<F: FnOnce(Program, SpanTable) + Send + Copy> Callbacks for Cb<F> {
     fn after_analysis<'tcx>(
         &mut self,
         _compiler: &Compiler,
         queries: &'tcx Queries<'tcx>,
     ) -> Compilation {
         queries.global_ctxt().unwrap().enter(|arg| {
-            let prog = Ctxt::new(arg).translate();
-            (self.callback)(prog);
+            let (prog, span_table) = Ctxt::new(arg).translate();
+            (self.callback)(prog, span_table);
         });
 
         Compilation::Stop
@@ -9,6 +9,7 @@ extern crate rustc_hir;
 extern crate rustc_interface;
 extern crate rustc_middle;
 extern crate rustc_mir_dataflow;
+extern crate rustc_span;
 extern crate rustc_target;
 
 mod rs {
@@ -16,7 +17,9 @@ mod rs {
     pub use rustc_middle::mir::UnevaluatedConst;
     pub use rustc_middle::mir::{interpret::*, *};
     pub use rustc_middle::ty::*;
+    pub use rustc_middle::ty::adjustment::PointerCast;
     pub use rustc_mir_dataflow::storage::always_storage_live_locals;
+    pub use rustc_span::Span;
     pub use rustc_target::abi::{call::*, Align, Size};
 }
 
@@ -60,27 +63,94 @@ use get::get_mini;
 mod chunks;
 use chunks::calc_chunks;
 
+mod schedule;
+use schedule::Schedule;
+
+mod debug;
+
+mod exec;
+use exec::{FnNames, Limits, Observer, Outcome};
+
+mod trace;
+use trace::Tracer;
+
+mod profile;
+use profile::Profiler;
+
 use std::collections::HashMap;
 use std::path::Path;
 
+/// flags which are followed by a value, like `--seed 42`.
+const VALUE_FLAGS: &[&str] = &[
+    "--seed",
+    "--runs",
+    "--exhaustive",
+    "--profile-folded",
+    "--max-steps",
+    "--max-stack-depth",
+    "--max-memory",
+    "--timeout",
+    "--commands",
+];
+
 fn main() {
-    let file = std::env::args()
-        .skip(1)
-        .filter(|x| !x.starts_with('-'))
+    let mut args: Vec<String> = std::env::args().skip(1).collect();
+
+    // `minimize debug <file>` starts the interactive debugger.
+    let debug = args.first().map_or(false, |x| x == "debug");
+    if debug {
+        args.remove(0);
+    }
+
+    // the file is the first argument which is neither a flag nor the value of a flag.
+    let file = args
+        .iter()
+        .enumerate()
+        .filter(|(i, x)| !x.starts_with('-') && (*i == 0 || !VALUE_FLAGS.contains(&&*args[i - 1])))
+        .map(|(_, x)| x.clone())
         .next()
         .unwrap_or_else(|| String::from("file.rs"));
 
-    get_mini(file, |prog| {
-        let dump = std::env::args().skip(1).any(|x| x == "--dump");
-        if dump {
+    let dump = args.iter().any(|x| x == "--dump");
+    let trace = args.iter().any(|x| x == "--trace");
+    let folded: Option<String> = flag_value(&args, "--profile-folded");
+    let profile = folded.is_some() || args.iter().any(|x| x == "--profile");
+    let schedule = Schedule::from_args(&args);
+    let limits = Limits::from_args(&args);
+
+    get_mini(file, |prog, span_table| {
+        if debug {
+            debug::debug(prog, span_table, flag_value(&args, "--commands"));
+        } else if dump {
             dump_program(prog);
         } else {
-            match run_program(prog) {
-                TerminationInfo::IllFormed => eprintln!("ERR: program not well-formed."),
-                TerminationInfo::MachineStop => { /* silent exit. */ }
-                TerminationInfo::Ub(err) => eprintln!("UB: {}", err.get_internal()),
-                _ => unreachable!(),
+            let tracer = trace.then(|| Tracer { span_table: &span_table, fn_names: FnNames::new(prog) });
+            let profiler = profile.then(|| Profiler::new(prog));
+            let mut observer = (tracer, profiler);
+
+            schedule.run(prog, limits, &mut observer);
+
+            if let Some(profiler) = observer.1 {
+                profiler.print_report();
+                if let Some(path) = &folded {
+                    profiler.write_folded(path);
+                }
             }
         }
     });
 }
+
+/// Returns the value of a flag like `--seed 42`.
+/// If the flag is given multiple times, the last one counts, so that defaults can be overridden.
+fn flag_value<T: std::str::FromStr>(args: &[String], flag: &str) -> Option<T> {
+    let i = args.iter().rposition(|x| x == flag)?;
+    let Some(val) = args.get(i + 1) else {
+        eprintln!("`{flag}` requires a value!");
+        std::process::exit(1);
+    };
+    let Ok(val) = val.parse() else {
+        eprintln!("`{flag}` has an invalid value `{val}`!");
+        std::process::exit(1);
+    };
+    Some(val)
+}
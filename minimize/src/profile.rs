@@ -0,0 +1,75 @@
+use crate::*;
+use exec::{fmt_block, fmt_fn, FnNames};
+
+/// Implements `--profile`: counts the executed statements and terminators.
+pub struct Profiler {
+    fn_names: FnNames,
+    total: u64,
+    per_fn: HashMap<FnName, u64>,
+    per_block: HashMap<(FnName, BbName), u64>,
+    // maps a call stack like `f0;f3;f5` to the number of steps executed in it.
+    per_stack: HashMap<String, u64>,
+}
+
+impl Observer for Profiler {
+    fn before_step(&mut self, machine: &Machine<BasicMemory>, _prog: Program) {
+        let positions = self.fn_names.stack_positions(machine);
+        let Some(&(fn_name, bb_name, _)) = positions.last() else { return };
+
+        self.total += 1;
+        *self.per_fn.entry(fn_name).or_default() += 1;
+        *self.per_block.entry((fn_name, bb_name)).or_default() += 1;
+
+        let stack: Vec<String> = positions.iter().map(|(f, _, _)| fmt_fn(*f)).collect();
+        *self.per_stack.entry(stack.join(";")).or_default() += 1;
+    }
+}
+
+impl Profiler {
+    pub fn new(prog: Program) -> Profiler {
+        Profiler {
+            fn_names: FnNames::new(prog),
+            total: 0,
+            per_fn: HashMap::new(),
+            per_block: HashMap::new(),
+            per_stack: HashMap::new(),
+        }
+    }
+
+    /// Prints the counts to stderr, sorted by the number of steps and then by name.
+    pub fn print_report(&self) {
+        eprintln!("== profile: {} steps ==", self.total);
+
+        eprintln!("per function:");
+        for (fn_name, count) in sorted(&self.per_fn, |f| f.0.get_internal()) {
+            eprintln!("{:>12} {:>6.2}%  {}", count, self.percentage(count), fmt_fn(fn_name));
+        }
+
+        eprintln!("per basic block:");
+        for ((fn_name, bb_name), count) in sorted(&self.per_block, |(f, bb)| (f.0.get_internal(), bb.0.get_internal())) {
+            eprintln!("{:>12} {:>6.2}%  {}", count, self.percentage(count), fmt_block(fn_name, bb_name));
+        }
+    }
+
+    /// Writes the call stacks in the "folded" format used by flamegraph tools,
+    /// i.e. one line `f0;f3;f5 <count>` per call stack.
+    pub fn write_folded(&self, path: &str) {
+        let mut out = String::new();
+        for (stack, count) in sorted(&self.per_stack, |stack| stack.clone()) {
+            out.push_str(&format!("{stack} {count}\n"));
+        }
+        std::fs::write(path, out).unwrap_or_else(|e| panic!("could not write `{path}`: {e}"));
+    }
+
+    fn percentage(&self, count: u64) -> f64 {
+        count as f64 * 100.0 / self.total as f64
+    }
+}
+
+// sorts the entries of a map by their count, the highest count comes first.
+// Entries with the same count are sorted by `name`, so that the report is deterministic.
+fn sorted<K: Clone, N: Ord>(map: &HashMap<K, u64>, name: impl Fn(&K) -> N) -> Vec<(K, u64)> {
+    let mut v: Vec<(K, u64)> = map.iter().map(|(k, c)| (k.clone(), *c)).collect();
+    v.sort_by_key(|(k, c)| (std::cmp::Reverse(*c), name(k)));
+    v
+}
@@ -13,6 +13,23 @@ pub struct Ctxt<'tcx> {
     pub globals: Map<GlobalName, Global>,
 
     pub functions: Map<FnName, Function>,
+
+    /// Stores the Rust source location of each translated statement and terminator.
+    pub span_table: SpanTable,
+}
+
+/// Identifies a statement of a basic block by its index.
+/// The terminator of a block with `n` statements has index `n`.
+pub type StmtIdx = (FnName, BbName, usize);
+
+/// Maps MiniRust statements and terminators to the Rust source code they originate from.
+pub type SpanTable = HashMap<StmtIdx, SourceLoc>;
+
+/// A line in a Rust source file.
+#[derive(Clone, PartialEq, Eq, Hash, Debug)]
+pub struct SourceLoc {
+    pub file: String,
+    pub line: usize,
 }
 
 impl<'tcx> Ctxt<'tcx> {
@@ -23,10 +40,11 @@ impl<'tcx> Ctxt<'tcx> {
             alloc_map: Default::default(),
             globals: Default::default(),
             functions: Default::default(),
+            span_table: Default::default(),
         }
     }
 
-    pub fn translate(mut self) -> Program {
+    pub fn translate(mut self) -> (Program, SpanTable) {
         let (entry, _ty) = self.tcx.entry_fn(()).unwrap();
         let substs_ref: rs::SubstsRef<'tcx> = self.tcx.intern_substs(&[]);
         let entry_name = FnName(Name::from_internal(0));
@@ -57,11 +75,13 @@ impl<'tcx> Ctxt<'tcx> {
         let start = FnName(Name::from_internal(number_of_fns as _));
         self.functions.insert(start, mk_start_fn(0));
 
-        Program {
+        let prog = Program {
             start,
             functions: self.functions,
             globals: self.globals,
-        }
+        };
+
+        (prog, self.span_table)
     }
 }
 
@@ -146,6 +166,17 @@ impl<'cx, 'tcx> FnCtxt<'cx, 'tcx> {
         }
     }
 
+    /// stores the source location of `span` in the span table.
+    pub fn record_span(&mut self, bb_name: BbName, stmt_idx: usize, span: rs::Span) {
+        let fn_name = self.cx.fn_name_map[&(self.def_id, self.substs_ref)];
+        let loc = self.cx.tcx.sess.source_map().lookup_char_pos(span.lo());
+        let loc = SourceLoc {
+            file: loc.file.name.prefer_local().to_string(),
+            line: loc.line,
+        };
+        self.cx.span_table.insert((fn_name, bb_name, stmt_idx), loc);
+    }
+
     pub fn translate(mut self) -> Function {
         // associate names for each mir BB.
         for bb_id in self.body.basic_blocks.indices() {
@@ -194,7 +225,7 @@ impl<'cx, 'tcx> FnCtxt<'cx, 'tcx> {
         for (id, bb_name) in self.bb_name_map.clone() {
             // TODO fix clone
             let bb_data = &self.body.basic_blocks[id].clone(); // TODO fix clone
-            let bb = translate_bb(bb_data, &mut self);
+            let bb = translate_bb(bb_name, bb_data, &mut self);
             self.blocks.insert(bb_name, bb);
         }
         self.blocks.insert(init_bb, init_blk);
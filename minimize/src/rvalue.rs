@@ -173,6 +173,15 @@ pub fn translate_rvalue<'cx, 'tcx>(
                 operand: GcCow::new(operand),
             }
         }
+        rs::Rvalue::Cast(rs::CastKind::Pointer(rs::PointerCast::ReifyFnPointer), operand, _) => {
+            // this is required to pass functions to `spawn`.
+            let rs::Operand::Constant(box c) = operand else { panic!() };
+            let rs::ConstantKind::Val(_, f_ty) = c.literal else { panic!() };
+            let rs::TyKind::FnDef(f, substs_ref) = f_ty.kind() else { panic!() };
+
+            let fn_name = fn_name_of((*f, *substs_ref), fcx);
+            build::fn_ptr(fn_name.0.get_internal())
+        }
         rs::Rvalue::Repeat(op, c) => {
             let c = c.try_eval_usize(fcx.cx.tcx, rs::ParamEnv::empty()).unwrap();
             let c = Int::from(c);
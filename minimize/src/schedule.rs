@@ -0,0 +1,161 @@
+use crate::*;
+
+/// How the non-determinism of a program is resolved when running it.
+///
+/// Every `pick` of the MiniRust machine (e.g. choosing addresses or threads) is a choice point.
+#[derive(Copy, Clone)]
+pub enum Schedule {
+    /// A single run with random choices. This is the default.
+    Random,
+    /// `runs` runs with reproducible choices, the `i`-th run uses the seed `seed + i`.
+    /// This is used for `--seed N` and `--runs K`.
+    Seeded { seed: u64, runs: u64 },
+    /// Explores every execution, where each choice point has at most `bound` alternatives.
+    /// This stops after `max_runs` executions, if given.
+    /// This is used for `--exhaustive BOUND`.
+    Exhaustive { bound: usize, max_runs: Option<u64> },
+}
+
+impl Schedule {
+    pub fn from_args(args: &[String]) -> Schedule {
+        let seed: Option<u64> = flag_value(args, "--seed");
+        let runs: Option<u64> = flag_value(args, "--runs");
+        let bound: Option<usize> = flag_value(args, "--exhaustive");
+
+        match (seed, runs, bound) {
+            (None, None, None) => Schedule::Random,
+            (_, runs, Some(bound)) => {
+                assert!(bound > 0, "`--exhaustive` requires a positive bound!");
+                Schedule::Exhaustive {
+                    bound,
+                    max_runs: runs,
+                }
+            }
+            (seed, runs, None) => Schedule::Seeded {
+                // without an explicit seed, we start at a random one.
+                seed: seed.unwrap_or_else(random_seed),
+                runs: runs.unwrap_or(1),
+            },
+        }
+    }
+
+    pub fn run(self, prog: Program, limits: Limits, observer: &mut impl Observer) {
+        match self {
+            Schedule::Random => {
+                set_nondet_mode(NondetMode::Random);
+                report(exec::run(prog, limits, observer));
+            }
+            Schedule::Seeded { seed, runs: 1 } => {
+                set_nondet_mode(NondetMode::Seeded(seed));
+                report(exec::run(prog, limits, observer));
+            }
+            Schedule::Seeded { seed, runs } => {
+                let mut summary = Summary::default();
+                for i in 0..runs {
+                    let seed = seed.wrapping_add(i);
+                    set_nondet_mode(NondetMode::Seeded(seed));
+                    let outcome = exec::run(prog, limits, observer);
+                    summary.add(outcome, format!("seed {seed}"));
+                }
+                summary.print();
+            }
+            Schedule::Exhaustive { bound, max_runs } => {
+                let mut summary = Summary::default();
+                let mut prefix = Some(Vec::new());
+                while let Some(p) = prefix {
+                    if matches!(max_runs, Some(m) if summary.runs >= m) {
+                        eprintln!("stopped after {} runs, the exploration is incomplete.", summary.runs);
+                        break;
+                    }
+
+                    set_nondet_mode(NondetMode::Exhaustive { prefix: p, bound });
+                    let outcome = exec::run(prog, limits, observer);
+                    let trace = take_choice_trace();
+
+                    let choices: Vec<usize> = trace.iter().map(|c| c.taken).collect();
+                    summary.add(outcome, format!("choices {choices:?}"));
+
+                    prefix = next_prefix(trace);
+                }
+                summary.print();
+            }
+        }
+    }
+}
+
+/// Prints the outcome of a single run, in the format expected by the ui tests.
+pub fn report(outcome: Outcome) {
+    if let Some(msg) = outcome_msg(outcome) {
+        eprintln!("{msg}");
+    }
+}
+
+/// Describes how a program terminated, `None` stands for a regular exit.
+fn outcome_msg(outcome: Outcome) -> Option<String> {
+    let info = match outcome {
+        Outcome::Terminated(info) => info,
+        Outcome::ResourceExhausted(msg) => return Some(format!("ERR: resource exhausted: {msg}.")),
+    };
+
+    match info {
+        TerminationInfo::IllFormed => Some(String::from("ERR: program not well-formed.")),
+        TerminationInfo::MachineStop => None,
+        TerminationInfo::Ub(err) => Some(format!("UB: {}", err.get_internal())),
+        TerminationInfo::Deadlock => Some(String::from("ERR: program dead-locked.")),
+        _ => unreachable!(),
+    }
+}
+
+/// Counts how often each outcome occurred over multiple runs.
+#[derive(Default)]
+struct Summary {
+    runs: u64,
+    // the outcome, how often it occurred, and an example of how to reproduce it.
+    outcomes: Vec<(Option<String>, u64, String)>,
+}
+
+impl Summary {
+    fn add(&mut self, outcome: Outcome, example: String) {
+        self.runs += 1;
+        let msg = outcome_msg(outcome);
+        match self.outcomes.iter_mut().find(|(m, _, _)| *m == msg) {
+            Some((_, count, _)) => *count += 1,
+            None => self.outcomes.push((msg, 1, example)),
+        }
+    }
+
+    fn print(mut self) {
+        self.outcomes.sort_by(|a, b| b.1.cmp(&a.1));
+
+        eprintln!("== {} runs ==", self.runs);
+        for (msg, count, example) in self.outcomes {
+            let msg = msg.unwrap_or_else(|| String::from("regular exit"));
+            eprintln!("{count}x {msg} (e.g. {example})");
+        }
+    }
+}
+
+/// Computes the prefix for the next execution in a depth-first exploration,
+/// or `None` if all executions have been explored.
+fn next_prefix(mut trace: Vec<Choice>) -> Option<Vec<usize>> {
+    // the last choice point with an unexplored alternative is incremented,
+    // all choice points after it will start with their first alternative again.
+    while let Some(c) = trace.pop() {
+        if c.taken + 1 < c.alternatives {
+            let mut prefix: Vec<usize> = trace.iter().map(|c| c.taken).collect();
+            prefix.push(c.taken + 1);
+            return Some(prefix);
+        }
+    }
+
+    None
+}
+
+fn random_seed() -> u64 {
+    use std::time::{SystemTime, UNIX_EPOCH};
+
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_nanos() as u64
+}
@@ -0,0 +1,20 @@
+use crate::*;
+use exec::{fmt_pos, fmt_stmt, FnNames};
+
+/// Implements `--trace`: logs every executed statement and terminator to stderr.
+pub struct Tracer<'a> {
+    pub span_table: &'a SpanTable,
+    pub fn_names: FnNames,
+}
+
+impl Observer for Tracer<'_> {
+    fn before_step(&mut self, machine: &Machine<BasicMemory>, prog: Program) {
+        let positions = self.fn_names.stack_positions(machine);
+        let Some(&pos) = positions.last() else { return };
+
+        // the depth is the number of frames below the current one.
+        let depth = positions.len() - 1;
+        let indent = "  ".repeat(depth);
+        eprintln!("[{depth}] {indent}{}: {}", fmt_pos(pos, self.span_table), fmt_stmt(pos, prog));
+    }
+}
@@ -74,6 +74,7 @@ pub fn translate_ty<'tcx>(ty: rs::Ty<'tcx>, tcx: rs::TyCtxt<'tcx>) -> Type {
             let pointee = layout_of(*ty, tcx);
             Type::Ptr(PtrType::Raw { pointee })
         }
+        rs::TyKind::FnPtr(_) => Type::Ptr(PtrType::FnPtr),
         rs::TyKind::Array(ty, c) => {
             let count = Int::from(c.eval_usize(tcx, rs::ParamEnv::empty()));
             let elem = GcCow::new(translate_ty(*ty, tcx));
@@ -3,7 +3,9 @@ use std::process::Command;
 
 fn cfg(path: &str) -> ui_test::Config {
     ui_test::Config {
-        args: Vec::new(),
+        // a non-terminating test fails instead of hanging the whole run.
+        // Tests can override these limits with `//@compile-flags`, as the last occurrence of a flag counts.
+        args: ["--max-steps", "10000000", "--timeout", "120"].map(String::from).to_vec(),
         trailing_args: Vec::new(),
         host: Some(String::new()), // not used, ui_test fails if it's not set.
         target: None,
@@ -31,4 +33,15 @@ fn main() {
 
     ui_test::run_tests(cfg("./tests/pass")).unwrap();
     ui_test::run_tests(cfg("./tests/ub")).unwrap();
+    ui_test::run_tests(cfg("./tests/limits")).unwrap();
+    ui_test::run_tests(cfg("./tests/trace")).unwrap();
+    ui_test::run_tests(cfg("./tests/profile")).unwrap();
+
+    // the arguments come before the file, so this runs `minimize debug <file>`.
+    // The debugger stops at the end of its commands, so it needs no limits.
+    let debug = ui_test::Config {
+        args: vec![String::from("debug")],
+        ..cfg("./tests/debug")
+    };
+    ui_test::run_tests(debug).unwrap();
 }
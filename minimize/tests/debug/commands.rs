@@ -0,0 +1,17 @@
+//@compile-flags: --commands tests/debug/commands.cmds
+// the numbering of functions and blocks, and the statements, depend on the translation.
+//@normalize-stdout-test: "f\d+:bb\d+\[\d+\]" -> "fN:bbN[N]"
+//@normalize-stdout-test: "(?m)^  [A-Z]\w*.*$" -> "  .."
+//@normalize-stdout-test: "\([^()]*commands\.rs:" -> "(commands.rs:"
+// only the local `x` and the global `G` are checked, the other ones are temporaries.
+//@normalize-stdout-test: "(?m)^_(0|[2-9]|\d\d+) = .*\n" -> ""
+//@normalize-stdout-test: "(?m)^g\d+: " -> "gN: "
+extern crate intrinsics;
+use intrinsics::*;
+
+static G: u32 = 7;
+
+fn main() {
+    let x: u32 = 5;
+    print(x + G);
+}
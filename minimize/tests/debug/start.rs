@@ -0,0 +1,9 @@
+// stdin is closed, so the debugger only prints the initial position and stops at the first prompt.
+//@normalize-stdout-test: "(?m)^(at f\d+:bb\d+\[\d+\]).*$" -> "$1"
+//@normalize-stdout-test: "(?m)^  (\w+) \{.*$" -> "  $1 { .. }"
+
+fn f() {}
+
+fn main() {
+    f();
+}
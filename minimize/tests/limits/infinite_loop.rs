@@ -0,0 +1,8 @@
+//@compile-flags: --max-steps 1000
+extern crate intrinsics;
+use intrinsics::*;
+
+fn main() {
+    print(1);
+    loop {}
+}
@@ -0,0 +1,12 @@
+//@compile-flags: --max-stack-depth 100
+#![allow(unconditional_recursion)]
+
+extern crate intrinsics;
+
+fn rec(x: u32) -> u32 {
+    rec(x + 1)
+}
+
+fn main() {
+    rec(0);
+}
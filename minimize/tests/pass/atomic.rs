@@ -0,0 +1,35 @@
+extern crate intrinsics;
+use intrinsics::*;
+
+static mut COUNTER: u32 = 0;
+
+fn thread(_data_ptr: *const ()) {
+    unsafe { increment(&mut COUNTER as *mut u32); }
+}
+
+// increments `*ptr` using a compare-exchange loop.
+unsafe fn increment(ptr: *mut u32) {
+    let mut old = atomic_load(ptr);
+    loop {
+        let prev = compare_exchange(ptr, old, old + 1);
+        if prev == old { break; }
+        old = prev;
+    }
+}
+
+fn main() {
+    let id1 = spawn(thread, &() as *const ());
+    let id2 = spawn(thread, &() as *const ());
+    unsafe { increment(&mut COUNTER as *mut u32); }
+
+    join(id1);
+    join(id2);
+
+    fence();
+    unsafe {
+        let ptr = &mut COUNTER as *mut u32;
+        print(atomic_load(ptr));
+        atomic_store(ptr, 7);
+        print(COUNTER);
+    }
+}
@@ -0,0 +1,16 @@
+extern crate intrinsics;
+use intrinsics::*;
+
+static mut X: u32 = 0;
+
+fn thread(data_ptr: *const ()) {
+    let data_ptr = data_ptr as *const u32;
+    unsafe { X = *data_ptr + 1; }
+}
+
+fn main() {
+    let x: u32 = 41;
+    let id = spawn(thread, &x as *const u32 as *const ());
+    join(id);
+    print(unsafe { X });
+}
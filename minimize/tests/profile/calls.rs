@@ -0,0 +1,10 @@
+//@compile-flags: --profile
+// `main` is `f0`, `f` is `f1` and the start function calling `main` is `f2`.
+// Each function begins with a block allocating its locals, which is the block after the last one of the MIR body.
+
+fn f() {}
+
+fn main() {
+    f();
+    f();
+}
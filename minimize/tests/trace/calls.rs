@@ -0,0 +1,11 @@
+//@compile-flags: --trace
+// only the kind of each statement and terminator is checked, their fields are printed with their debug output.
+//@normalize-stderr-test: "(?m)([\])]): ([A-Z]\w*).*$" -> "$1: $2"
+//@normalize-stderr-test: "\([^()]*calls\.rs:" -> "(calls.rs:"
+
+fn f() {}
+
+fn main() {
+    f();
+    f();
+}
@@ -0,0 +1,20 @@
+//@compile-flags: --exhaustive 2
+// every interleaving of the two threads is explored, so the race is found independently of the schedule.
+// Not every interleaving races, so the number of runs and of regular exits is not checked.
+//@normalize-stderr-test: "== \d+ runs ==" -> "== N runs =="
+//@normalize-stderr-test: "(?m)^\d+x regular exit .*\n" -> ""
+//@normalize-stderr-test: "(?m)^\d+x (UB: Data race) .*$" -> "$1"
+extern crate intrinsics;
+use intrinsics::*;
+
+static mut X: u32 = 0;
+
+fn thread(_data_ptr: *const ()) {
+    unsafe { X = 1; }
+}
+
+fn main() {
+    let id = spawn(thread, &() as *const ());
+    unsafe { X = 2; }
+    join(id);
+}
@@ -0,0 +1,37 @@
+//! The `specr!` macro, which transpiles specr lang code inline.
+
+use proc_macro::TokenStream;
+use quote::ToTokens;
+use specr_transpile::prelude::Module;
+
+/// Applies the passes of specr-transpile to the given items, as if they were a module of a generated crate.
+///
+/// The generated code refers to `libspecr`, so the crate using this macro needs to depend on it
+/// and import it with `#[macro_use] extern crate libspecr;`, like generated crates do.
+/// Further, the items need `libspecr::prelude::*` in scope.
+///
+/// Errors are reported at the spans of the given code.
+///
+/// ```ignore
+/// #[macro_use] extern crate libspecr;
+/// use libspecr::prelude::*;
+///
+/// specr_macro::specr! {
+///     pub enum List {
+///         Cons(u32, #[specr::indirection] List),
+///         Nil,
+///     }
+/// }
+/// ```
+#[proc_macro]
+pub fn specr(input: TokenStream) -> TokenStream {
+    let ast = syn::parse_macro_input!(input as syn::File);
+
+    match specr_transpile::transpile(vec![Module::new("specr", ast)]) {
+        Ok(krate) => {
+            let m = krate.modules.into_iter().next().unwrap();
+            m.ast.into_token_stream().into()
+        },
+        Err(diag) => diag.into_syn_error().unwrap().into_compile_error().into(),
+    }
+}
@@ -0,0 +1,28 @@
+//! Tests that the code generated by `specr!` compiles, and behaves like the specr code.
+
+#[macro_use] extern crate libspecr;
+use libspecr::prelude::*;
+
+specr_macro::specr! {
+    pub enum List {
+        Cons(Int, #[specr::indirection] List),
+        Nil,
+    }
+
+    pub fn list(x: Int, y: Int) -> List {
+        List::Cons(x, List::Cons(y, List::Nil))
+    }
+
+    pub fn sum(l: List) -> Int {
+        match l {
+            List::Cons(x, rest) => x + sum(rest),
+            List::Nil => Int::ZERO,
+        }
+    }
+}
+
+#[test]
+fn indirection() {
+    let l = list(Int::from(1), Int::from(2));
+    assert_eq!(sum(l), Int::from(3));
+}
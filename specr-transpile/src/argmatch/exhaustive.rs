@@ -0,0 +1,221 @@
+use crate::prelude::*;
+use crate::resolve::{Resolver, Def, ItemPath};
+use crate::source_map::SourceMap;
+use super::{Arm, AttrInfo};
+
+/// Warns about argmatches which are not exhaustive, and about unreachable submatches.
+///
+/// rustc checks the generated match as well, but its diagnostics point into the generated code.
+/// The checks here are approximations, which never warn about correct argmatches:
+/// - Exhaustiveness is only checked for arguments of enums defined in the specr code.
+///   A submatch is considered to cover a whole variant, even if its pattern only covers some of its fields.
+/// - A submatch is unreachable, if an earlier submatch without guard has the same or an irrefutable pattern for each argument.
+pub(super) struct Ctx<'a> {
+    pub resolver: &'a Resolver,
+    pub module: &'a str,
+    pub source_map: &'a SourceMap,
+}
+
+// the variants of an argument covered by a pattern.
+enum Cover {
+    All,
+    Variants(Vec<String>),
+}
+
+// we don't list more missing cases than this.
+const MAX_MISSING: usize = 3;
+
+// we don't check argmatches with more cases than this.
+const MAX_CASES: usize = 100_000;
+
+impl Ctx<'_> {
+    /// `ii` is the impl block of the argmatch, or `None` for free functions.
+    pub(super) fn check(&self, ii: Option<&ItemImpl>, sig: &Signature, attr_info: &AttrInfo, arms: &[Arm], diag: &mut Diagnostics) {
+        let self_ty = ii.and_then(|ii| self.resolver.resolve_type(self.module, &ii.self_ty));
+
+        self.check_unreachable(self_ty.as_ref(), arms, diag);
+        self.check_exhaustive(self_ty.as_ref(), sig, attr_info, arms, diag);
+    }
+
+    fn check_unreachable(&self, self_ty: Option<&Def>, arms: &[Arm], diag: &mut Diagnostics) {
+        for (i, arm) in arms.iter().enumerate() {
+            let covering = arms[..i].iter().find(|earlier| {
+                earlier.guard.is_none() && earlier.pats.iter().zip(&arm.pats).all(|(p1, p2)| {
+                    self.irrefutable(self_ty, p1) || p1.to_token_stream().to_string() == p2.to_token_stream().to_string()
+                })
+            });
+            let Some(covering) = covering else { continue };
+
+            let msg = match self.source_map.lookup_span(covering.span) {
+                Some(loc) => format!("unreachable submatch, it is already covered by the submatch at {loc}"),
+                None => "unreachable submatch, it is already covered by an earlier submatch".to_string(),
+            };
+            diag.warning(self.source_map, arm.span, msg);
+        }
+    }
+
+    fn check_exhaustive(&self, self_ty: Option<&Def>, sig: &Signature, attr_info: &AttrInfo, arms: &[Arm], diag: &mut Diagnostics) {
+        // the enum of each argument we match upon, if it is an enum of the specr code.
+        let enums: Vec<Option<ItemPath>> = attr_info.args.iter().map(|(idx, _)| {
+            let ty = match &sig.inputs[*idx] {
+                FnArg::Receiver(r) => &*r.ty,
+                FnArg::Typed(pt) => &*pt.ty,
+            };
+            self.enum_of(self_ty, ty)
+        }).collect();
+
+        if enums.iter().all(Option::is_none) { return; }
+
+        let variants: Vec<&[String]> = enums.iter().map(|e| match e {
+            Some(e) => self.resolver.variants(e),
+            // arguments of other types are represented by a single case `_`.
+            None => &[],
+        }).collect();
+
+        let n_cases = variants.iter().map(|v| v.len().max(1)).try_fold(1usize, |a, b| a.checked_mul(b));
+        if !n_cases.map_or(false, |n| n <= MAX_CASES) { return; }
+
+        // the covered variants of each unguarded arm.
+        let covers: Vec<Vec<Cover>> = arms.iter()
+                                          .filter(|arm| arm.guard.is_none())
+                                          .map(|arm| arm.pats.iter().zip(&enums).map(|(p, e)| self.cover(self_ty, p, e.as_ref())).collect())
+                                          .collect();
+
+        let mut missing = Vec::new();
+        let mut n_missing = 0;
+        for case in cases(&variants) {
+            let covered = covers.iter().any(|cover| {
+                cover.iter().zip(&case).all(|(c, v)| match (c, v) {
+                    (Cover::All, _) | (_, None) => true,
+                    (Cover::Variants(vs), Some(v)) => vs.contains(v),
+                })
+            });
+            if covered { continue; }
+
+            n_missing += 1;
+            if missing.len() < MAX_MISSING {
+                let case: Vec<String> = case.iter().zip(&enums).map(|(v, e)| match (v, e) {
+                    (Some(v), Some(e)) => format!("{}::{v}", e.name),
+                    _ => "_".to_string(),
+                }).collect();
+                let case = match &case[..] {
+                    [x] => x.clone(),
+                    _ => format!("({})", case.join(", ")),
+                };
+                missing.push(format!("`{case}`"));
+            }
+        }
+
+        if n_missing == 0 { return; }
+
+        let mut msg = format!("argmatch `{}` is not exhaustive, {} not covered", sig.ident, missing.join(", "));
+        if n_missing > missing.len() {
+            msg.push_str(&format!(" and {} more", n_missing - missing.len()));
+        }
+        diag.warning(self.source_map, sig.ident.span(), msg);
+    }
+
+    // the enum `ty` refers to, if it is defined in the specr code.
+    fn enum_of(&self, self_ty: Option<&Def>, ty: &Type) -> Option<ItemPath> {
+        let def = match ty {
+            Type::Reference(r) => return self.enum_of(self_ty, &r.elem),
+            Type::Paren(p) => return self.enum_of(self_ty, &p.elem),
+            Type::Path(p) if p.qself.is_none() && p.path.is_ident("Self") => self_ty.cloned(),
+            _ => self.resolver.resolve_type(self.module, ty),
+        };
+        let Some(Def::Enum(e)) = def else { return None };
+
+        Some(e)
+    }
+
+    // the variant of the enum `e` which `path` refers to.
+    fn variant(&self, self_ty: Option<&Def>, path: &Path, e: &ItemPath) -> Option<String> {
+        match self.resolver.resolve(self.module, self_ty, path) {
+            Ok(Def::Variant(p, v)) if &p == e => Some(v),
+            Ok(_) => None,
+            // the variant might be imported from outside of the specr code, like through `Self`.
+            Err(_) => {
+                let last = path.segments.last()?.ident.to_string();
+                self.resolver.variants(e).iter().find(|v| **v == last).cloned()
+            },
+        }
+    }
+
+    // the variants of `e` which `pat` covers.
+    // Variants are considered covered, even if `pat` only covers some of their fields.
+    fn cover(&self, self_ty: Option<&Def>, pat: &Pat, e: Option<&ItemPath>) -> Cover {
+        let Some(e) = e else { return Cover::All };
+
+        let path = match pat {
+            Pat::Ident(pi) => match &pi.subpat {
+                Some((_, p)) => return self.cover(self_ty, p, Some(e)),
+                // an ident pattern can also refer to a unit variant, if it is imported.
+                None => match self.resolver.resolve(self.module, self_ty, &pi.ident.clone().into()) {
+                    Ok(Def::Variant(p, v)) if &p == e => return Cover::Variants(vec![v]),
+                    _ => return Cover::All,
+                },
+            },
+            Pat::Path(p) => &p.path,
+            Pat::TupleStruct(p) => &p.path,
+            Pat::Struct(p) => &p.path,
+            Pat::Paren(p) => return self.cover(self_ty, &p.pat, Some(e)),
+            Pat::Reference(r) => return self.cover(self_ty, &r.pat, Some(e)),
+            Pat::Or(o) => {
+                let mut variants = Vec::new();
+                for p in &o.cases {
+                    match self.cover(self_ty, p, Some(e)) {
+                        Cover::All => return Cover::All,
+                        Cover::Variants(v) => variants.extend(v),
+                    }
+                }
+                return Cover::Variants(variants);
+            },
+            _ => return Cover::All,
+        };
+
+        match self.variant(self_ty, path, e) {
+            Some(v) => Cover::Variants(vec![v]),
+            None => Cover::All,
+        }
+    }
+
+    // whether `pat` matches every value.
+    fn irrefutable(&self, self_ty: Option<&Def>, pat: &Pat) -> bool {
+        match pat {
+            Pat::Wild(_) | Pat::Rest(_) => true,
+            Pat::Ident(pi) => match &pi.subpat {
+                Some((_, p)) => self.irrefutable(self_ty, p),
+                // uppercase idents might be unit variants like `None`, even if we can't resolve them.
+                None => {
+                    let upper = pi.ident.to_string().starts_with(char::is_uppercase);
+                    !upper && self.resolver.resolve(self.module, self_ty, &pi.ident.clone().into()).is_err()
+                },
+            },
+            Pat::Tuple(t) => t.elems.iter().all(|p| self.irrefutable(self_ty, p)),
+            Pat::Paren(p) => self.irrefutable(self_ty, &p.pat),
+            Pat::Reference(r) => self.irrefutable(self_ty, &r.pat),
+            Pat::Type(t) => self.irrefutable(self_ty, &t.pat),
+            _ => false,
+        }
+    }
+}
+
+// all combinations of variants, `None` stands for any value of an argument without variants.
+fn cases<'a>(variants: &[&'a [String]]) -> Vec<Vec<Option<&'a String>>> {
+    let mut cases = vec![Vec::new()];
+    for vs in variants {
+        let choices: Vec<Option<&String>> = match vs.is_empty() {
+            true => vec![None],
+            false => vs.iter().map(Some).collect(),
+        };
+        cases = cases.into_iter()
+                     .flat_map(|case| choices.iter().map(move |c| {
+                         let mut case = case.clone();
+                         case.push(*c);
+                         case
+                     }))
+                     .collect();
+    }
+
+    cases
+}
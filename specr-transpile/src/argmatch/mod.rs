@@ -0,0 +1,609 @@
+use crate::prelude::*;
+use crate::resolve::{Resolver, Def, ItemPath};
+
+use syn::ext::IdentExt;
+use syn::parse::{ParseStream, Parser};
+
+mod exhaustive;
+mod relocate;
+
+/// Resolve `argmatches` from the source code, by converting them to a match.
+///
+/// How does `argmatch` work:
+/// There is a method with an argmatch attribute
+/// impl Option<()> {
+///     #[specr::argmatch(self)]
+///     fn foo(self);
+/// }
+///
+/// And then a sequence of submatches:
+/// impl Foo {
+///     fn foo(None: Self) { ... }
+/// }
+///
+/// impl Foo {
+///     fn foo(Some(()): Self) { ... }
+/// }
+///
+/// This will be glued together into:
+///
+/// impl Option<()> {
+///     fn foo(self) {
+///         match self {
+///             None => ...,
+///             Some(()) => ...,
+///         }
+///     }
+/// }
+///
+///
+/// Argmatches can also match on multiple arguments, like `#[specr::argmatch(a, b)]`, which results in a tuple match.
+/// Submatches can have a guard, like `#[specr::guard(x > 0)]`.
+///
+/// Argmatch also works for free functions. Their submatches are the functions of the same name in the same module,
+/// submatches in other modules refer to the argmatch by `#[specr::submatch(<path>)]`.
+/// Submatches of methods may be located in any module, as long as their impl block is the same.
+///
+/// See the README for more information.
+/// Malformed argmatches are reported to `diag`, as well as warnings for argmatches which are not exhaustive,
+/// or whose submatches are unreachable.
+pub fn argmatch(mut mods: Vec<Module>, diag: &mut Diagnostics) -> Vec<Module> {
+    for m in mods.iter_mut() {
+        add_bodies(&mut m.ast);
+    }
+
+    let resolver = Resolver::new(&mods);
+    check_submatch_attrs(&mods, &resolver, diag);
+
+    while let Some(argmatch) = locate_argmatch(&mods) {
+        let method_idx = argmatch.method_idx;
+
+        // remove the `argmatch` attribute.
+        method_idx.attrs_mut(&mut mods).remove(argmatch.attr_idx);
+
+        let attr_info = match argmatch.attr_info {
+            Ok(x) => x,
+            Err(e) => {
+                diag.syn_error(&mods[method_idx.mod_idx].source_map, e);
+                continue;
+            },
+        };
+
+        let submatches = locate_submatches(&method_idx, &attr_info, &mods, &resolver, diag);
+        let (arms, items) = construct_arms(&method_idx, &attr_info, &mods, &submatches, &resolver, diag);
+
+        // the submatches of other modules are moved into the module of the argmatch.
+        relocate::widen(&mut mods, &items);
+        for s in &submatches {
+            if s.mod_idx != method_idx.mod_idx {
+                let source_map = mods[s.mod_idx].source_map.clone();
+                mods[method_idx.mod_idx].source_map.extend(&source_map);
+            }
+        }
+
+        let m = &mods[method_idx.mod_idx];
+        let ctx = exhaustive::Ctx { resolver: &resolver, module: &m.name, source_map: &m.source_map };
+        ctx.check(method_idx.impl_block(&mods), method_idx.as_ref(&mods).sig, &attr_info, &arms, diag);
+
+        // set the newly-constructed block.
+        *method_idx.block_mut(&mut mods) = construct_block(&attr_info, &arms);
+
+        clear_submatches(&mut mods, submatches);
+    }
+
+    mods
+}
+
+// represents a `fn` item, either within an impl block or a free function.
+#[derive(PartialEq, Eq, PartialOrd, Ord, Clone, Copy)]
+struct FnIdx {
+    mod_idx: usize,
+    item_idx: usize,
+    // the index within the impl block, `None` for free functions.
+    fn_idx: Option<usize>,
+}
+
+// the parts of a method or free function which argmatch cares about.
+struct FnRef<'a> {
+    attrs: &'a [Attribute],
+    sig: &'a Signature,
+    block: &'a Block,
+}
+
+impl FnIdx {
+    fn as_ref<'a>(&self, mods: &'a [Module]) -> FnRef<'a> {
+        match (&mods[self.mod_idx].ast.items[self.item_idx], self.fn_idx) {
+            (Item::Fn(f), None) => FnRef { attrs: &f.attrs, sig: &f.sig, block: &f.block },
+            (Item::Impl(ii), Some(fn_idx)) => {
+                let ImplItem::Fn(ref iim) = ii.items[fn_idx] else { panic!() };
+                FnRef { attrs: &iim.attrs, sig: &iim.sig, block: &iim.block }
+            },
+            _ => panic!(),
+        }
+    }
+
+    fn attrs_mut<'a>(&self, mods: &'a mut [Module]) -> &'a mut Vec<Attribute> {
+        match (&mut mods[self.mod_idx].ast.items[self.item_idx], self.fn_idx) {
+            (Item::Fn(f), None) => &mut f.attrs,
+            (Item::Impl(ii), Some(fn_idx)) => {
+                let ImplItem::Fn(ref mut iim) = ii.items[fn_idx] else { panic!() };
+                &mut iim.attrs
+            },
+            _ => panic!(),
+        }
+    }
+
+    fn block_mut<'a>(&self, mods: &'a mut [Module]) -> &'a mut Block {
+        match (&mut mods[self.mod_idx].ast.items[self.item_idx], self.fn_idx) {
+            (Item::Fn(f), None) => &mut f.block,
+            (Item::Impl(ii), Some(fn_idx)) => {
+                let ImplItem::Fn(ref mut iim) = ii.items[fn_idx] else { panic!() };
+                &mut iim.block
+            },
+            _ => panic!(),
+        }
+    }
+
+    // the impl block containing the method, `None` for free functions.
+    fn impl_block<'a>(&self, mods: &'a [Module]) -> Option<&'a ItemImpl> {
+        self.fn_idx?;
+        let Item::Impl(ref ii) = mods[self.mod_idx].ast.items[self.item_idx] else { panic!() };
+        Some(ii)
+    }
+}
+
+// all methods and free functions of a module.
+fn fns(mod_idx: usize, ast: &syn::File) -> impl Iterator<Item=FnIdx> + '_ {
+    ast.items.iter().enumerate().flat_map(move |(item_idx, x)| {
+        let fn_idxs: Vec<Option<usize>> = match x {
+            Item::Fn(_) => vec![None],
+            Item::Impl(ii) => ii.items.iter()
+                                      .enumerate()
+                                      .filter(|(_, y)| matches!(y, ImplItem::Fn(_)))
+                                      .map(|(fn_idx, _)| Some(fn_idx))
+                                      .collect(),
+            _ => Vec::new(),
+        };
+        fn_idxs.into_iter().map(move |fn_idx| FnIdx { mod_idx, item_idx, fn_idx })
+    })
+}
+
+// expresses everything that can be contained in an `specr::argmatch` attribute.
+struct AttrInfo {
+    // the indices and idents of the function arguments we match upon.
+    // Typically, this is just `self` with index 0.
+    args: Vec<(usize, Ident)>,
+}
+
+// a submatch, converted to an arm of the match.
+struct Arm {
+    // one pattern for each argument we match upon.
+    pats: Vec<Pat>,
+    guard: Option<Expr>,
+    block: Block,
+
+    // the span of the submatch name.
+    span: Span,
+}
+
+struct Argmatch {
+    // the method or free function which has the `argmatch` attribute.
+    method_idx: FnIdx,
+
+    // which attribute is the argmatch attribute.
+    attr_idx: usize,
+
+    // the information in this attribute, or an error if it is malformed.
+    attr_info: syn::Result<AttrInfo>,
+}
+
+/// syn parses functions without a body, like `fn foo(self);`, as `Item::Verbatim` or `ImplItem::Verbatim`.
+/// This gives argmatch functions an empty body, which gets replaced by the match.
+pub fn add_bodies(ast: &mut syn::File) {
+    let parse_bodyless = |input: ParseStream| {
+        let attrs = input.call(Attribute::parse_outer)?;
+        let vis = input.parse()?;
+        let sig: Signature = input.parse()?;
+        input.parse::<Token![;]>()?;
+        Ok((attrs, vis, sig))
+    };
+
+    for x in ast.items.iter_mut() {
+        if let Item::Verbatim(tokens) = x {
+            let Ok((attrs, vis, sig)) = parse_bodyless.parse2(tokens.clone()) else { continue };
+            if get_attr_info(&attrs, &sig).is_some() {
+                *x = Item::Fn(ItemFn { attrs, vis, sig, block: parse_quote!({}) });
+            }
+            continue;
+        }
+
+        let Item::Impl(ii) = x else { continue };
+        for y in ii.items.iter_mut() {
+            let ImplItem::Verbatim(tokens) = y else { continue };
+            let Ok((attrs, vis, sig)) = parse_bodyless.parse2(tokens.clone()) else { continue };
+            if get_attr_info(&attrs, &sig).is_some() {
+                *y = ImplItem::Fn(ImplItemFn { attrs, vis, defaultness: None, sig, block: parse_quote!({}) });
+            }
+        }
+    }
+}
+
+// finds a method or free function with an #[specr::argmatch] attribute.
+fn locate_argmatch(mods: &[Module]) -> Option<Argmatch> {
+    for (mod_idx, m) in mods.iter().enumerate() {
+        for method_idx in fns(mod_idx, &m.ast) {
+            let f = method_idx.as_ref(mods);
+            let Some((attr_idx, attr_info)) = get_attr_info(f.attrs, f.sig) else { continue };
+            return Some(Argmatch { method_idx, attr_idx, attr_info });
+        }
+    }
+
+    None
+}
+
+// converts the submatches to match arms.
+// Submatches with a malformed guard are reported to `diag` and ignored.
+//
+// The arms of submatches in other modules than the argmatch are relocated, see `relocate::relocate`.
+// The private items they refer to are returned as well.
+fn construct_arms(argmatch_idx: &FnIdx, attr_info: &AttrInfo, mods: &[Module], submatches: &[FnIdx], resolver: &Resolver, diag: &mut Diagnostics) -> (Vec<Arm>, Vec<ItemPath>) {
+    let mut arms = Vec::new();
+    let mut items = Vec::new();
+
+    for x in submatches {
+        let f = x.as_ref(mods);
+        let guard = match get_guard(f.attrs) {
+            Some(Ok(guard)) => Some(guard),
+            Some(Err(e)) => {
+                diag.syn_error(&mods[x.mod_idx].source_map, e);
+                continue;
+            },
+            None => None,
+        };
+
+        // `is_submatch` made sure that these are no receivers.
+        let pats = attr_info.args.iter().map(|(idx, _)| {
+            let FnArg::Typed(pt) = &f.sig.inputs[*idx] else { unreachable!() };
+            (*pt.pat).clone()
+        }).collect();
+
+        let mut arm = Arm { pats, guard, block: f.block.clone(), span: f.sig.ident.span() };
+        if x.mod_idx != argmatch_idx.mod_idx {
+            items.extend(relocate::relocate(&mut arm, &mods[x.mod_idx].name, &mods[argmatch_idx.mod_idx].name, f.sig, mods, resolver));
+        }
+        arms.push(arm);
+    }
+
+    (arms, items)
+}
+
+fn construct_block(attr_info: &AttrInfo, arms: &[Arm]) -> Block {
+    // a single argument is matched directly, multiple arguments are matched as a tuple.
+    let tuple = |x: Vec<TokenStream>| match &x[..] {
+        [x] => x.clone(),
+        _ => quote! { (#(#x),*) },
+    };
+
+    let scrutinee = tuple(attr_info.args.iter().map(|(_, ident)| ident.to_token_stream()).collect());
+    let arms = arms.iter().map(|arm| {
+        let pat = tuple(arm.pats.iter().map(|p| p.to_token_stream()).collect());
+        let guard = arm.guard.as_ref().map(|g| quote! { if #g });
+        let block = &arm.block;
+        quote! { #pat #guard => #block, }
+    });
+
+    parse_quote! {{
+        match #scrutinee {
+            #(#arms)*
+        }
+    }}
+}
+
+// returns the submatches in the order of the modules, and in the order they are written down within each module.
+// Submatches with a mismatching signature are reported to `diag` and ignored.
+fn locate_submatches(argmatch_idx: &FnIdx, attr_info: &AttrInfo, mods: &[Module], resolver: &Resolver, diag: &mut Diagnostics) -> Vec<FnIdx> {
+    let mut submatches = Vec::new();
+
+    for (mod_idx, m) in mods.iter().enumerate() {
+        for method_idx in fns(mod_idx, &m.ast) {
+            match is_submatch(argmatch_idx, attr_info, &method_idx, mods, resolver) {
+                SubmatchResult::Yes => {
+                    submatches.push(method_idx);
+                },
+                SubmatchResult::No => {},
+                SubmatchResult::YesButMismatch { span, error_msg } => {
+                    diag.error(&m.source_map, span, error_msg);
+                },
+            }
+        }
+    }
+
+    submatches
+}
+
+enum SubmatchResult {
+    Yes,
+    No,
+
+    // It seems to be a submatch, but something is off.
+    // This generates an error.
+    YesButMismatch { span: Span, error_msg: String },
+}
+
+fn is_submatch(argmatch_idx: &FnIdx, attr_info: &AttrInfo, method_idx: &FnIdx, mods: &[Module], resolver: &Resolver) -> SubmatchResult {
+    if method_idx == argmatch_idx {
+        // this is no "submatch", it's the original method_idx itself!
+        return SubmatchResult::No;
+    }
+
+    let f1 = argmatch_idx.as_ref(mods);
+    let f2 = method_idx.as_ref(mods);
+
+    // check that the functions have the same name.
+    if f1.sig.ident != f2.sig.ident {
+        return SubmatchResult::No;
+    }
+
+    match (argmatch_idx.impl_block(mods), method_idx.impl_block(mods)) {
+        // check that the impl blocks are compatible (including type, optional trait, generics)
+        (Some(ii1), Some(ii2)) => {
+            let ii1 = impl_header(ii1, &mods[argmatch_idx.mod_idx].name, resolver);
+            let ii2 = impl_header(ii2, &mods[method_idx.mod_idx].name, resolver);
+            if ii1 != ii2 {
+                return SubmatchResult::No;
+            }
+        },
+        // free functions of other modules need to refer to the argmatch.
+        (None, None) => {
+            let refers = match get_submatch_path(f2.attrs) {
+                Some(Ok(path)) => resolver.resolve(&mods[method_idx.mod_idx].name, None, &path).ok() == Some(argmatch_def(argmatch_idx, mods)),
+                // `check_submatch_attrs` reports malformed attributes.
+                Some(Err(_)) => false,
+                None => method_idx.mod_idx == argmatch_idx.mod_idx,
+            };
+            if !refers {
+                return SubmatchResult::No;
+            }
+        },
+        _ => return SubmatchResult::No,
+    }
+
+    // check that signature are the same, except for the FnArgs we match upon.
+    let hide_match_idents = |sig: &Signature| {
+        let mut sig = sig.clone();
+        for (idx, _) in &attr_info.args {
+            sig.inputs[*idx] = parse_quote!(self);
+        }
+
+        sig
+    };
+    let mismatch = || {
+        let error_msg = format!("`argmatch` encountered signature mismatch: expected `{}`, found `{}`", f1.sig.to_token_stream(), f2.sig.to_token_stream());
+        SubmatchResult::YesButMismatch { span: f2.sig.ident.span(), error_msg }
+    };
+
+    if f1.sig.inputs.len() != f2.sig.inputs.len() {
+        return mismatch();
+    }
+
+    if hide_match_idents(f1.sig) != hide_match_idents(f2.sig) {
+        return mismatch();
+    }
+
+    // the arguments we match upon need to be patterns.
+    for (idx, _) in &attr_info.args {
+        if let FnArg::Receiver(r) = &f2.sig.inputs[*idx] {
+            let error_msg = format!("expected a pattern instead of `self`, like `fn {}(<pattern>: Self)`", f2.sig.ident);
+            return SubmatchResult::YesButMismatch { span: r.self_token.span, error_msg };
+        }
+    }
+
+    SubmatchResult::Yes
+
+}
+
+// the impl block without its items, where the paths of the specr code are replaced by their definition,
+// like `crate::lang::Foo` for `Foo`. This way, impl blocks of different modules can be compared.
+fn impl_header(ii: &ItemImpl, module: &str, resolver: &Resolver) -> ItemImpl {
+    struct Canonicalize<'a> {
+        module: &'a str,
+        resolver: &'a Resolver,
+    }
+
+    impl VisitMut for Canonicalize<'_> {
+        fn visit_path_mut(&mut self, path: &mut Path) {
+            visit_path_mut(self, path);
+
+            let Ok(def @ (Def::Enum(_) | Def::Item(_))) = self.resolver.resolve(self.module, None, path) else { return };
+            let args = path.segments.last().unwrap().arguments.clone();
+            *path = parse_str(&format!("crate::{def}")).unwrap();
+            path.segments.last_mut().unwrap().arguments = args;
+        }
+    }
+
+    let mut ii = ii.clone();
+    ii.items.clear();
+    Canonicalize { module, resolver }.visit_item_impl_mut(&mut ii);
+
+    ii
+}
+
+// the definition of a free argmatch function, which `#[specr::submatch(<path>)]` refers to.
+fn argmatch_def(argmatch_idx: &FnIdx, mods: &[Module]) -> Def {
+    let name = argmatch_idx.as_ref(mods).sig.ident.to_string();
+    Def::Item(ItemPath { module: mods[argmatch_idx.mod_idx].name.clone(), name })
+}
+
+// reports `#[specr::submatch(<path>)]` attributes which are malformed, or don't refer to a free argmatch function.
+fn check_submatch_attrs(mods: &[Module], resolver: &Resolver, diag: &mut Diagnostics) {
+    let argmatches: Vec<Def> = mods.iter()
+                                   .enumerate()
+                                   .flat_map(|(mod_idx, m)| fns(mod_idx, &m.ast))
+                                   .filter(|x| x.fn_idx.is_none())
+                                   .filter(|x| { let f = x.as_ref(mods); get_attr_info(f.attrs, f.sig).is_some() })
+                                   .map(|x| argmatch_def(&x, mods))
+                                   .collect();
+
+    for (mod_idx, m) in mods.iter().enumerate() {
+        for x in fns(mod_idx, &m.ast) {
+            let f = x.as_ref(mods);
+            let Some(path) = get_submatch_path(f.attrs) else { continue };
+            let path = match path {
+                Ok(path) => path,
+                Err(e) => {
+                    diag.syn_error(&m.source_map, e);
+                    continue;
+                },
+            };
+
+            if x.fn_idx.is_some() {
+                let msg = "`#[specr::submatch]` is only used by free functions, submatches of methods are found by their impl block";
+                diag.error(&m.source_map, path.span(), msg);
+                continue;
+            }
+
+            let def = resolver.resolve(&m.name, None, &path).ok();
+            if !def.map_or(false, |d| argmatches.contains(&d)) {
+                let msg = format!("`{}` is no argmatch function", path.to_token_stream().to_string().replace(' ', ""));
+                diag.error(&m.source_map, path.span(), msg);
+            }
+        }
+    }
+}
+
+fn clear_submatches(mods: &mut [Module], mut submatches: Vec<FnIdx>) {
+    // remove later items first, so that the indices of the other submatches stay valid.
+    submatches.sort();
+    submatches.reverse();
+
+    for s in submatches {
+        let ast = &mut mods[s.mod_idx].ast;
+        let Some(fn_idx) = s.fn_idx else {
+            ast.items.remove(s.item_idx);
+            continue;
+        };
+
+        let Item::Impl(ref mut ii) = ast.items[s.item_idx] else { panic!() };
+        ii.items.remove(fn_idx);
+
+        // it the resulting impl block would then be empty, remove it.
+        if ii.items.is_empty() {
+            ast.items.remove(s.item_idx);
+        }
+    }
+}
+
+// Searches for an `argmatch` attribute and returns its index and info.
+// The info is an error, if the attribute is malformed.
+fn get_attr_info(attrs: &[Attribute], sig: &Signature) -> Option<(usize, syn::Result<AttrInfo>)> {
+    let attr_idx = attrs.iter().position(|a| is_specr_attr(a, "argmatch"))?;
+    Some((attr_idx, parse_attr_info(sig, &attrs[attr_idx])))
+}
+
+fn parse_attr_info(sig: &Signature, attr: &Attribute) -> syn::Result<AttrInfo> {
+    let expected = "expected `#[specr::argmatch(<argument>, ...)]`";
+    let parser = |input: ParseStream| Punctuated::<Ident, Token![,]>::parse_terminated_with(input, Ident::parse_any);
+    let idents = attr.parse_args_with(parser).map_err(|e| Error::new(e.span(), expected))?;
+    if idents.is_empty() {
+        return Err(Error::new_spanned(attr, expected));
+    }
+
+    let mut args: Vec<(usize, Ident)> = Vec::new();
+    for match_ident in idents {
+        let match_idx = if match_ident == "self" {
+            matches!(sig.inputs.first(), Some(FnArg::Receiver(_))).then_some(0)
+        } else {
+            sig.inputs.iter().position(|arg| {
+                let FnArg::Typed(pat_ty) = arg else { return false };
+                let Pat::Ident(pi) = &*pat_ty.pat else { return false };
+
+                pi.ident == match_ident
+            })
+        };
+        let Some(match_idx) = match_idx else {
+            return Err(Error::new(match_ident.span(), format!("`{}` has no argument `{match_ident}`", sig.ident)));
+        };
+        if args.iter().any(|(idx, _)| *idx == match_idx) {
+            return Err(Error::new(match_ident.span(), format!("`{match_ident}` is matched upon twice")));
+        }
+
+        args.push((match_idx, match_ident));
+    }
+
+    Ok(AttrInfo { args })
+}
+
+// Searches for a `#[specr::submatch(<path>)]` attribute of a free function and parses its path.
+fn get_submatch_path(attrs: &[Attribute]) -> Option<syn::Result<Path>> {
+    let attr = attrs.iter().find(|a| is_specr_attr(a, "submatch"))?;
+    let path = attr.parse_args().map_err(|e| Error::new(e.span(), "expected `#[specr::submatch(<path>)]`"));
+    Some(path)
+}
+
+// Searches for a `#[specr::guard(<expr>)]` attribute of a submatch and parses its guard.
+fn get_guard(attrs: &[Attribute]) -> Option<syn::Result<Expr>> {
+    let attr = attrs.iter().find(|a| is_specr_attr(a, "guard"))?;
+    Some(attr.parse_args())
+}
+
+// checks whether `attr` is `#[specr::<name>...]`.
+pub(crate) fn is_specr_attr(attr: &Attribute, name: &str) -> bool {
+    let segments: Vec<String> = attr.path().segments
+                                           .iter()
+                                           .map(|x| x.ident.to_string())
+                                           .collect();
+
+    segments == ["specr", name]
+}
+
+#[test]
+fn test_relocate() {
+    let ops = Module::new("ops", parse_quote! {
+        pub enum Expr { Add, Neg, Nop }
+
+        #[specr::argmatch(e)]
+        pub fn ev(e: Expr, x: u32) -> u32;
+    });
+    let ops2 = Module::new("ops2", parse_quote! {
+        use crate::ops::Expr::{self, *};
+
+        const ONE: u32 = 1;
+        fn helper(x: u32) -> u32 { x + ONE }
+
+        #[specr::submatch(crate::ops::ev)]
+        fn ev(Add: Expr, x: u32) -> u32 { helper(x) }
+
+        #[specr::submatch(crate::ops::ev)]
+        #[specr::guard(helper(x) > ONE)]
+        fn ev(Neg: Expr, x: u32) -> u32 { assert!(helper(x) > 0); 0 }
+
+        // locals shadow the items of the module.
+        #[specr::submatch(crate::ops::ev)]
+        fn ev(e: Expr, x: u32) -> u32 { let helper = |x| x; helper(x) }
+    });
+
+    let mut diag = Diagnostics::default();
+    let mods = argmatch(vec![ops, ops2], &mut diag);
+    assert!(diag.messages().is_empty());
+
+    let expected: syn::File = parse_quote! {
+        pub enum Expr { Add, Neg, Nop }
+
+        pub fn ev(e: Expr, x: u32) -> u32 {
+            match e {
+                crate::ops::Expr::Add => { crate::ops2::helper(x) },
+                crate::ops::Expr::Neg if crate::ops2::helper(x) > crate::ops2::ONE => { assert!(crate::ops2::helper(x) > 0); 0 },
+                e => { let helper = |x| x; helper(x) },
+            }
+        }
+    };
+    assert_eq!(prettyplease::unparse(&mods[0].ast), prettyplease::unparse(&expected));
+
+    let expected: syn::File = parse_quote! {
+        use crate::ops::Expr::{self, *};
+
+        pub(crate) const ONE: u32 = 1;
+        pub(crate) fn helper(x: u32) -> u32 { x + ONE }
+    };
+    assert_eq!(prettyplease::unparse(&mods[1].ast), prettyplease::unparse(&expected));
+}
@@ -0,0 +1,184 @@
+use crate::prelude::*;
+use crate::resolve::{Resolver, Def, ItemPath};
+use crate::typerec::EXPR_MACROS;
+use super::Arm;
+
+use std::collections::HashSet;
+
+/// Prepares the arm of a submatch of another module for being moved into the module of the argmatch.
+///
+/// The paths of the specr code within the arm are resolved in `module`, the module of the submatch,
+/// and replaced by their definition, like `crate::lang::helper` for `helper`.
+/// The arguments and generic parameters of `sig`, the signature of the submatch, are never replaced.
+///
+/// Returns the items the arm refers to which are not visible in `target`, the module of the argmatch, if they are private.
+/// Names imported from other crates are kept as they are.
+pub(super) fn relocate(arm: &mut Arm, module: &str, target: &str, sig: &Signature, mods: &[Module], resolver: &Resolver) -> Vec<ItemPath> {
+    let mut locals = Locals { module, mods, resolver, names: HashSet::new() };
+    for arg in &sig.inputs {
+        if let FnArg::Typed(pt) = arg {
+            locals.visit_pat(&pt.pat);
+        }
+    }
+    for p in &arm.pats {
+        locals.visit_pat(p);
+    }
+    if let Some(g) = &arm.guard {
+        locals.visit_expr(g);
+    }
+    locals.visit_block(&arm.block);
+    locals.names.extend(sig.generics.params.iter().filter_map(|p| match p {
+        GenericParam::Type(t) => Some(t.ident.to_string()),
+        GenericParam::Const(c) => Some(c.ident.to_string()),
+        GenericParam::Lifetime(_) => None,
+    }));
+
+    let mut v = Canonicalize { module, resolver, locals: locals.names, items: Vec::new() };
+    for p in &mut arm.pats {
+        v.visit_pat_mut(p);
+    }
+    if let Some(g) = &mut arm.guard {
+        v.visit_expr_mut(g);
+    }
+    v.visit_block_mut(&mut arm.block);
+
+    // private items are visible within their module and its children.
+    v.items.retain(|p| target != p.module && !target.starts_with(&format!("{}::", p.module)));
+    v.items
+}
+
+/// Makes the private `items` visible in the whole crate, so that moved submatches can still refer to them.
+pub(super) fn widen(mods: &mut [Module], items: &[ItemPath]) {
+    for p in items {
+        let Some(m) = mods.iter_mut().find(|m| m.name == p.module) else { continue };
+        for item in &mut m.ast.items {
+            let (ident, vis) = match item {
+                Item::Const(x) => (&x.ident, &mut x.vis),
+                Item::Enum(x) => (&x.ident, &mut x.vis),
+                Item::Fn(x) => (&x.sig.ident, &mut x.vis),
+                Item::Static(x) => (&x.ident, &mut x.vis),
+                Item::Struct(x) => (&x.ident, &mut x.vis),
+                Item::Trait(x) => (&x.ident, &mut x.vis),
+                Item::Type(x) => (&x.ident, &mut x.vis),
+                Item::Union(x) => (&x.ident, &mut x.vis),
+                _ => continue,
+            };
+            if ident == &p.name && matches!(vis, Visibility::Inherited) {
+                *vis = parse_quote!(pub(crate));
+            }
+        }
+    }
+}
+
+// collects the names of the local variables bound within the arm.
+struct Locals<'a> {
+    module: &'a str,
+    mods: &'a [Module],
+    resolver: &'a Resolver,
+    names: HashSet<String>,
+}
+
+impl Locals<'_> {
+    // identifier patterns which refer to unit variants, constants or unit structs bind no variable.
+    fn binds_variable(&self, p: &PatIdent) -> bool {
+        if p.by_ref.is_some() || p.mutability.is_some() || p.subpat.is_some() { return true; }
+
+        match self.resolver.resolve(self.module, None, &Path::from(p.ident.clone())) {
+            Ok(Def::Variant(..)) => false,
+            Ok(Def::Item(item)) => {
+                let Some(m) = self.mods.iter().find(|m| m.name == item.module) else { return true };
+                !m.ast.items.iter().any(|i| match i {
+                    Item::Const(c) => c.ident == item.name,
+                    Item::Struct(s) => s.ident == item.name && matches!(s.fields, Fields::Unit),
+                    _ => false,
+                })
+            },
+            _ => true,
+        }
+    }
+}
+
+impl Visit<'_> for Locals<'_> {
+    fn visit_pat_ident(&mut self, p: &PatIdent) {
+        if self.binds_variable(p) {
+            self.names.insert(p.ident.to_string());
+        }
+        visit_pat_ident(self, p);
+    }
+}
+
+struct Canonicalize<'a> {
+    module: &'a str,
+    resolver: &'a Resolver,
+    locals: HashSet<String>,
+
+    /// the items the canonicalized paths refer to.
+    items: Vec<ItemPath>,
+}
+
+impl Canonicalize<'_> {
+    // the longest prefix of `path` which refers to something defined in the specr code, and its number of segments.
+    fn resolve_prefix(&self, path: &Path) -> Option<(Def, usize)> {
+        if path.leading_colon.is_some() { return None; }
+
+        let first = path.segments[0].ident.to_string();
+        if first == "Self" || (first == "self" && path.segments.len() == 1) { return None; }
+        if self.locals.contains(&first) { return None; }
+
+        let segments: Vec<String> = path.segments.iter().map(|s| s.ident.to_string()).collect();
+        (1..=segments.len()).rev().find_map(|n| {
+            let def = self.resolver.resolve_segments(self.module, None, &segments[..n]).ok()?;
+            Some((def, n))
+        })
+    }
+}
+
+impl VisitMut for Canonicalize<'_> {
+    fn visit_path_mut(&mut self, path: &mut Path) {
+        visit_path_mut(self, path);
+
+        let Some((def, n)) = self.resolve_prefix(path) else { return };
+        match &def {
+            Def::Enum(p) | Def::Item(p) | Def::Variant(p, _) => self.items.push(p.clone()),
+            _ => {},
+        }
+
+        let canonical = match def {
+            Def::Crate => "crate".to_string(),
+            def => format!("crate::{def}"),
+        };
+        let mut new: Path = parse_str(&canonical).unwrap();
+        new.segments.last_mut().unwrap().arguments = path.segments[n-1].arguments.clone();
+        new.segments.extend(path.segments.iter().skip(n).cloned());
+        *path = new;
+    }
+
+    // identifier patterns referring to items, like unit variants, become path patterns.
+    fn visit_pat_mut(&mut self, p: &mut Pat) {
+        if let Pat::Ident(pi) = p {
+            if pi.by_ref.is_none() && pi.mutability.is_none() && pi.subpat.is_none() && !self.locals.contains(&pi.ident.to_string()) {
+                let mut path = Path::from(pi.ident.clone());
+                self.visit_path_mut(&mut path);
+                if path.segments.len() > 1 {
+                    *p = Pat::Path(PatPath { attrs: pi.attrs.clone(), qself: None, path });
+                }
+                return;
+            }
+        }
+        visit_pat_mut(self, p);
+    }
+
+    // the paths of macros are not resolved, but the arguments of macros like `assert!(..)` are.
+    fn visit_macro_mut(&mut self, i: &mut Macro) {
+        let Some(name) = i.path.segments.last() else { return };
+        if !EXPR_MACROS.contains(&&*name.ident.to_string()) { return; }
+        let Ok(mut args) = i.parse_body_with(Punctuated::<Expr, Token![,]>::parse_terminated) else { return };
+
+        for arg in args.iter_mut() {
+            self.visit_expr_mut(arg);
+        }
+        i.tokens = args.to_token_stream();
+    }
+
+    fn visit_attribute_mut(&mut self, _: &mut Attribute) {}
+}
@@ -1,59 +1,127 @@
-use std::mem;
+use std::collections::HashMap;
 
 use crate::prelude::*;
+use crate::source_map::SourceMap;
+use crate::argmatch::is_specr_attr;
 
 /// Traits all structs & enums should derive.
-static GENERAL_TRAITS: &[&str] = &["GcCompat", "Debug"];
+/// `Encode` is addressed by its path, so that it doesn't need to be imported.
+static GENERAL_TRAITS: &[&str] = &["GcCompat", "Debug", "libspecr::Encode"];
 
 /// Traits only "objects" should derive. They get used in maps, sets, etc.
 static OBJ_TRAITS: &[&str] = &["Clone", "Copy", "PartialEq", "Eq", "Hash"];
 
-/// Adds `#[derive(_)]` for all missing traits in `GENERAL_TRAITS` and `OBJ_TRAITS`.
-pub fn auto_derive(mut ast: syn::File) -> syn::File {
-    for i in ast.items.iter_mut() {
-        let attrs = match i {
-            Item::Struct(s) => {
-                &mut s.attrs
-            },
-            Item::Enum(e) => {
-                &mut e.attrs
-            },
-            _ => { continue; },
-        };
-
-        for t in GENERAL_TRAITS {
-            add_derive_attr(t, attrs);
-        }
+/// Traits to derive in addition to the default ones, and default ones not to derive.
+#[derive(Clone, Default, Debug, PartialEq, Eq)]
+pub struct DerivePolicy {
+    /// traits to derive, like `PartialOrd` or `serde::Serialize`.
+    pub add: Vec<String>,
+
+    /// traits not to derive, even if they are derived by default or added by a less specific policy.
+    pub remove: Vec<String>,
+}
+
+/// The derive policies of a module.
+#[derive(Clone, Default, Debug)]
+pub struct ModuleDerives {
+    /// applied to all structs & enums of the module, in this order.
+    /// These are the policies of the crate, of the parent modules and of the module itself.
+    pub policies: Vec<DerivePolicy>,
+
+    /// applied to single items after `policies`, by the name of the item.
+    pub items: HashMap<String, DerivePolicy>,
+}
+
+/// Adds `#[derive(_)]` for all missing traits in `GENERAL_TRAITS` and `OBJ_TRAITS`,
+/// adapted by the `derives` of each module and by `#[specr::no_derive(...)]` attributes.
+pub fn auto_derive(mut mods: Vec<Module>, diag: &mut Diagnostics) -> Vec<Module> {
+    for m in mods.iter_mut() {
+        for i in m.ast.items.iter_mut() {
+            let (ident, attrs) = match i {
+                Item::Struct(s) => {
+                    (&s.ident, &mut s.attrs)
+                },
+                Item::Enum(e) => {
+                    (&e.ident, &mut e.attrs)
+                },
+                _ => { continue; },
+            };
 
-        // If attr `#[no_obj]` is present remove it and skip obj traits.
-        if attrs.iter().any(is_no_obj) {
-            remove_no_obj(attrs);
-            continue;
+            let mut traits: Vec<String> = GENERAL_TRAITS.iter().map(|t| t.to_string()).collect();
+
+            // If attr `#[no_obj]` is present remove it and skip obj traits.
+            if attrs.iter().any(is_no_obj) {
+                remove_no_obj(attrs);
+            } else {
+                traits.extend(OBJ_TRAITS.iter().map(|t| t.to_string()));
+            }
+
+            let item_policy = m.derives.items.get(&ident.to_string());
+            for p in m.derives.policies.iter().chain(item_policy) {
+                apply_policy(p, &mut traits);
+            }
+            if let Some(p) = no_derive_policy(attrs, &m.source_map, diag) {
+                apply_policy(&p, &mut traits);
+            }
+
+            for t in &traits {
+                add_derive_attr(t, attrs);
+            }
         }
-        
-        for t in OBJ_TRAITS {
-            add_derive_attr(t, attrs);
+    }
+
+    mods
+}
+
+fn apply_policy(p: &DerivePolicy, traits: &mut Vec<String>) {
+    traits.retain(|t| !p.remove.iter().any(|r| same_trait(t, r)));
+    for t in &p.add {
+        if !traits.iter().any(|t2| same_trait(t, t2)) {
+            traits.push(t.clone());
         }
     }
+}
 
-    ast
+/// checks whether `a` and `b` name the same trait, where the traits of libspecr may be written with or without `libspecr::`.
+fn same_trait(a: &str, b: &str) -> bool {
+    a.strip_prefix("libspecr::").unwrap_or(a) == b.strip_prefix("libspecr::").unwrap_or(b)
+}
+
+/// removes `#[specr::no_derive(...)]` and returns the traits it lists as policy.
+fn no_derive_policy(attrs: &mut Vec<Attribute>, source_map: &SourceMap, diag: &mut Diagnostics) -> Option<DerivePolicy> {
+    let idx = attrs.iter().position(|a| is_specr_attr(a, "no_derive"))?;
+    let attr = attrs.remove(idx);
+
+    let paths = match attr.parse_args_with(Punctuated::<Path, Token![,]>::parse_terminated) {
+        Ok(paths) => paths,
+        Err(_) => {
+            diag.error(source_map, attr.meta.span(), "expected `#[specr::no_derive(<trait>, ...)]`");
+            return None;
+        },
+    };
+    let remove = paths.iter().map(path_str).collect();
+
+    Some(DerivePolicy { add: Vec::new(), remove })
 }
 
 /// checks whether `attrs` contains some attribute `#[derive(..., t, ...)]`
 fn contains_derive_attr(t: &str, attrs: &[Attribute]) -> bool {
     attrs.iter().any(|attr| {
-        let Meta::List(list) = &attr.meta else { return false };
-        if format!("{}", list.path.to_token_stream()) != "derive" { return false; }
-        let Some(TokenTree::Group(g)) = list.tokens.clone().into_iter().next() else { return false };
+        if !attr.path().is_ident("derive") { return false; }
+        let Ok(paths) = attr.parse_args_with(Punctuated::<Path, Token![,]>::parse_terminated) else { return false };
 
-        g.stream().into_iter().any(|tk| format!("{}", tk) == t)
+        paths.iter().any(|p| same_trait(&path_str(p), t))
     })
 }
 
+fn path_str(p: &Path) -> String {
+    p.to_token_stream().to_string().replace(' ', "")
+}
+
 /// checks if this attribute is `#[no_obj]`
 fn is_no_obj(attr: &Attribute) -> bool {
     let Meta::Path(path) = &attr.meta else {
-        return false 
+        return false
     };
 
     format!("{}", path.to_token_stream()) == "no_obj"
@@ -61,9 +129,7 @@ fn is_no_obj(attr: &Attribute) -> bool {
 
 /// removes `#[no_obj]`
 fn remove_no_obj(attrs: &mut Vec<Attribute>) {
-    let owned_attrs = mem::replace(attrs, vec![]);
-    let owned_attrs = owned_attrs.into_iter().filter(|a| !is_no_obj(a)).collect::<Vec<_>>();
-    _ = mem::replace(attrs, owned_attrs);
+    attrs.retain(|a| !is_no_obj(a));
 }
 
 /// adds `#[derive(t)]` to `attrs`, if it's missing.
@@ -73,13 +139,38 @@ fn add_derive_attr(t: &str, attrs: &mut Vec<Attribute>) {
     }
 }
 
-/// generates `#[derive(t)]`
+/// generates `#[derive(t)]`, `t` may be a path like `serde::Serialize`.
 fn derive_attr(t: &str) -> Attribute {
-    let id = format_ident!("{}", t);
+    let path: Path = parse_str(t).unwrap_or_else(|_| panic!("`{t}` is not a valid trait path"));
+
+    parse_quote! { #[derive(#path)] }
+}
 
-    // Attributes can not be parsed in of itself,
-    // but only as prefix to some Item.
-    let code = quote! { #[derive(#id)] struct X; };
-    let Item::Struct(item) = parse2(code).unwrap() else { unreachable!() };
-    item.attrs[0].clone()
+#[test]
+fn test_derive_policies() {
+    let mut m = Module::new("lang", parse_quote! {
+        pub struct A;
+        pub struct B;
+        #[specr::no_derive(Encode)]
+        pub struct C;
+        #[derive(Encode)]
+        pub struct D;
+    });
+    m.derives.policies = vec![
+        DerivePolicy { add: vec!["PartialOrd".into(), "Ord".into()], remove: vec!["Hash".into()] },
+        DerivePolicy { add: vec![], remove: vec!["Ord".into()] },
+    ];
+    m.derives.items.insert("B".into(), DerivePolicy { add: vec!["serde::Serialize".into(), "Hash".into()], remove: vec!["Debug".into(), "Encode".into()] });
+
+    let mut diag = Diagnostics::default();
+    let mods = auto_derive(vec![m], &mut diag);
+
+    let derives = |i: usize| -> Vec<String> {
+        let Item::Struct(s) = &mods[0].ast.items[i] else { unreachable!() };
+        s.attrs.iter().map(|a| a.parse_args::<Path>().unwrap()).map(|p| path_str(&p)).collect()
+    };
+    assert_eq!(derives(0), ["GcCompat", "Debug", "libspecr::Encode", "Clone", "Copy", "PartialEq", "Eq", "PartialOrd"]);
+    assert_eq!(derives(1), ["GcCompat", "Clone", "Copy", "PartialEq", "Eq", "PartialOrd", "serde::Serialize", "Hash"]);
+    assert_eq!(derives(2), ["GcCompat", "Debug", "Clone", "Copy", "PartialEq", "Eq", "PartialOrd"]);
+    assert_eq!(derives(3), ["Encode", "GcCompat", "Debug", "Clone", "Copy", "PartialEq", "Eq", "PartialOrd"]);
 }
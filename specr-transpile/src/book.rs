@@ -0,0 +1,479 @@
+use crate::prelude::*;
+use crate::source_map::SourceLoc;
+use crate::argmatch;
+use crate::resolve::{self, Resolver};
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+
+static KEYWORDS: &[&str] = &[
+    "as", "break", "const", "continue", "crate", "else", "enum", "extern", "false", "fn", "for", "if", "impl", "in",
+    "let", "loop", "match", "mod", "move", "mut", "pub", "ref", "return", "self", "Self", "static", "struct", "super",
+    "trait", "true", "type", "unsafe", "use", "where", "while", "dyn",
+];
+
+/// An item of the specr code, which mentions in code blocks link to.
+struct Def {
+    name: String,
+
+    /// the type or trait of the impl block, for methods.
+    owner: Option<String>,
+
+    /// the kind of the item as used by rustdoc, like `struct` or `fn`.
+    kind: &'static str,
+    module: String,
+
+    /// where the name of the item is written.
+    loc: SourceLoc,
+
+    /// the rustdoc page of the item, relative to the rustdoc directory. `None` for items rustdoc doesn't document.
+    rustdoc: Option<String>,
+}
+
+impl Def {
+    fn anchor(&self) -> String {
+        match &self.owner {
+            Some(o) => format!("method.{o}.{}", self.name),
+            None => format!("{}.{}", self.kind, self.name),
+        }
+    }
+}
+
+/// Generates an mdBook of the Markdown files of `mods`, returns its files as (path, content).
+///
+/// The prose is kept as it is, and the ```rust code blocks are rendered as HTML,
+/// where every mention of an item of the specr code links to its definition,
+/// and the name of each definition links to the rustdoc of the generated crate.
+/// Mentions are resolved like in the generated crate, so names imported by `use` link to the imported item.
+pub fn render(mods: &[Module], config: &Config, diag: &mut Diagnostics) -> Vec<(PathBuf, String)> {
+    let Some(book) = &config.book else { return Vec::new() };
+    let out = config.book_path().unwrap();
+
+    let defs = collect_defs(mods, &config.name);
+    let resolver = Resolver::new(mods);
+    let rustdoc = match &book.rustdoc {
+        Some(url) => url.trim_end_matches('/').to_string(),
+        None => relative_path(&out.join("book"), &config.output_path().join("target/doc")),
+    };
+
+    let pages: Vec<(&Module, Vec<(String, String)>)> = mods.iter().map(|m| (m, md_files(config, m, diag))).collect();
+
+    let mut summary = String::from("# Summary\n\n");
+    for (m, _) in pages.iter().filter(|(m, _)| m.parent().is_none()) {
+        summarize(m, &pages, 0, &mut summary);
+    }
+
+    let mut files = Vec::new();
+    for (m, md) in &pages {
+        for (page, content) in md {
+            let html = render_page(page, content, &m.name, &defs, &resolver, &rustdoc);
+            files.push((out.join("src").join(page), html));
+        }
+    }
+
+    let book_toml = format!("[book]\ntitle = {}\nsrc = \"src\"\n", toml::Value::from(book.title.clone()));
+    files.push((out.join("book.toml"), book_toml));
+    files.push((out.join("src").join("SUMMARY.md"), summary));
+
+    files
+}
+
+// adds the chapters of `m` and its nested modules to `summary`.
+// `pages` are the Markdown files of each module.
+fn summarize(m: &Module, pages: &[(&Module, Vec<(String, String)>)], depth: usize, summary: &mut String) {
+    let indent = "  ".repeat(depth);
+    summary.push_str(&format!("{indent}- [{}]()\n", m.ident()));
+    let md = pages.iter().find(|(x, _)| x.name == m.name).map_or(&[][..], |(_, md)| &md[..]);
+    for (page, content) in md {
+        let title = content.lines()
+                           .find_map(|l| l.strip_prefix("# "))
+                           .map(|t| t.trim().to_string())
+                           .unwrap_or_else(|| page.rsplit('/').next().unwrap().trim_end_matches(".md").to_string());
+        summary.push_str(&format!("{indent}  - [{title}]({page})\n"));
+    }
+
+    for (child, _) in pages.iter().filter(|(c, _)| c.parent() == Some(&m.name)) {
+        summarize(child, pages, depth + 1, summary);
+    }
+}
+
+// the Markdown files of a module as (path relative to the input directory, content), ordered by name.
+// Files which cannot be read are reported, and left out of the book.
+fn md_files(config: &Config, m: &Module, diag: &mut Diagnostics) -> Vec<(String, String)> {
+    let dir = m.name.replace("::", "/");
+    let entries = match fs::read_dir(config.input_path().join(&dir)) {
+        Ok(entries) => entries,
+        Err(e) => {
+            diag.error_at(SourceLoc { file: dir, line: 1 }, format!("cannot read the directory of module `{}`: {e}", m.name));
+            return Vec::new();
+        },
+    };
+
+    let mut files: Vec<PathBuf> = Vec::new();
+    for f in entries {
+        match f {
+            Ok(f) => files.push(f.path()),
+            Err(e) => diag.error_at(SourceLoc { file: dir.clone(), line: 1 }, format!("cannot read the directory of module `{}`: {e}", m.name)),
+        }
+    }
+    files.retain(|f| f.is_file() && f.extension().map_or(false, |x| x == "md"));
+    files.sort();
+
+    let mut out = Vec::new();
+    for f in files {
+        let page = format!("{dir}/{}", f.file_name().unwrap().to_string_lossy());
+        match fs::read_to_string(&f) {
+            Ok(content) => out.push((page, content)),
+            Err(e) => diag.error_at(SourceLoc { file: page, line: 1 }, format!("cannot read file: {e}")),
+        }
+    }
+
+    out
+}
+
+// collects the items of all modules, and the methods of inherent impls and traits.
+fn collect_defs(mods: &[Module], krate: &str) -> Vec<Def> {
+    let krate = krate.replace('-', "_");
+    let mut defs: Vec<Def> = Vec::new();
+
+    for m in mods {
+        let doc_dir = format!("{krate}/{}", m.name.replace("::", "/"));
+        let mut add = |name: &Ident, owner: Option<(&Ident, &'static str)>, kind: &'static str, public: bool| {
+            let Some(loc) = m.source_map.lookup_span(name.span()) else { return };
+            let name = name.to_string();
+            let owner_name = owner.map(|(o, _)| o.to_string());
+            // argmatch functions are defined multiple times, the first one is the declaration.
+            if defs.iter().any(|d| d.module == m.name && d.name == name && d.owner == owner_name && d.kind == kind) { return; }
+
+            let rustdoc = public.then(|| match owner {
+                Some((o, owner_kind)) => format!("{doc_dir}/{owner_kind}.{o}.html#{kind}.{name}"),
+                None => format!("{doc_dir}/{kind}.{name}.html"),
+            });
+            defs.push(Def { name, owner: owner_name, kind, module: m.name.clone(), loc, rustdoc });
+        };
+
+        // this way, the declarations of argmatch functions are functions as well.
+        let mut ast = m.ast.clone();
+        argmatch::add_bodies(&mut ast);
+
+        let type_kinds: HashMap<String, &'static str> = ast.items.iter().filter_map(|i| match i {
+            Item::Struct(s) => Some((s.ident.to_string(), "struct")),
+            Item::Enum(e) => Some((e.ident.to_string(), "enum")),
+            _ => None,
+        }).collect();
+        let is_pub = |v: &Visibility| matches!(v, Visibility::Public(_));
+
+        for item in &ast.items {
+            match item {
+                Item::Struct(s) => add(&s.ident, None, "struct", is_pub(&s.vis)),
+                Item::Enum(e) => add(&e.ident, None, "enum", is_pub(&e.vis)),
+                Item::Fn(f) => add(&f.sig.ident, None, "fn", is_pub(&f.vis)),
+                Item::Type(t) => add(&t.ident, None, "type", is_pub(&t.vis)),
+                Item::Const(c) => add(&c.ident, None, "constant", is_pub(&c.vis)),
+                Item::Static(s) => add(&s.ident, None, "static", is_pub(&s.vis)),
+                Item::Trait(t) => {
+                    add(&t.ident, None, "trait", is_pub(&t.vis));
+                    for ti in &t.items {
+                        let TraitItem::Fn(f) = ti else { continue };
+                        let kind = if f.default.is_some() { "method" } else { "tymethod" };
+                        add(&f.sig.ident, Some((&t.ident, "trait")), kind, is_pub(&t.vis));
+                    }
+                },
+                Item::Impl(ii) if ii.trait_.is_none() => {
+                    let Type::Path(p) = &*ii.self_ty else { continue };
+                    let Some(owner) = p.path.segments.last().map(|s| &s.ident) else { continue };
+                    let owner_kind = type_kinds.get(&owner.to_string()).copied();
+                    for x in &ii.items {
+                        let ImplItem::Fn(f) = x else { continue };
+                        let public = is_pub(&f.vis) && owner_kind.is_some();
+                        add(&f.sig.ident, Some((owner, owner_kind.unwrap_or("struct"))), "method", public);
+                    }
+                },
+                _ => {},
+            }
+        }
+    }
+
+    defs
+}
+
+// converts the ```rust code blocks of a Markdown file to HTML, and keeps everything else.
+fn render_page(page: &str, content: &str, module: &str, defs: &[Def], resolver: &Resolver, rustdoc: &str) -> String {
+    // the path from the generated HTML file of `page` back to the root of the book.
+    let root = "../".repeat(page.matches('/').count());
+    let ctx = Ctx { page, module, defs, resolver, root: &root, rustdoc };
+
+    let mut out = String::new();
+    let mut lines = content.lines().enumerate();
+    while let Some((i, line)) = lines.next() {
+        if !line.starts_with("```rust") {
+            out.push_str(line);
+            out.push('\n');
+            continue;
+        }
+
+        let mut code = Vec::new();
+        let mut closed = false;
+        for (_, l) in lines.by_ref() {
+            if l == "```" { closed = true; break; }
+            code.push(l);
+        }
+        if !closed {
+            // unclosed code blocks are not part of the specr code, see `filter_specr_lang`.
+            out.push_str(line);
+            out.push('\n');
+            for l in code {
+                out.push_str(l);
+                out.push('\n');
+            }
+            break;
+        }
+
+        // `i` is the 0-based line of the fence, so the code starts at the 1-based line `i + 2`.
+        out.push_str("<pre class=\"hljs specr-code\">");
+        out.push_str(&ctx.render_code(&code.join("\n"), i + 2));
+        out.push_str("</pre>\n");
+    }
+
+    out
+}
+
+struct Ctx<'a> {
+    page: &'a str,
+    module: &'a str,
+    defs: &'a [Def],
+    resolver: &'a Resolver,
+    root: &'a str,
+    rustdoc: &'a str,
+}
+
+impl Ctx<'_> {
+    // renders the code starting at line `line` of the page as HTML.
+    fn render_code(&self, code: &str, mut line: usize) -> String {
+        let chars: Vec<char> = code.chars().collect();
+        let mut out = String::new();
+        let mut anchored = HashSet::new();
+        // the last two characters which are not whitespace, to find out whether an identifier is a method or a path segment.
+        let mut tail = [' ', ' '];
+        // the segments of the path the last identifier belongs to, like `["crate", "lang", "Expr"]`.
+        let mut path: Vec<String> = Vec::new();
+
+        let mut i = 0;
+        while i < chars.len() {
+            let c = chars[i];
+            let start = i;
+            let class = if c == '/' && chars.get(i+1) == Some(&'/') {
+                while i < chars.len() && chars[i] != '\n' { i += 1; }
+                Some("hljs-comment")
+            } else if c == '/' && chars.get(i+1) == Some(&'*') {
+                i += 2;
+                while i < chars.len() && !(chars[i] == '*' && chars.get(i+1) == Some(&'/')) { i += 1; }
+                i = (i + 2).min(chars.len());
+                Some("hljs-comment")
+            } else if c == '"' || string_start(&chars[i..]).is_some() {
+                i = string_end(&chars, i);
+                Some("hljs-string")
+            } else if c == '\'' && (chars.get(i+2) == Some(&'\'') || chars.get(i+1) == Some(&'\\')) {
+                i += 2;
+                while i < chars.len() && chars[i-1] != '\'' { i += 1; }
+                Some("hljs-string")
+            } else if c.is_ascii_digit() {
+                while i < chars.len() && (chars[i].is_alphanumeric() || chars[i] == '_' || chars[i] == '.' && chars.get(i+1).map_or(false, |c| c.is_ascii_digit())) { i += 1; }
+                Some("hljs-number")
+            } else if c.is_alphabetic() || c == '_' {
+                while i < chars.len() && (chars[i].is_alphanumeric() || chars[i] == '_') { i += 1; }
+                let ident: String = chars[start..i].iter().collect();
+                // lifetimes are no mentions of items.
+                let lifetime = start > 0 && chars[start-1] == '\'';
+                if KEYWORDS.contains(&&*ident) {
+                    Some("hljs-keyword")
+                } else if lifetime {
+                    None
+                } else {
+                    if tail != [':', ':'] { path.clear(); }
+                    path.push(ident.clone());
+                    out.push_str(&self.ident(&path, line, tail, &mut anchored));
+                    tail = [tail[1], 'a'];
+                    continue;
+                }
+            } else {
+                i += 1;
+                None
+            };
+
+            let text: String = chars[start..i].iter().collect();
+            line += text.matches('\n').count();
+            match class {
+                Some(class) => out.push_str(&format!("<span class=\"{class}\">{}</span>", escape(&text))),
+                None => out.push_str(&escape(&text)),
+            }
+            for c in text.chars().filter(|c| !c.is_whitespace()) {
+                tail = [tail[1], c];
+                // keywords like `crate` and `self` can start a path.
+                if c != ':' && class != Some("hljs-keyword") { path.clear(); }
+            }
+            if class == Some("hljs-keyword") {
+                path = vec![text];
+            }
+        }
+
+        out
+    }
+
+    // renders the last identifier of `path` at `line`, as anchor if it is the name of a definition, and as link if it mentions one.
+    // `tail` are the two characters before it which are not whitespace.
+    fn ident(&self, path: &[String], line: usize, tail: [char; 2], anchored: &mut HashSet<(usize, String)>) -> String {
+        let ident = &**path.last().unwrap();
+        let def = self.defs.iter().find(|d| {
+            d.name == ident && d.loc.file == self.page && d.loc.line == line && !anchored.contains(&(line, d.anchor()))
+        });
+        if let Some(d) = def {
+            anchored.insert((line, d.anchor()));
+            let href = match &d.rustdoc {
+                Some(doc) => format!(" href=\"{}\" title=\"rustdoc\"", self.doc_url(doc)),
+                None => String::new(),
+            };
+            return format!("<a id=\"{}\"{href}>{ident}</a>", d.anchor());
+        }
+
+        let is_method = tail[1] == '.' && tail[0] != '.';
+        // the type of `Self` is not known either, so `Self::f` is linked like a method.
+        let target = match is_method || path[0] == "Self" {
+            true => self.method(ident),
+            false => self.resolve(path),
+        };
+        let Some(target) = target else { return escape(ident) };
+
+        let page = target.loc.file.strip_suffix(".md").unwrap_or(&target.loc.file);
+        format!("<a href=\"{}{page}.html#{}\">{ident}</a>", self.root, target.anchor())
+    }
+
+    // finds the item `path` refers to, like the generated crate would.
+    // Paths to associated functions like `Expr::new` are resolved to the methods of the type.
+    fn resolve(&self, path: &[String]) -> Option<&Def> {
+        let item = |d: &resolve::Def| match d {
+            resolve::Def::Item(p) | resolve::Def::Enum(p) => Some(p.clone()),
+            _ => None,
+        };
+
+        if let Ok(d) = self.resolver.resolve_segments(self.module, None, path) {
+            let p = item(&d)?;
+            return self.defs.iter().find(|d| d.owner.is_none() && d.module == p.module && d.name == p.name);
+        }
+
+        let (name, owner) = path.split_last()?;
+        let owner = item(&self.resolver.resolve_segments(self.module, None, owner).ok()?)?;
+        self.defs.iter().find(|d| d.owner.as_ref() == Some(&owner.name) && d.module == owner.module && d.name == *name)
+    }
+
+    // the receiver of a method call is not known, so a method is only linked if its name is unambiguous.
+    // Methods of the current module are preferred.
+    fn method(&self, name: &str) -> Option<&Def> {
+        let candidates: Vec<&Def> = self.defs.iter().filter(|d| d.name == name && d.owner.is_some()).collect();
+        let local: Vec<&Def> = candidates.iter().copied().filter(|d| d.module == self.module).collect();
+        match (&local[..], &candidates[..]) {
+            ([d], _) => Some(d),
+            ([], [d]) => Some(d),
+            // ambiguous or unknown.
+            _ => None,
+        }
+    }
+
+    fn doc_url(&self, doc: &str) -> String {
+        if self.rustdoc.contains("://") || self.rustdoc.starts_with('/') {
+            format!("{}/{doc}", self.rustdoc)
+        } else {
+            format!("{}{}/{doc}", self.root, self.rustdoc)
+        }
+    }
+}
+
+// returns the length of the prefix of a string literal like `r#"`, `b"` or `br"`, if `chars` starts with one.
+fn string_start(chars: &[char]) -> Option<usize> {
+    let mut i = 0;
+    if chars.first() == Some(&'b') { i += 1; }
+    if chars.get(i) == Some(&'r') {
+        i += 1;
+        while chars.get(i) == Some(&'#') { i += 1; }
+    }
+    (i > 0 && chars.get(i) == Some(&'"')).then_some(i + 1)
+}
+
+// returns the end of the string literal starting at `i`.
+fn string_end(chars: &[char], i: usize) -> usize {
+    let prefix = string_start(&chars[i..]).unwrap_or(1);
+    let raw = chars[i..i+prefix].contains(&'r');
+    let hashes = chars[i..i+prefix].iter().filter(|c| **c == '#').count();
+
+    let mut j = i + prefix;
+    while j < chars.len() {
+        if chars[j] == '\\' && !raw {
+            j += 2;
+            continue;
+        }
+        if chars[j] == '"' && chars[j+1..].iter().take(hashes).filter(|c| **c == '#').count() == hashes {
+            return (j + 1 + hashes).min(chars.len());
+        }
+        j += 1;
+    }
+
+    chars.len()
+}
+
+fn escape(s: &str) -> String {
+    s.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
+
+// the relative path from the directory `from` to `to`, both absolute.
+fn relative_path(from: &Path, to: &Path) -> String {
+    let from = normalize(from);
+    let to = normalize(to);
+    let common = from.iter().zip(&to).take_while(|(a, b)| a == b).count();
+
+    let mut parts: Vec<String> = vec!["..".to_string(); from.len() - common];
+    parts.extend(to[common..].iter().cloned());
+    parts.join("/")
+}
+
+// the components of an absolute path, with `.` and `..` resolved.
+fn normalize(path: &Path) -> Vec<String> {
+    let mut out = Vec::new();
+    for c in path.components() {
+        match c {
+            Component::ParentDir => { out.pop(); },
+            Component::Normal(x) => out.push(x.to_string_lossy().into_owned()),
+            _ => {},
+        }
+    }
+
+    out
+}
+
+#[test]
+fn test_render_page() {
+    let lang = "# Lang\n\n```rust\npub struct Expr;\n\nimpl Expr {\n    pub fn new() -> Expr { Expr }\n}\n```\n";
+    let other = "```rust\npub struct Expr;\n```\n";
+    let eval = "```rust\nuse crate::lang::Expr;\n\nfn eval(e: Expr) -> Expr { Expr::new() }\n```\n";
+
+    let mut diag = Diagnostics::default();
+    let mut mods = Vec::new();
+    for (name, content) in [("lang", lang), ("other", other), ("eval", eval)] {
+        let m = crate::source::from_markdown(name, &[(format!("{name}.md"), content.to_string())], &mut diag);
+        mods.push(m.unwrap());
+    }
+    let defs = collect_defs(&mods, "spec");
+    let resolver = Resolver::new(&mods);
+
+    let html = render_page("lang/lang.md", lang, "lang", &defs, &resolver, "doc");
+    assert!(html.starts_with("# Lang\n\n<pre class=\"hljs specr-code\">"));
+    assert!(html.contains("<span class=\"hljs-keyword\">struct</span> <a id=\"struct.Expr\" href=\"../doc/spec/lang/struct.Expr.html\" title=\"rustdoc\">Expr</a>;"));
+    assert!(html.contains("<a id=\"method.Expr.new\" href=\"../doc/spec/lang/struct.Expr.html#method.new\" title=\"rustdoc\">new</a>() -&gt; <a href=\"../lang/lang.html#struct.Expr\">Expr</a>"));
+
+    // `Expr` is imported from `lang`, even though `other` has an `Expr` as well.
+    let html = render_page("eval/eval.md", eval, "eval", &defs, &resolver, "doc");
+    assert!(html.contains("<span class=\"hljs-keyword\">crate</span>::lang::<a href=\"../lang/lang.html#struct.Expr\">Expr</a>;"));
+    assert!(html.contains("(e: <a href=\"../lang/lang.html#struct.Expr\">Expr</a>) -&gt; <a href=\"../lang/lang.html#struct.Expr\">Expr</a>"));
+    assert!(html.contains("<a href=\"../lang/lang.html#struct.Expr\">Expr</a>::<a href=\"../lang/lang.html#method.Expr.new\">new</a>()"));
+    assert!(!html.contains("other.html"));
+}
@@ -0,0 +1,195 @@
+use crate::prelude::*;
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+
+/// The file in the generated crate which remembers the inputs it was generated from.
+///
+/// ```toml
+/// hash = "8d3c1f0a2b4e6d7c"
+/// files = ["Cargo.toml", "src/lib.rs", ...]
+/// ```
+pub const CACHE_FILE: &str = "specr-cache.toml";
+
+/// Hashes everything the generated crate depends on:
+/// the config file, all Markdown files in the input directory, and the transpiler itself.
+///
+/// The hash is stored on disk, so it must not change between runs or Rust versions.
+/// That's why we use FNV-1a on the raw bytes instead of `DefaultHasher`.
+pub fn input_hash(config: &Config) -> u64 {
+    let mut hasher = Fnv::new();
+
+    // a rebuilt transpiler might generate different code.
+    if let Ok(exe) = std::env::current_exe() {
+        if let Ok(modified) = fs::metadata(exe).and_then(|m| m.modified()) {
+            let since_epoch = modified.duration_since(UNIX_EPOCH).unwrap_or_default();
+            hasher.write(&since_epoch.as_nanos().to_le_bytes());
+        }
+    }
+
+    // files which cannot be read are skipped, reading them again during the transpilation reports the error.
+    // In `--watch` mode, this happens e.g. while an editor replaces a file, and the next poll sees the new file.
+    if let Ok(bytes) = fs::read(&config.path) {
+        hasher.write_chunk(&bytes);
+    }
+
+    let input = config.input_path();
+    for f in md_files(&input) {
+        hasher.write_chunk(f.strip_prefix(&input).unwrap().to_string_lossy().as_bytes());
+        if let Ok(bytes) = fs::read(&f) {
+            hasher.write_chunk(&bytes);
+        }
+    }
+
+    hasher.0
+}
+
+// The 64-bit FNV-1a hash, see http://www.isthe.com/chongo/tech/comp/fnv/.
+struct Fnv(u64);
+
+impl Fnv {
+    fn new() -> Fnv {
+        Fnv(0xcbf29ce484222325)
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        for b in bytes {
+            self.0 ^= *b as u64;
+            self.0 = self.0.wrapping_mul(0x100000001b3);
+        }
+    }
+
+    // writes the length before the bytes, so that e.g. the chunks "ab", "c" and "a", "bc" hash differently.
+    fn write_chunk(&mut self, bytes: &[u8]) {
+        self.write(&(bytes.len() as u64).to_le_bytes());
+        self.write(bytes);
+    }
+}
+
+/// Checks whether the generated crate was generated from inputs with hash `hash`, and is still complete.
+pub fn is_fresh(config: &Config, hash: u64) -> bool {
+    let out = config.output_path();
+    let Ok(s) = fs::read_to_string(out.join(CACHE_FILE)) else { return false };
+    let Ok(table) = s.parse::<toml::Table>() else { return false };
+
+    let hash_matches = table.get("hash").and_then(|h| h.as_str()) == Some(&*format!("{hash:016x}"));
+    let files_exist = match table.get("files").and_then(|f| f.as_array()) {
+        Some(files) => files.iter().all(|f| f.as_str().map_or(false, |f| out.join(f).exists())),
+        None => false,
+    };
+
+    hash_matches && files_exist
+}
+
+/// Remembers that the generated crate, consisting of `files`, was generated from inputs with hash `hash`.
+/// Files of the previously generated crate which are not part of it anymore are removed,
+/// like the module of a deleted Markdown file.
+pub fn store(config: &Config, hash: u64, files: &[PathBuf]) {
+    let out = config.output_path();
+    let files: Vec<String> = files.iter()
+                                  .map(|f| f.strip_prefix(&out).unwrap_or(f).to_string_lossy().into_owned())
+                                  .collect();
+
+    for old in stored_files(&out) {
+        if !files.contains(&old) {
+            let _ = fs::remove_file(out.join(old));
+        }
+    }
+
+    let files: Vec<toml::Value> = files.into_iter().map(Into::into).collect();
+    let mut table = toml::Table::new();
+    table.insert("hash".to_string(), format!("{hash:016x}").into());
+    table.insert("files".to_string(), files.into());
+    fs::write(out.join(CACHE_FILE), table.to_string()).unwrap();
+}
+
+// returns the files of the generated crate in `out`, as listed by its cache file.
+// Only relative paths within `out` are returned, so that nothing outside of it is ever removed.
+fn stored_files(out: &Path) -> Vec<String> {
+    let Ok(s) = fs::read_to_string(out.join(CACHE_FILE)) else { return Vec::new() };
+    let Ok(table) = s.parse::<toml::Table>() else { return Vec::new() };
+    let Some(files) = table.get("files").and_then(|f| f.as_array()) else { return Vec::new() };
+
+    files.iter()
+         .filter_map(|f| f.as_str())
+         .filter(|f| Path::new(f).components().all(|c| matches!(c, std::path::Component::Normal(_))))
+         .map(|f| f.to_string())
+         .collect()
+}
+
+// returns all .md files within `dir` recursively, in a deterministic order.
+fn md_files(dir: &Path) -> Vec<PathBuf> {
+    let mut files = Vec::new();
+    let Ok(entries) = fs::read_dir(dir) else { return files };
+
+    for e in entries.flatten() {
+        let path = e.path();
+        if path.is_dir() {
+            files.extend(md_files(&path));
+        } else if path.extension().map_or(false, |x| x == "md") {
+            files.push(path);
+        }
+    }
+
+    files.sort();
+    files
+}
+
+// loads the config of a new, empty test directory, which generates `out` from the Markdown files in `input`.
+#[cfg(test)]
+fn test_config(name: &str) -> Config {
+    let dir = std::env::temp_dir().join(format!("specr-{name}-{}", std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(dir.join("input/lang")).unwrap();
+    fs::create_dir_all(dir.join("out/src")).unwrap();
+    fs::write(dir.join("specr.toml"), "input = \"input\"\noutput = \"out\"\nname = \"spec\"\n").unwrap();
+
+    Config::from_file(&dir.join("specr.toml"))
+}
+
+#[test]
+fn test_is_fresh() {
+    let config = test_config("is-fresh");
+    let md = config.input_path().join("lang/lang.md");
+    let lib = config.output_path().join("src/lib.rs");
+    fs::write(&md, "```rust\nfn foo() {}\n```\n").unwrap();
+
+    let hash = input_hash(&config);
+    assert!(!is_fresh(&config, hash));
+
+    fs::write(&lib, "").unwrap();
+    store(&config, hash, std::slice::from_ref(&lib));
+    assert!(is_fresh(&config, hash));
+    assert!(!is_fresh(&config, hash.wrapping_add(1)));
+
+    fs::write(&md, "```rust\nfn bar() {}\n```\n").unwrap();
+    assert_ne!(input_hash(&config), hash);
+
+    // a generated file was removed.
+    fs::remove_file(&lib).unwrap();
+    assert!(!is_fresh(&config, hash));
+
+    fs::remove_dir_all(&config.root).unwrap();
+}
+
+#[test]
+fn test_store() {
+    let config = test_config("store");
+    let out = config.output_path();
+    let [a, b] = [out.join("src/a.rs"), out.join("src/b.rs")];
+    fs::write(&a, "").unwrap();
+    fs::write(&b, "").unwrap();
+
+    store(&config, 0, &[a.clone(), b.clone()]);
+    store(&config, 0, std::slice::from_ref(&a));
+    assert!(a.exists());
+    assert!(!b.exists());
+
+    // files outside of the generated crate are never removed.
+    fs::write(out.join(CACHE_FILE), "hash = \"0\"\nfiles = [\"../specr.toml\"]\n").unwrap();
+    store(&config, 0, &[]);
+    assert!(config.path.exists());
+
+    fs::remove_dir_all(&config.root).unwrap();
+}
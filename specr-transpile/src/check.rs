@@ -0,0 +1,57 @@
+use crate::prelude::*;
+
+use std::fs;
+use std::process::Command;
+
+/// The file in the generated crate which maps the generated items back to the Markdown code.
+///
+/// For each generated file, it contains a table mapping the line of each item, method and statement to its Markdown location:
+/// ```toml
+/// ["src/lang.rs"]
+/// 12 = "lang/step.md:123"
+/// ```
+pub const SOURCE_MAP_FILE: &str = "specr-source-map.toml";
+
+/// Runs `cargo check` on the generated crate,
+/// and prints its diagnostics with the locations mapped back to the Markdown code.
+/// Returns whether the check succeeded.
+pub fn check(config: &Config) -> bool {
+    let out = config.output_path();
+    let source_map = fs::read_to_string(out.join(SOURCE_MAP_FILE)).unwrap();
+    let source_map = source_map.parse::<toml::Table>().unwrap();
+
+    let output = Command::new("cargo")
+        .args(["check", "--message-format=short"])
+        .current_dir(&out)
+        .output()
+        .expect("Could not run `cargo check`!");
+
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    for line in stderr.lines() {
+        match remap(line, &source_map) {
+            Some(l) => eprintln!("{l}"),
+            None => eprintln!("{line}"),
+        }
+    }
+
+    output.status.success()
+}
+
+// converts `src/lang.rs:57:9: error: ...` to `lang/step.md:123: error: ... (src/lang.rs:57:9)`.
+fn remap(line: &str, source_map: &toml::Table) -> Option<String> {
+    let mut parts = line.splitn(4, ':');
+    let file = parts.next()?;
+    let line_nr: usize = parts.next()?.parse().ok()?;
+    let column: usize = parts.next()?.parse().ok()?;
+    let msg = parts.next()?;
+
+    // the location is taken from the last item, method or statement starting before `line_nr`.
+    let items = source_map.get(file)?.as_table()?;
+    let loc = items.iter()
+                   .filter_map(|(l, loc)| Some((l.parse::<usize>().ok()?, loc.as_str()?)))
+                   .filter(|(l, _)| *l <= line_nr)
+                   .max_by_key(|(l, _)| *l)
+                   .map(|(_, loc)| loc)?;
+
+    Some(format!("{loc}:{msg} ({file}:{line_nr}:{column})"))
+}
@@ -1,7 +1,21 @@
 use std::path::{Path, PathBuf};
+use std::collections::HashMap;
 use std::fs;
 
+use quote::ToTokens;
+
+use crate::auto_derive::{DerivePolicy, ModuleDerives};
+
+/// The default version of libspecr, which is released together with specr-transpile.
+const LIBSPECR_VERSION: &str = concat!("=", env!("CARGO_PKG_VERSION"));
+
+/// The default version of gccompat-derive.
+const GCCOMPAT_DERIVE_VERSION: &str = "=0.1.3";
+
 pub struct Config {
+    /// the config file.
+    pub path: PathBuf,
+
     /// config root directory.
     pub root: PathBuf,
 
@@ -20,17 +34,99 @@ pub struct Config {
 
     /// The name of the generated crate.
     pub name: String,
+
+    /// The dependencies of the generated crate, in the format of the `[dependencies]` table of a `Cargo.toml`.
+    /// This always contains `libspecr` and `gccompat-derive`. Relative paths are already resolved.
+    pub dependencies: toml::Table,
+
+    /// The `[features]` table of the generated crate.
+    pub features: toml::Table,
+
+    /// If this is `Some`, only these modules are generated.
+    pub include: Option<Vec<String>>,
+
+    /// These modules are not generated.
+    pub exclude: Vec<String>,
+
+    /// If this is `Some`, only these modules are generated, in this order.
+    pub list: Option<Vec<String>>,
+
+    /// The traits to derive for the structs & enums, given by the `[derive]` table.
+    pub derive: DeriveConfig,
+
+    /// The optional passes to run, like `visit`.
+    pub passes: Vec<String>,
+
+    /// If this is `Some`, an mdBook of the spec is generated, as given by the `[book]` table.
+    pub book: Option<BookConfig>,
+
+    /// Whether to run `cargo check` on the generated crate, set by `--check`.
+    pub check: bool,
+
+    /// Whether to transpile again whenever the input changes, set by `--watch`.
+    pub watch: bool,
+
+    /// The pass after which the code of each module is dumped, set by `--dump-after=<pass>`.
+    pub dump_after: Option<String>,
+}
+
+/// The `[book]` table.
+pub struct BookConfig {
+    /// where to generate the book.
+    pub output: String,
+
+    /// the title of the book, the name of the generated crate by default.
+    pub title: String,
+
+    /// the directory containing the rustdoc of the generated crate, like `https://docs.example.org/spec`.
+    /// By default, this is the `target/doc` directory of the generated crate.
+    pub rustdoc: Option<String>,
+}
+
+/// The derive policies of the crate, of modules and of single items.
+#[derive(Default)]
+pub struct DeriveConfig {
+    /// applies to all structs & enums.
+    pub default: DerivePolicy,
+
+    /// by module name, like `lang::step`. Applies to nested modules as well.
+    pub modules: HashMap<String, DerivePolicy>,
+
+    /// by item path, like `lang::Program`.
+    pub items: HashMap<String, DerivePolicy>,
 }
 
 impl Config {
     pub fn load() -> Config {
-        let [_, ref f] = std::env::args().collect::<Vec<_>>()[..] else {
+        let args: Vec<String> = std::env::args().skip(1).collect();
+        let check = args.iter().any(|x| x == "--check");
+        let watch = args.iter().any(|x| x == "--watch");
+        let dump_after = args.iter().find_map(|x| x.strip_prefix("--dump-after=")).map(String::from);
+        if let Some(p) = &dump_after {
+            if crate::pipeline::pass(p).is_none() {
+                let passes: Vec<&str> = crate::pipeline::PASSES.iter().map(|p| p.name).collect();
+                eprintln!("Unknown pass `{p}`, the passes are: {}", passes.join(", "));
+                std::process::exit(1);
+            }
+        }
+        let files: Vec<&String> = args.iter().filter(|x| !x.starts_with("--")).collect();
+        let [f] = files[..] else {
             eprintln!("Usage:");
-            eprintln!("specr-transpile <specr.toml>");
+            eprintln!("specr-transpile <specr.toml> [--check] [--watch] [--dump-after=<pass>]");
             eprintln!("");
             panic!("invalid amount of command-line arguments!");
         };
 
+        let mut config = Config::from_file(Path::new(f));
+        config.check = check;
+        config.watch = watch;
+        config.dump_after = dump_after;
+
+        config
+    }
+
+    /// Loads the config file `f`, without any command-line flags.
+    pub fn from_file(f: &Path) -> Config {
         let f = fs::canonicalize(f).unwrap();
         let s = fs::read_to_string(&f).unwrap();
         let root = f.parent().unwrap().to_path_buf();
@@ -45,17 +141,115 @@ impl Config {
                           .unwrap_or_else(Vec::new);
         let channel = table.get("channel")
                           .map(|v| v.clone().try_into().expect("`channel` is required to be a string!"));
-        let name = table.get("name").expect("`name` is missing in config file")
+        let name: String = table.get("name").expect("`name` is missing in config file")
                           .clone().try_into().expect("`name` is required to be a string!");
+        let workspace: Option<String> = table.get("workspace")
+                          .map(|v| v.clone().try_into().expect("`workspace` is required to be a string!"));
+        let dependencies: toml::Table = table.get("dependencies")
+                          .map(|v| v.clone().try_into().expect("`dependencies` is required to be a table!"))
+                          .unwrap_or_default();
+        let features = table.get("features")
+                          .map(|v| v.clone().try_into().expect("`features` is required to be a table!"))
+                          .unwrap_or_default();
+        let modules: toml::Table = table.get("modules")
+                          .map(|v| v.clone().try_into().expect("`modules` is required to be a table!"))
+                          .unwrap_or_default();
+        let include = modules.get("include")
+                          .map(|v| v.clone().try_into().expect("`modules.include` is required to be an array of strings!"));
+        let exclude = modules.get("exclude")
+                          .map(|v| v.clone().try_into().expect("`modules.exclude` is required to be an array of strings!"))
+                          .unwrap_or_default();
+        let list = modules.get("list")
+                          .map(|v| v.clone().try_into().expect("`modules.list` is required to be an array of strings!"));
+        let passes: Vec<String> = table.get("passes")
+                          .map(|v| v.clone().try_into().expect("`passes` is required to be an array of strings!"))
+                          .unwrap_or_default();
+        for p in &passes {
+            if !crate::pipeline::pass(p).map_or(false, |p| p.optional) {
+                let optional: Vec<&str> = crate::pipeline::PASSES.iter().filter(|p| p.optional).map(|p| p.name).collect();
+                eprintln!("Unknown optional pass `{p}` in `passes`, the optional passes are: {}", optional.join(", "));
+                std::process::exit(1);
+            }
+        }
+        let book = table.get("book").map(|v| {
+            let book = v.as_table().expect("`book` is required to be a table!");
+            let output = book.get("output").expect("`book.output` missing in config file")
+                             .as_str().expect("`book.output` is no string!").to_string();
+            let title = book.get("title")
+                            .map(|v| v.as_str().expect("`book.title` is no string!").to_string())
+                            .unwrap_or_else(|| name.clone());
+            let rustdoc = book.get("rustdoc")
+                              .map(|v| v.as_str().expect("`book.rustdoc` is no string!").to_string());
+            BookConfig { output, title, rustdoc }
+        });
+        let derive = table.get("derive")
+                          .map(|v| DeriveConfig::parse(v.as_table().expect("`derive` is required to be a table!")))
+                          .unwrap_or_default();
 
-        Config {
+        let mut config = Config {
+            path: f,
             root,
             input,
             output,
             attrs,
             channel,
             name,
+            dependencies: toml::Table::new(),
+            features,
+            include,
+            exclude,
+            list,
+            derive,
+            passes,
+            book,
+            check: false,
+            watch: false,
+            dump_after: None,
+        };
+        config.dependencies = config.resolve_dependencies(dependencies, workspace);
+
+        config
+    }
+
+    // adds the default dependencies, and makes `path` dependencies relative to the config root.
+    fn resolve_dependencies(&self, mut deps: toml::Table, workspace: Option<String>) -> toml::Table {
+        for (name, version) in [("libspecr", LIBSPECR_VERSION), ("gccompat-derive", GCCOMPAT_DERIVE_VERSION)] {
+            if deps.contains_key(name) { continue; }
+
+            // in workspace mode, we use the crates from a local checkout of this repository.
+            let dep = match &workspace {
+                Some(ws) => {
+                    let mut t = toml::Table::new();
+                    t.insert("path".to_string(), format!("{ws}/{name}").into());
+                    t.into()
+                },
+                None => version.into(),
+            };
+            deps.insert(name.to_string(), dep);
         }
+
+        for (_, dep) in deps.iter_mut() {
+            let Some(dep) = dep.as_table_mut() else { continue };
+            let Some(path) = dep.get("path") else { continue };
+            let path = path.as_str().expect("dependency `path` is required to be a string!");
+            let path = self.canonicalize(path).to_string_lossy().into_owned();
+            dep.insert("path".to_string(), path.into());
+        }
+
+        deps
+    }
+
+    /// Whether the module `name` should be generated, according to `include` and `list`.
+    /// Module names are paths like `lang::step`.
+    pub fn is_included(&self, name: &str) -> bool {
+        [&self.include, &self.list].into_iter()
+                                   .flatten()
+                                   .all(|i| i.iter().any(|x| x == name))
+    }
+
+    /// Whether the module `name` is excluded, either directly or as a child of an excluded module.
+    pub fn is_excluded(&self, name: &str) -> bool {
+        self.exclude.iter().any(|x| name == x || name.starts_with(&format!("{x}::")))
     }
 
     pub fn input_path(&self) -> PathBuf {
@@ -66,6 +260,11 @@ impl Config {
         self.canonicalize(&self.output)
     }
 
+    /// The directory of the mdBook, if one is generated.
+    pub fn book_path(&self) -> Option<PathBuf> {
+        self.book.as_ref().map(|b| self.canonicalize(&b.output))
+    }
+
     // converts relative paths to be relative from `root`
     fn canonicalize(&self, t: impl AsRef<Path>) -> PathBuf {
         let path = t.as_ref();
@@ -76,3 +275,64 @@ impl Config {
         }
     }
 }
+
+impl DeriveConfig {
+    // parses the `[derive]` table, which has the keys `add` and `remove`, and the subtables `modules` and `items`.
+    fn parse(table: &toml::Table) -> DeriveConfig {
+        let policies = |key: &str| -> HashMap<String, DerivePolicy> {
+            let Some(t) = table.get(key) else { return HashMap::new() };
+            let t = t.as_table().unwrap_or_else(|| panic!("`derive.{key}` is required to be a table!"));
+            t.iter()
+             .map(|(name, v)| {
+                 let v = v.as_table().unwrap_or_else(|| panic!("`derive.{key}.\"{name}\"` is required to be a table!"));
+                 (name.clone(), DerivePolicy::parse(v, &format!("derive.{key}.\"{name}\"")))
+             })
+             .collect()
+        };
+
+        DeriveConfig {
+            default: DerivePolicy::parse(table, "derive"),
+            modules: policies("modules"),
+            items: policies("items"),
+        }
+    }
+
+    /// The derive policies of the module `name`.
+    /// The policies of parent modules come before the policy of the module itself, so that it can override them.
+    pub fn for_module(&self, name: &str) -> ModuleDerives {
+        let mut policies = vec![self.default.clone()];
+        let mut prefix = String::new();
+        for segment in name.split("::") {
+            if !prefix.is_empty() { prefix.push_str("::"); }
+            prefix.push_str(segment);
+            policies.extend(self.modules.get(&prefix).cloned());
+        }
+
+        let items = self.items.iter()
+                              .filter_map(|(path, p)| {
+                                  let (module, item) = path.rsplit_once("::")?;
+                                  (module == name).then(|| (item.to_string(), p.clone()))
+                              })
+                              .collect();
+
+        ModuleDerives { policies, items }
+    }
+}
+
+impl DerivePolicy {
+    fn parse(table: &toml::Table, key: &str) -> DerivePolicy {
+        let traits = |k: &str| -> Vec<String> {
+            let traits: Vec<String> = table.get(k)
+                 .map(|v| v.clone().try_into().unwrap_or_else(|_| panic!("`{key}.{k}` is required to be an array of strings!")))
+                 .unwrap_or_default();
+
+            // normalizes paths like `serde :: Serialize`, so that they compare equal to the paths in the code.
+            traits.iter().map(|t| {
+                let path: syn::Path = syn::parse_str(t).unwrap_or_else(|_| panic!("`{t}` in `{key}.{k}` is not a trait path!"));
+                path.to_token_stream().to_string().replace(' ', "")
+            }).collect()
+        };
+
+        DerivePolicy { add: traits("add"), remove: traits("remove") }
+    }
+}
@@ -0,0 +1,145 @@
+use crate::prelude::*;
+use crate::source_map::{SourceMap, SourceLoc};
+
+use std::fmt::Display;
+use std::path::{Path, PathBuf};
+use std::result::Result;
+
+/// Collects the errors and warnings found in the specr code.
+///
+/// Errors are not fatal immediately, so that all errors of all modules can be reported at once.
+/// Call `abort_if_errors` at the points where transpilation can no longer continue.
+#[derive(Default)]
+pub struct Diagnostics {
+    /// the input directory, the Markdown files in `SourceLoc`s are relative to it.
+    /// Empty, if the code doesn't come from Markdown files.
+    input: PathBuf,
+    errors: Vec<Diagnostic>,
+}
+
+struct Diagnostic {
+    level: Level,
+    msg: String,
+    loc: Location,
+    /// the column within `loc` (1-based).
+    column: usize,
+    /// the span of the error, if it is located in the code.
+    span: Option<Span>,
+}
+
+enum Location {
+    /// a line of the Markdown code.
+    Code(SourceLoc),
+    /// a whole file, like a Markdown file which cannot be read.
+    File(PathBuf),
+    /// code generated by the transpiler, which has no location in the Markdown code.
+    Generated,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Level {
+    Error,
+    Warning,
+}
+
+impl Diagnostics {
+    pub fn new(config: &Config) -> Diagnostics {
+        Diagnostics {
+            input: config.input_path(),
+            errors: Vec::new(),
+        }
+    }
+
+    /// reports an error at `span`, which is mapped back to the Markdown code using `source_map`.
+    pub fn error(&mut self, source_map: &SourceMap, span: Span, msg: impl Display) {
+        self.push(Level::Error, source_map, span, msg);
+    }
+
+    /// reports a warning at `span`, warnings don't abort the transpilation.
+    pub fn warning(&mut self, source_map: &SourceMap, span: Span, msg: impl Display) {
+        self.push(Level::Warning, source_map, span, msg);
+    }
+
+    fn push(&mut self, level: Level, source_map: &SourceMap, span: Span, msg: impl Display) {
+        let loc = source_map.lookup_span(span).map_or(Location::Generated, Location::Code);
+        // proc_macro2 columns are 0-based.
+        let column = span.start().column + 1;
+        self.errors.push(Diagnostic { level, msg: msg.to_string(), loc, column, span: Some(span) });
+    }
+
+    /// reports an error at the beginning of `loc`.
+    pub fn error_at(&mut self, loc: SourceLoc, msg: impl Display) {
+        self.errors.push(Diagnostic { level: Level::Error, msg: msg.to_string(), loc: Location::Code(loc), column: 1, span: None });
+    }
+
+    /// reports an error concerning the whole file `file`.
+    pub fn error_in(&mut self, file: &Path, msg: impl Display) {
+        self.errors.push(Diagnostic { level: Level::Error, msg: msg.to_string(), loc: Location::File(file.to_path_buf()), column: 1, span: None });
+    }
+
+    /// reports a warning concerning the whole file `file`, like the config file.
+    pub fn warning_in(&mut self, file: &Path, msg: impl Display) {
+        self.errors.push(Diagnostic { level: Level::Warning, msg: msg.to_string(), loc: Location::File(file.to_path_buf()), column: 1, span: None });
+    }
+
+    /// reports all errors contained in `e`.
+    pub fn syn_error(&mut self, source_map: &SourceMap, e: syn::Error) {
+        for e in e {
+            self.error(source_map, e.span(), e);
+        }
+    }
+
+    /// moves all errors of `other` into `self`.
+    pub fn append(&mut self, mut other: Diagnostics) {
+        self.errors.append(&mut other.errors);
+    }
+
+    /// the messages of all errors and warnings.
+    #[cfg(test)]
+    pub(crate) fn messages(&self) -> Vec<&str> {
+        self.errors.iter().map(|e| &*e.msg).collect()
+    }
+
+    pub fn has_errors(&self) -> bool {
+        self.errors.iter().any(|e| e.level == Level::Error)
+    }
+
+    /// combines all errors into one `syn::Error`, which can be emitted by a proc macro.
+    /// Warnings are dropped, as proc macros cannot emit them.
+    pub fn into_syn_error(self) -> Option<syn::Error> {
+        self.errors.into_iter()
+                   .filter(|e| e.level == Level::Error)
+                   .map(|e| syn::Error::new(e.span.unwrap_or_else(Span::call_site), e.msg))
+                   .reduce(|mut a, b| { a.combine(b); a })
+    }
+
+    /// prints all errors and warnings, and returns `Err(Aborted)` if there are errors.
+    pub fn abort_if_errors(&mut self) -> Result<(), Aborted> {
+        let n = self.errors.iter().filter(|e| e.level == Level::Error).count();
+
+        for e in &self.errors {
+            match e.level {
+                Level::Error => eprintln!("error: {}", e.msg),
+                Level::Warning => eprintln!("warning: {}", e.msg),
+            }
+            match &e.loc {
+                Location::Code(loc) => eprintln!("  --> {}:{}:{}", self.input.join(&loc.file).display(), loc.line, e.column),
+                Location::File(file) => eprintln!("  --> {}", file.display()),
+                Location::Generated => eprintln!("  --> <generated code>"),
+            }
+            eprintln!();
+        }
+
+        self.errors.clear();
+        if n == 0 { return Ok(()); }
+
+        let s = if n == 1 { "" } else { "s" };
+        eprintln!("aborting due to {n} error{s}");
+
+        Err(Aborted)
+    }
+}
+
+/// Transpilation was aborted, as errors were reported.
+#[derive(Debug)]
+pub struct Aborted;
@@ -0,0 +1,222 @@
+use crate::prelude::*;
+use crate::source_map::SourceMap;
+use crate::argmatch::is_specr_attr;
+
+/// Generates `Display` impls from `#[specr::display("...")]` attributes on structs and enum variants.
+///
+/// The attribute takes a format string like `format!`, whose arguments are the fields by name,
+/// or by index for tuple structs and variants:
+///
+/// enum Expr {
+///     #[specr::display("{lhs} + {rhs}")]
+///     Add { lhs: Expr, rhs: Expr },
+///     #[specr::display("-{0}")]
+///     Neg(Expr),
+/// }
+///
+/// becomes
+///
+/// impl std::fmt::Display for Expr {
+///     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+///         match self {
+///             Expr::Add { lhs, rhs, .. } => write!(f, "{lhs} + {rhs}"),
+///             Expr::Neg(_0) => write!(f, "-{_0}"),
+///         }
+///     }
+/// }
+///
+/// If a variant of an enum has the attribute, all its variants need to have it.
+pub fn display(mut mods: Vec<Module>, diag: &mut Diagnostics) -> Vec<Module> {
+    for m in mods.iter_mut() {
+        let mut impls = Vec::new();
+        for item in m.ast.items.iter_mut() {
+            let imp = match item {
+                Item::Struct(s) => display_struct(s, &m.source_map, diag),
+                Item::Enum(e) => display_enum(e, &m.source_map, diag),
+                _ => None,
+            };
+            impls.extend(imp);
+        }
+        m.ast.items.extend(impls);
+    }
+
+    mods
+}
+
+fn display_struct(s: &mut ItemStruct, source_map: &SourceMap, diag: &mut Diagnostics) -> Option<Item> {
+    let fmt = take_attr(&mut s.attrs, source_map, diag)?;
+    let (fmt, used) = parse_fmt(&fmt, &s.fields, source_map, diag)?;
+
+    // fields are accessed by `self.field`, so that typerec can unwrap `#[specr::indirection]` fields.
+    let bindings = used.iter().map(|(binding, member)| quote! { let #binding = &self.#member; });
+    let body = quote! {
+        #(#bindings)*
+        write!(f, #fmt)
+    };
+
+    Some(display_impl(&s.ident, &s.generics, body))
+}
+
+fn display_enum(e: &mut ItemEnum, source_map: &SourceMap, diag: &mut Diagnostics) -> Option<Item> {
+    if let Some(i) = e.attrs.iter().position(|a| is_specr_attr(a, "display")) {
+        let attr = e.attrs.remove(i);
+        diag.error(source_map, attr.meta.span(), "`#[specr::display]` goes on the variants of an enum");
+    }
+
+    let mut fmts = Vec::new();
+    for v in e.variants.iter_mut() {
+        fmts.push(take_attr(&mut v.attrs, source_map, diag));
+    }
+    if fmts.iter().all(Option::is_none) { return None; }
+
+    let ident = &e.ident;
+    let mut arms = Vec::new();
+    for (v, fmt) in e.variants.iter().zip(fmts) {
+        let Some(fmt) = fmt else {
+            diag.error(source_map, v.ident.span(), format!("missing `#[specr::display]`, as other variants of `{ident}` have one"));
+            continue;
+        };
+        let Some((fmt, used)) = parse_fmt(&fmt, &v.fields, source_map, diag) else { continue };
+
+        let var = &v.ident;
+        let pat = match &v.fields {
+            Fields::Named(_) => {
+                // named fields are bound by their name.
+                let fields = used.iter().map(|(binding, _)| binding);
+                quote! { #ident::#var { #(#fields,)* .. } }
+            },
+            Fields::Unnamed(fields) => {
+                let bindings = (0..fields.unnamed.len()).map(|i| match used.iter().find(|(_, m)| *m == Member::Unnamed(i.into())) {
+                    Some((binding, _)) => quote! { #binding },
+                    None => quote! { _ },
+                });
+                quote! { #ident::#var(#(#bindings),*) }
+            },
+            Fields::Unit => quote! { #ident::#var },
+        };
+        arms.push(quote! { #pat => write!(f, #fmt), });
+    }
+
+    let body = match arms.is_empty() {
+        true => quote! { match *self {} },
+        false => quote! { match self { #(#arms)* } },
+    };
+
+    Some(display_impl(ident, &e.generics, body))
+}
+
+fn display_impl(ident: &Ident, generics: &Generics, body: TokenStream) -> Item {
+    // type parameters need to be `Display`, as they might be printed.
+    let mut generics = generics.clone();
+    for p in generics.type_params_mut() {
+        p.bounds.push(parse_quote!(std::fmt::Display));
+    }
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+
+    parse_quote! {
+        impl #impl_generics std::fmt::Display for #ident #ty_generics #where_clause {
+            fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+                #body
+            }
+        }
+    }
+}
+
+// removes `#[specr::display("...")]` from `attrs`, and returns its format string.
+fn take_attr(attrs: &mut Vec<Attribute>, source_map: &SourceMap, diag: &mut Diagnostics) -> Option<LitStr> {
+    let i = attrs.iter().position(|a| is_specr_attr(a, "display"))?;
+    let attr = attrs.remove(i);
+
+    match attr.parse_args::<LitStr>() {
+        Ok(fmt) => Some(fmt),
+        Err(_) => {
+            diag.error(source_map, attr.meta.span(), "expected `#[specr::display(\"<format string>\")]`");
+            None
+        },
+    }
+}
+
+// checks that the arguments of the format string `lit` are fields of `fields`.
+// Returns the format string with `{0}` renamed to `{_0}`, and the bindings of the used fields.
+fn parse_fmt(lit: &LitStr, fields: &Fields, source_map: &SourceMap, diag: &mut Diagnostics) -> Option<(LitStr, Vec<(Ident, Member)>)> {
+    let s = lit.value();
+    let mut out = String::new();
+    let mut used: Vec<(Ident, Member)> = Vec::new();
+
+    let mut chars = s.chars().peekable();
+    while let Some(c) = chars.next() {
+        out.push(c);
+        if c == '}' {
+            // `}}` is an escaped `}`.
+            if chars.peek() == Some(&'}') { out.push(chars.next().unwrap()); }
+            continue;
+        }
+        if c != '{' { continue; }
+        if chars.peek() == Some(&'{') {
+            out.push(chars.next().unwrap());
+            continue;
+        }
+
+        let mut arg = String::new();
+        while let Some(c) = chars.peek().filter(|c| **c != '}' && **c != ':') {
+            arg.push(*c);
+            chars.next();
+        }
+        let arg = arg.trim();
+
+        let member = match arg.parse::<usize>() {
+            Ok(i) if i < fields.len() && matches!(fields, Fields::Unnamed(_)) => Member::Unnamed(i.into()),
+            _ if fields.iter().any(|f| f.ident.as_ref().map_or(false, |id| id == arg)) => Member::Named(format_ident!("{arg}")),
+            _ => {
+                let msg = match arg {
+                    "" => "`{}` is not supported, name the printed field like `{field}` or `{0}`".to_string(),
+                    _ => format!("`{{{arg}}}` is no field"),
+                };
+                diag.error(source_map, lit.span(), msg);
+                return None;
+            },
+        };
+        let binding = match &member {
+            Member::Named(id) => id.clone(),
+            Member::Unnamed(i) => format_ident!("_{}", i.index),
+        };
+        out.push_str(&binding.to_string());
+        if !used.iter().any(|(_, m)| *m == member) {
+            used.push((binding, member));
+        }
+    }
+
+    Some((LitStr::new(&out, lit.span()), used))
+}
+
+#[test]
+fn test_display_errors() {
+    let m = Module::new("lang", parse_quote! {
+        #[specr::display("{x}")]
+        pub enum A {
+            #[specr::display("{}")]
+            X(Int),
+            #[specr::display("{1}")]
+            Y(Int),
+            #[specr::display("{y}")]
+            Z { x: Int },
+            W,
+        }
+
+        #[specr::display(x)]
+        pub struct B { x: Int }
+    });
+
+    let mut diag = Diagnostics::default();
+    let mods = display(vec![m], &mut diag);
+
+    assert_eq!(diag.messages(), [
+        "`#[specr::display]` goes on the variants of an enum",
+        "`{}` is not supported, name the printed field like `{field}` or `{0}`",
+        "`{1}` is no field",
+        "`{y}` is no field",
+        "missing `#[specr::display]`, as other variants of `A` have one",
+        "expected `#[specr::display(\"<format string>\")]`",
+    ]);
+    assert!(!mods[0].ast.to_token_stream().to_string().contains("display"));
+}
@@ -0,0 +1,309 @@
+use crate::prelude::*;
+use crate::source_map::SourceMap;
+use crate::argmatch::is_specr_attr;
+use crate::resolve::{Resolver, Def, ItemPath};
+use crate::source::TEST_MOD;
+
+/// Turns functions with a `#[specr::gc_root]` attribute into safepoints of the garbage collector.
+///
+/// After the body of such a function ran, every object which is not reachable from its roots is collected.
+/// The roots are the receiver (unless it is taken by value), the arguments of reference type, and the return value.
+/// The roots are registered by a generated function, which the driver can also call to collect at other points:
+///
+/// #[specr::gc_root]
+/// fn step(&mut self, prog: &Program) -> NdResult { <body> }
+///
+/// becomes
+///
+/// fn step(&mut self, prog: &Program) -> NdResult {
+///     let __specr_out = (|| -> NdResult { <body> })();
+///     Self::step_gc_roots(&*self, &*prog, &__specr_out);
+///     __specr_out
+/// }
+///
+/// impl Machine {
+///     /// Collects every object which is not reachable from the roots of `step`.
+///     pub fn step_gc_roots(&self, prog: &Program, __specr_out: &NdResult) {
+///         libspecr::hidden::mark_and_sweep_roots(&[&*self, &*prog, __specr_out]);
+///     }
+/// }
+///
+/// The body is wrapped in a closure, so that `return` and `?` within it still work.
+/// `&str` arguments are no roots, as they contain no objects. Other unsized arguments like `&[T]` or `&dyn Trait` are rejected,
+/// as they cannot be roots. Calls of such functions from specr code are rejected too, as the values of the caller would not be roots.
+pub fn gc_root(mut mods: Vec<Module>, diag: &mut Diagnostics) -> Vec<Module> {
+    let resolver = Resolver::new(&mods);
+    let roots = collect_gc_roots(&mods, &resolver);
+    if roots.is_empty() { return mods; }
+
+    for m in &mods {
+        let mut checker = CallChecker { module: &m.name, resolver: &resolver, roots: &roots, self_ty: None, source_map: &m.source_map, diag };
+        checker.visit_file(&m.ast);
+    }
+
+    for m in mods.iter_mut() {
+        let mut generated: Vec<Item> = Vec::new();
+        for item in m.ast.items.iter_mut() {
+            match item {
+                Item::Fn(f) => {
+                    let Some(register) = safepoint(&mut f.attrs, &f.sig, &mut f.block, None, &m.source_map, diag) else { continue };
+                    let register = register_fn(&f.vis, &f.sig, None, &register);
+                    generated.push(parse_quote!(#register));
+                },
+                Item::Impl(ii) => {
+                    let mut methods: Vec<ImplItem> = Vec::new();
+                    for x in ii.items.iter_mut() {
+                        let ImplItem::Fn(iim) = x else { continue };
+                        let Some(register) = safepoint(&mut iim.attrs, &iim.sig, &mut iim.block, Some(&ii.self_ty), &m.source_map, diag) else { continue };
+                        // methods of trait impls inherit the visibility of the trait, so their register function is public.
+                        let vis = match ii.trait_ {
+                            Some(_) => parse_quote!(pub),
+                            None => iim.vis.clone(),
+                        };
+                        let register = register_fn(&vis, &iim.sig, Some(&ii.self_ty), &register);
+                        methods.push(parse_quote!(#register));
+                    }
+                    if methods.is_empty() { continue; }
+
+                    // trait impls cannot have additional methods, so the register functions get an impl of their own.
+                    let (impl_generics, _, where_clause) = ii.generics.split_for_impl();
+                    let self_ty = &ii.self_ty;
+                    generated.push(parse_quote! {
+                        impl #impl_generics #self_ty #where_clause {
+                            #(#methods)*
+                        }
+                    });
+                },
+                _ => {},
+            }
+        }
+        m.ast.items.extend(generated);
+    }
+
+    mods
+}
+
+/// A function with `#[specr::gc_root]`.
+struct GcRoot {
+    /// the type of the impl block of a method.
+    owner: Option<Def>,
+    path: ItemPath,
+    /// the number of arguments, without the receiver.
+    arity: usize,
+}
+
+// finds all functions with a `#[specr::gc_root]` attribute.
+fn collect_gc_roots(mods: &[Module], resolver: &Resolver) -> Vec<GcRoot> {
+    let mut roots = Vec::new();
+    for m in mods {
+        let mut add = |owner: Option<Def>, sig: &Signature| {
+            let path = ItemPath { module: m.name.clone(), name: sig.ident.to_string() };
+            let arity = sig.inputs.iter().filter(|a| matches!(a, FnArg::Typed(_))).count();
+            roots.push(GcRoot { owner, path, arity });
+        };
+
+        for item in &m.ast.items {
+            match item {
+                Item::Fn(f) if f.attrs.iter().any(|a| is_specr_attr(a, "gc_root")) => add(None, &f.sig),
+                Item::Impl(ii) => {
+                    for x in &ii.items {
+                        let ImplItem::Fn(iim) = x else { continue };
+                        if !iim.attrs.iter().any(|a| is_specr_attr(a, "gc_root")) { continue; }
+                        add(resolver.resolve_type(&m.name, &ii.self_ty), &iim.sig);
+                    }
+                },
+                _ => {},
+            }
+        }
+    }
+
+    roots
+}
+
+// reports calls of `#[specr::gc_root]` functions.
+struct CallChecker<'a> {
+    module: &'a str,
+    resolver: &'a Resolver,
+    roots: &'a [GcRoot],
+    /// the type of the current impl block.
+    self_ty: Option<Def>,
+    source_map: &'a SourceMap,
+    diag: &'a mut Diagnostics,
+}
+
+impl CallChecker<'_> {
+    fn error(&mut self, name: &str, span: Span) {
+        self.diag.error(self.source_map, span, format!("`{name}` is a `#[specr::gc_root]` function, so it cannot be called from specr code, as the values of its callers would not be roots"));
+    }
+}
+
+impl<'ast> Visit<'ast> for CallChecker<'_> {
+    // tests drive the specr code, so they may call `#[specr::gc_root]` functions.
+    fn visit_item_mod(&mut self, m: &'ast ItemMod) {
+        if m.ident != TEST_MOD {
+            syn::visit::visit_item_mod(self, m);
+        }
+    }
+
+    fn visit_item_impl(&mut self, ii: &'ast ItemImpl) {
+        let outer = std::mem::replace(&mut self.self_ty, self.resolver.resolve_type(self.module, &ii.self_ty));
+        syn::visit::visit_item_impl(self, ii);
+        self.self_ty = outer;
+    }
+
+    fn visit_expr_call(&mut self, c: &'ast ExprCall) {
+        syn::visit::visit_expr_call(self, c);
+
+        let Expr::Path(p) = &*c.func else { return };
+        if p.qself.is_some() { return; }
+
+        // a free function like `reset(..)`.
+        if let Ok(Def::Item(path)) = self.resolver.resolve(self.module, self.self_ty.as_ref(), &p.path) {
+            if self.roots.iter().any(|r| r.owner.is_none() && r.path == path) {
+                self.error(&path.name, p.span());
+            }
+            return;
+        }
+
+        // a method like `Machine::step(..)` or `Self::step(..)`.
+        let segments: Vec<String> = p.path.segments.iter().map(|s| s.ident.to_string()).collect();
+        let Some((name, owner)) = segments.split_last() else { return };
+        if p.path.leading_colon.is_some() || owner.is_empty() { return; }
+        let Ok(owner) = self.resolver.resolve_segments(self.module, self.self_ty.as_ref(), owner) else { return };
+        if self.roots.iter().any(|r| r.owner.as_ref() == Some(&owner) && r.path.name == *name) {
+            self.error(name, p.span());
+        }
+    }
+
+    fn visit_expr_method_call(&mut self, mc: &'ast ExprMethodCall) {
+        syn::visit::visit_expr_method_call(self, mc);
+
+        // the type of the receiver is not known, so every method with this name and number of arguments is considered.
+        let name = mc.method.to_string();
+        if self.roots.iter().any(|r| r.owner.is_some() && r.path.name == name && r.arity == mc.args.len()) {
+            self.error(&name, mc.method.span());
+        }
+    }
+}
+
+// adds the safepoint to the body of a function, if it has a `#[specr::gc_root]` attribute.
+// `self_ty` is the type of the impl block of a method.
+// Returns the roots of the function, as (name, type) without the return value.
+fn safepoint(attrs: &mut Vec<Attribute>, sig: &Signature, block: &mut Block, self_ty: Option<&Type>, source_map: &SourceMap, diag: &mut Diagnostics) -> Option<Vec<(TokenStream, Type)>> {
+    let attr_idx = attrs.iter().position(|a| is_specr_attr(a, "gc_root"))?;
+    let attr = attrs.remove(attr_idx);
+
+    if !matches!(attr.meta, Meta::Path(_)) {
+        diag.error(source_map, attr.meta.span(), "expected `#[specr::gc_root]`");
+        return None;
+    }
+    if let Some(c) = &sig.constness {
+        diag.error(source_map, c.span, "`#[specr::gc_root]` cannot be used on `const` functions");
+        return None;
+    }
+
+    let mut roots: Vec<(TokenStream, Type)> = Vec::new();
+    for arg in &sig.inputs {
+        match arg {
+            FnArg::Receiver(r) if r.reference.is_some() => roots.push((quote! { self }, parse_quote!(Self))),
+            // the receiver was moved into the body.
+            FnArg::Receiver(_) => {},
+            FnArg::Typed(pt) => {
+                let (Pat::Ident(pi), Type::Reference(r)) = (&*pt.pat, &*pt.ty) else { continue };
+                match &*r.elem {
+                    // strings contain no objects.
+                    Type::Path(p) if p.path.is_ident("str") => continue,
+                    Type::Slice(_) | Type::TraitObject(_) => {
+                        diag.error(source_map, pt.ty.span(), "the arguments of a `#[specr::gc_root]` function are roots, so they cannot be unsized; use a `List` instead of a slice, or a sized type instead of `dyn`");
+                        return None;
+                    },
+                    _ => {},
+                }
+                roots.push((pi.ident.to_token_stream(), (*r.elem).clone()));
+            },
+        }
+    }
+
+    let ret = match &sig.output {
+        ReturnType::Default => quote! { () },
+        ReturnType::Type(_, ty) => ty.to_token_stream(),
+    };
+    let stmts = &block.stmts;
+    let register = format_ident!("{}_gc_roots", sig.ident);
+    let register = match self_ty {
+        Some(_) => quote! { Self::#register },
+        None => quote! { #register },
+    };
+    let args = roots.iter().map(|(ident, _)| ident);
+
+    *block = parse_quote! {{
+        let __specr_out = (|| -> #ret { #(#stmts)* })();
+        #register(#(&*#args,)* &__specr_out);
+        __specr_out
+    }};
+
+    Some(roots)
+}
+
+// generates the function which registers `roots` and collects everything not reachable from them.
+fn register_fn(vis: &Visibility, sig: &Signature, self_ty: Option<&Type>, roots: &[(TokenStream, Type)]) -> TokenStream {
+    let ident = format_ident!("{}_gc_roots", sig.ident);
+    let (generics, _, where_clause) = sig.generics.split_for_impl();
+    let params = roots.iter().map(|(name, ty)| match self_ty {
+        Some(_) if name.to_string() == "self" => quote! { &self },
+        _ => quote! { #name: &#ty },
+    });
+    let ret = match &sig.output {
+        ReturnType::Default => quote! { () },
+        ReturnType::Type(_, ty) => ty.to_token_stream(),
+    };
+    let args = roots.iter().map(|(name, _)| name);
+    let doc = format!(" Collects every object which is not reachable from the roots of `{}`.", sig.ident);
+
+    quote! {
+        #[doc = #doc]
+        #vis fn #ident #generics (#(#params,)* __specr_out: &#ret) #where_clause {
+            libspecr::hidden::mark_and_sweep_roots(&[#(&*#args,)* __specr_out]);
+        }
+    }
+}
+
+#[test]
+fn test_gc_root_errors() {
+    let m = Module::new("lang", parse_quote! {
+        pub struct Machine { pc: u32 }
+
+        impl Machine {
+            #[specr::gc_root]
+            pub fn step(&mut self, n: u32) {
+                self.pc += n;
+            }
+
+            pub fn run(&mut self) {
+                self.step(1);
+                Self::step(self, 2);
+                Machine::step(self, 3);
+                reset(self);
+                self.step_by(1, 2);
+            }
+        }
+
+        #[specr::gc_root]
+        pub fn reset(m: &mut Machine) {
+            m.pc = 0;
+        }
+
+        #[specr::gc_root]
+        pub fn sum(l: &[u32]) -> u32 { 0 }
+
+        #[specr::gc_root]
+        pub fn log(m: &Machine, msg: &str) {}
+    });
+
+    let mut diag = Diagnostics::default();
+    gc_root(vec![m], &mut diag);
+
+    let call = |f: &str| format!("`{f}` is a `#[specr::gc_root]` function, so it cannot be called from specr code, as the values of its callers would not be roots");
+    let unsized_arg = "the arguments of a `#[specr::gc_root]` function are roots, so they cannot be unsized; use a `List` instead of a slice, or a sized type instead of `dyn`".to_string();
+    assert_eq!(diag.messages(), [call("step"), call("step"), call("step"), call("reset"), unsized_arg]);
+}
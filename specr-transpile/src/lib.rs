@@ -0,0 +1,86 @@
+//! The passes of specr-transpile, which convert specr lang code to Rust code.
+//!
+//! `transpile` applies all passes to a list of modules.
+//! The `specr-transpile` binary uses it to generate a crate from Markdown files,
+//! and the `specr!` macro of `specr-macro` uses it on inline code.
+
+// TODO consistent module naming scheme for module and entry function.
+mod argmatch;
+mod merge_impls;
+mod diagnostics;
+mod typerec;
+mod resolve;
+mod auto_derive;
+mod auto_obj_bound;
+mod index;
+mod gc_root;
+mod lint;
+mod visit;
+mod display;
+mod config;
+
+// used by the `specr-transpile` binary and the golden tests.
+pub mod source;
+pub mod source_map;
+pub mod pipeline;
+pub mod book;
+
+pub mod prelude {
+    pub use crate::source::Module;
+    pub use crate::config::Config;
+    pub use crate::diagnostics::{Diagnostics, Aborted};
+
+    pub use std::collections::HashSet;
+    pub use quote::{quote, format_ident, ToTokens};
+    pub use syn::*;
+    pub use syn::visit_mut::*;
+    pub use syn::visit::*;
+    pub use proc_macro2::{TokenStream, TokenTree, Span};
+    pub use syn::punctuated::Punctuated;
+    pub use syn::spanned::Spanned;
+}
+use prelude::*;
+use std::result::Result;
+
+/// The modules of the generated crate, with all passes applied.
+pub struct GeneratedCrate {
+    /// in the same order as the modules given to `transpile`.
+    /// Each module declares its nested modules at its end.
+    pub modules: Vec<Module>,
+
+    /// the warnings found while transpiling.
+    pub warnings: Diagnostics,
+}
+
+/// Applies all passes to `mods`.
+///
+/// The modules are transpiled together, as passes like `typerec` need to see the whole crate.
+/// If any errors are found, they are returned instead, together with the warnings.
+pub fn transpile(mods: Vec<Module>) -> Result<GeneratedCrate, Diagnostics> {
+    transpile_with(mods, &[], |_, _| {})
+}
+
+/// Like `transpile`, but also runs the optional passes in `enabled`, and calls `after_pass` with the modules after each pass.
+pub fn transpile_with(mods: Vec<Module>, enabled: &[&str], after_pass: impl FnMut(&pipeline::Pass, &[Module])) -> Result<GeneratedCrate, Diagnostics> {
+    let mut diag = Diagnostics::default();
+    let Ok(mods) = pipeline::run(mods, enabled, &mut diag, after_pass) else { return Err(diag) };
+
+    // the (parent, ident) of each nested module.
+    let children: Vec<(String, Ident)> = mods.iter()
+                                             .filter_map(|m| Some((m.parent()?.to_string(), format_ident!("{}", m.ident()))))
+                                             .collect();
+
+    let modules = mods.into_iter().map(|mut m| {
+        // declare the nested modules at the end, so that they can use the macros of their parent.
+        for (_, ident) in children.iter().filter(|(p, _)| *p == m.name) {
+            m.ast.items.push(parse_quote! {
+                #[allow(unused_imports)]
+                #[macro_use] pub mod #ident;
+            });
+        }
+
+        m
+    }).collect();
+
+    Ok(GeneratedCrate { modules, warnings: diag })
+}
@@ -0,0 +1,277 @@
+use crate::prelude::*;
+use crate::source_map::SourceMap;
+use crate::argmatch::{self, is_specr_attr};
+use crate::typerec::is_compound_assign;
+
+/// The lints, with the message they report.
+pub static LINTS: &[(&str, &str)] = &[
+    ("index_mut", "`[]` gives a copy of the element, so mutating it doesn't change the indexed value; use `set` or `mutate_at` instead"),
+    ("push_in_loop", "each `push` clones the whole list; build the list with `collect()` instead of pushing in a loop"),
+    ("non_copy_field", "objects need to be `Copy`, use `List`, `Set`, `Map` or `#[specr::indirection]` instead of this type"),
+    ("reference_field", "garbage collected values cannot contain references, store the value itself instead"),
+];
+
+// types of the standard library which are not `Copy`.
+// `String` is missing, as it refers to the `Copy` string of libspecr, unless it is written as `std::string::String`.
+static NON_COPY_TYPES: &[&str] = &[
+    "Vec", "VecDeque", "Box", "Rc", "Arc", "HashMap", "HashSet", "BTreeMap", "BTreeSet", "Cell", "RefCell",
+];
+
+/// Reports violations of the rules of specr lang as warnings, before the other passes transform the code.
+///
+/// Lints are allowed by `#[specr::allow(<lint>, ...)]` on items, methods, fields, statements and expressions.
+pub fn lint(mut mods: Vec<Module>, diag: &mut Diagnostics) -> Vec<Module> {
+    for m in mods.iter_mut() {
+        // this way, argmatch functions are linted and can be printed by `--dump-after=lint`.
+        argmatch::add_bodies(&mut m.ast);
+
+        let mut linter = Linter { source_map: &m.source_map, diag, allowed: Vec::new(), loops: 0 };
+        linter.visit_file_mut(&mut m.ast);
+    }
+
+    mods
+}
+
+struct Linter<'a> {
+    source_map: &'a SourceMap,
+    diag: &'a mut Diagnostics,
+
+    /// the lints allowed by the attributes around the current node.
+    allowed: Vec<String>,
+
+    /// the number of loops around the current node.
+    loops: usize,
+}
+
+impl Linter<'_> {
+    fn warn(&mut self, lint: &str, span: Span) {
+        if self.allowed.iter().any(|l| l == lint) { return; }
+        let (_, msg) = LINTS.iter().find(|(name, _)| *name == lint).unwrap();
+        self.diag.warning(self.source_map, span, format!("{msg} (lint `{lint}`)"));
+    }
+
+    // removes the `#[specr::allow(...)]` attributes from `attrs`, and allows their lints.
+    // Returns how many lints were allowed, so that they can be removed after visiting the node.
+    fn allow(&mut self, attrs: &mut Vec<Attribute>) -> usize {
+        let mut n = 0;
+        let mut i = 0;
+        while i < attrs.len() {
+            if !is_specr_attr(&attrs[i], "allow") { i += 1; continue; }
+            let attr = attrs.remove(i);
+
+            let Ok(lints) = attr.parse_args_with(Punctuated::<Ident, Token![,]>::parse_terminated) else {
+                self.diag.error(self.source_map, attr.meta.span(), "expected `#[specr::allow(<lint>, ...)]`");
+                continue;
+            };
+            for l in lints {
+                if !LINTS.iter().any(|(name, _)| l == name) {
+                    let names: Vec<&str> = LINTS.iter().map(|(name, _)| *name).collect();
+                    self.diag.warning(self.source_map, l.span(), format!("unknown lint `{l}`, the lints are: {}", names.join(", ")));
+                }
+                self.allowed.push(l.to_string());
+                n += 1;
+            }
+        }
+
+        n
+    }
+
+    fn allowed_in<T>(&mut self, node: &mut T, attrs: impl FnOnce(&mut T) -> &mut Vec<Attribute>, visit: impl FnOnce(&mut Self, &mut T)) {
+        let n = self.allow(attrs(node));
+        visit(self, node);
+        self.allowed.truncate(self.allowed.len() - n);
+    }
+
+    // checks the fields of a struct or enum.
+    fn fields(&mut self, attrs: &[Attribute], fields: &mut [&mut Field]) {
+        let is_obj = !attrs.iter().any(|a| a.path().is_ident("no_obj"));
+        for f in fields {
+            let n = self.allow(&mut f.attrs);
+            self.field_type(&f.ty, is_obj);
+            self.allowed.truncate(self.allowed.len() - n);
+        }
+    }
+
+    fn field_type(&mut self, ty: &Type, is_obj: bool) {
+        match ty {
+            Type::Reference(r) => self.warn("reference_field", r.and_token.span),
+            Type::Path(p) => {
+                let path: Vec<String> = p.path.segments.iter().map(|s| s.ident.to_string()).collect();
+                if is_obj && matches!(&path[..], [std, s, t] if (std == "std" || std == "alloc") && s == "string" && t == "String") {
+                    self.warn("non_copy_field", p.path.span());
+                }
+                for s in &p.path.segments {
+                    if is_obj && NON_COPY_TYPES.contains(&&*s.ident.to_string()) {
+                        self.warn("non_copy_field", s.ident.span());
+                    }
+                    let PathArguments::AngleBracketed(args) = &s.arguments else { continue };
+                    for arg in &args.args {
+                        let GenericArgument::Type(t) = arg else { continue };
+                        self.field_type(t, is_obj);
+                    }
+                }
+            },
+            Type::Tuple(t) => {
+                for t in &t.elems {
+                    self.field_type(t, is_obj);
+                }
+            },
+            Type::Array(a) => self.field_type(&a.elem, is_obj),
+            Type::Paren(p) => self.field_type(&p.elem, is_obj),
+            Type::Group(g) => self.field_type(&g.elem, is_obj),
+            _ => {},
+        }
+    }
+}
+
+// whether mutating `e` mutates a copy returned by `[]`, like `a[i]` or `a[i].x`.
+fn is_index_place(e: &Expr) -> bool {
+    match e {
+        Expr::Index(_) => true,
+        Expr::Field(f) => is_index_place(&f.base),
+        Expr::Paren(p) => is_index_place(&p.expr),
+        _ => false,
+    }
+}
+
+impl VisitMut for Linter<'_> {
+    fn visit_item_mut(&mut self, node: &mut Item) {
+        let n = match node {
+            Item::Struct(s) => self.allow(&mut s.attrs),
+            Item::Enum(e) => self.allow(&mut e.attrs),
+            Item::Fn(f) => self.allow(&mut f.attrs),
+            Item::Impl(ii) => self.allow(&mut ii.attrs),
+            Item::Trait(t) => self.allow(&mut t.attrs),
+            Item::Const(c) => self.allow(&mut c.attrs),
+            Item::Static(s) => self.allow(&mut s.attrs),
+            Item::Mod(m) => self.allow(&mut m.attrs),
+            _ => 0,
+        };
+
+        match node {
+            Item::Struct(s) => {
+                let mut fields: Vec<&mut Field> = s.fields.iter_mut().collect();
+                self.fields(&s.attrs, &mut fields);
+            },
+            Item::Enum(e) => {
+                let mut fields: Vec<&mut Field> = e.variants.iter_mut().flat_map(|v| v.fields.iter_mut()).collect();
+                self.fields(&e.attrs, &mut fields);
+            },
+            _ => visit_item_mut(self, node),
+        }
+
+        self.allowed.truncate(self.allowed.len() - n);
+    }
+
+    fn visit_impl_item_mut(&mut self, node: &mut ImplItem) {
+        match node {
+            ImplItem::Fn(f) => self.allowed_in(f, |f| &mut f.attrs, visit_impl_item_fn_mut),
+            _ => visit_impl_item_mut(self, node),
+        }
+    }
+
+    fn visit_trait_item_mut(&mut self, node: &mut TraitItem) {
+        match node {
+            TraitItem::Fn(f) => self.allowed_in(f, |f| &mut f.attrs, visit_trait_item_fn_mut),
+            _ => visit_trait_item_mut(self, node),
+        }
+    }
+
+    fn visit_local_mut(&mut self, node: &mut Local) {
+        self.allowed_in(node, |l| &mut l.attrs, visit_local_mut);
+    }
+
+    fn visit_expr_mut(&mut self, node: &mut Expr) {
+        match expr_attrs(node) {
+            Some(attrs) => {
+                let n = self.allow(attrs);
+                self.lint_expr(node);
+                self.allowed.truncate(self.allowed.len() - n);
+            },
+            None => self.lint_expr(node),
+        }
+    }
+}
+
+// the attributes of expressions which are commonly used as statements.
+fn expr_attrs(e: &mut Expr) -> Option<&mut Vec<Attribute>> {
+    match e {
+        Expr::Assign(x) => Some(&mut x.attrs),
+        Expr::Binary(x) => Some(&mut x.attrs),
+        Expr::Block(x) => Some(&mut x.attrs),
+        Expr::Call(x) => Some(&mut x.attrs),
+        Expr::ForLoop(x) => Some(&mut x.attrs),
+        Expr::If(x) => Some(&mut x.attrs),
+        Expr::Loop(x) => Some(&mut x.attrs),
+        Expr::Macro(x) => Some(&mut x.attrs),
+        Expr::Match(x) => Some(&mut x.attrs),
+        Expr::MethodCall(x) => Some(&mut x.attrs),
+        Expr::While(x) => Some(&mut x.attrs),
+        _ => None,
+    }
+}
+
+impl Linter<'_> {
+    fn lint_expr(&mut self, node: &mut Expr) {
+        match node {
+            Expr::Assign(a) if is_index_place(&a.left) => self.warn("index_mut", a.left.span()),
+            Expr::Binary(b) if is_compound_assign(&b.op) && is_index_place(&b.left) => self.warn("index_mut", b.left.span()),
+            Expr::Reference(r) if r.mutability.is_some() && is_index_place(&r.expr) => self.warn("index_mut", r.expr.span()),
+            Expr::MethodCall(mc) if mc.method == "push" && self.loops > 0 => self.warn("push_in_loop", mc.method.span()),
+            _ => {},
+        }
+
+        // the iterated expression of a `for` loop, or the condition of a `while` loop, are not within the loop.
+        match node {
+            Expr::ForLoop(f) => {
+                self.visit_pat_mut(&mut f.pat);
+                self.visit_expr_mut(&mut f.expr);
+                self.loops += 1;
+                self.visit_block_mut(&mut f.body);
+                self.loops -= 1;
+            },
+            Expr::While(w) => {
+                self.visit_expr_mut(&mut w.cond);
+                self.loops += 1;
+                self.visit_block_mut(&mut w.body);
+                self.loops -= 1;
+            },
+            Expr::Loop(l) => {
+                self.loops += 1;
+                self.visit_block_mut(&mut l.body);
+                self.loops -= 1;
+            },
+            _ => visit_expr_mut(self, node),
+        }
+    }
+}
+
+#[test]
+fn test_lints() {
+    let m = Module::new("lang", parse_quote! {
+        pub struct A { name: std::string::String, s: String, r: &'static u32 }
+
+        #[no_obj]
+        #[specr::allow(reference_field)]
+        pub struct B { names: Vec<String>, r: &'static u32 }
+
+        pub fn f(mut l: List<A>, n: u32) {
+            l[0] = l[1];
+            l[0].name += 1;
+            let _ = &mut l[2];
+            for i in 0..n {
+                l.push(l[i]);
+                #[specr::allow(push_in_loop)]
+                let _ = l.push(l[i]);
+            }
+            l.push(l[0]);
+        }
+    });
+
+    let mut diag = Diagnostics::default();
+    let mods = lint(vec![m], &mut diag);
+
+    let lints: Vec<&str> = diag.messages().iter().map(|m| m.rsplit('`').nth(1).unwrap()).collect();
+    assert_eq!(lints, ["non_copy_field", "reference_field", "index_mut", "index_mut", "index_mut", "push_in_loop"]);
+    assert!(!mods[0].ast.to_token_stream().to_string().contains("allow"));
+}
@@ -1,30 +1,17 @@
-// TODO consistent module naming scheme for module and entry function.
-mod argmatch;
-mod merge_impls;
-mod source;
-mod typerec;
-mod auto_derive;
-mod auto_obj_bound;
-mod index;
+mod check;
+mod cache;
+mod watch;
 
-mod config;
+use specr_transpile::{prelude, source, source_map, book};
+use specr_transpile::pipeline::Pass;
+use prelude::*;
 
 use std::fs;
 use std::path::{PathBuf, Path};
+use std::result::Result;
 
-pub mod prelude {
-    pub use crate::source::Module;
-    pub use crate::config::Config;
-
-    pub use std::collections::HashSet;
-    pub use quote::{quote, format_ident, ToTokens};
-    pub use syn::*;
-    pub use syn::visit_mut::*;
-    pub use syn::visit::*;
-    pub use proc_macro2::{TokenStream, TokenTree, Span};
-    pub use syn::punctuated::Punctuated;
-}
-use prelude::*;
+/// The directory within the generated crate, where `--dump-after` writes to.
+const DUMP_DIR: &str = "specr-dump";
 
 fn exists<T: AsRef<Path>>(t: T) -> bool {
     t.as_ref().exists()
@@ -38,6 +25,17 @@ fn mkdir<T: AsRef<Path>>(t: T) {
     }
 }
 
+// writes `content` to `path`, unless the file already has this content.
+// This way, cargo doesn't rebuild the generated crate if nothing changed.
+// `path` is added to `files`, the list of generated files.
+fn write_if_changed(path: PathBuf, content: &str, files: &mut Vec<PathBuf>) {
+    if fs::read_to_string(&path).ok().as_deref() != Some(content) {
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(&path, content).unwrap();
+    }
+    files.push(path);
+}
+
 fn main() {
     let config = Config::load();
 
@@ -46,38 +44,93 @@ fn main() {
         std::process::exit(1);
     }
 
+    if config.watch {
+        watch::watch(&config);
+    }
+
+    if run(&config).is_err() {
+        std::process::exit(1);
+    }
+}
+
+/// Transpiles the input, and checks the generated crate if `--check` is given.
+fn run(config: &Config) -> Result<(), Aborted> {
+    transpile(config)?;
+
+    if config.check && !check::check(config) {
+        return Err(Aborted);
+    }
+
+    Ok(())
+}
+
+/// Generates the output crate, unless it is already up to date.
+fn transpile(config: &Config) -> Result<(), Aborted> {
+    let hash = cache::input_hash(config);
+    // dumps are not cached, so we always transpile if they are requested.
+    if config.dump_after.is_none() && cache::is_fresh(config, hash) {
+        return Ok(());
+    }
+
     mkdir(&config.output_path());
     mkdir(&config.output_path().join("src"));
 
-    let mods = source::fetch(&config.input_path());
-    create_cargo_toml(&config);
-    create_rust_toolchain(&config);
-    create_lib(&mods, &config);
-    compile(mods, &config);
+    let mut diag = Diagnostics::new(config);
+    let mods = source::fetch(config, &mut diag);
+    diag.abort_if_errors()?;
+
+    let mut files = Vec::new();
+    create_cargo_toml(&mods, config, &mut files);
+    create_rust_toolchain(config, &mut files);
+    for (path, content) in book::render(&mods, config, &mut diag) {
+        write_if_changed(path, &content, &mut files);
+    }
+    diag.abort_if_errors()?;
+    compile(mods, config, &mut diag, &mut files)?;
+
+    cache::store(config, hash, &files);
+
+    Ok(())
 }
 
-fn create_cargo_toml(config: &Config) {
+fn create_cargo_toml(mods: &[Module], config: &Config, files: &mut Vec<PathBuf>) {
     let package_name = &config.name;
-    let toml = format!("[package]\n\
+    let package = format!("[package]\n\
                 name = \"{}\"\n\
                 version = \"0.1.0\"\n\
                 edition = \"2021\"\n\
-                \n\
-                [dependencies]\n\
-                libspecr = \"=0.1.15\"\n\
-                gccompat-derive = \"=0.1.1\"\n\
                ", package_name);
-    fs::write(config.output_path().join("Cargo.toml"), &toml).unwrap();
+
+    // features used by ```rust,cfg(feature = "x") code blocks are declared, unless the config already does.
+    let mut features = config.features.clone();
+    for f in mods.iter().flat_map(|m| &m.features) {
+        if !features.contains_key(f) {
+            features.insert(f.clone(), toml::Value::Array(Vec::new()));
+        }
+    }
+
+    let mut toml = toml::Table::new();
+    toml.insert("dependencies".to_string(), config.dependencies.clone().into());
+    if !features.is_empty() {
+        toml.insert("features".to_string(), features.into());
+    }
+
+    let toml = format!("{package}\n{toml}");
+    write_if_changed(config.output_path().join("Cargo.toml"), &toml, files);
 }
 
-fn create_rust_toolchain(config: &Config) {
+fn create_rust_toolchain(config: &Config, files: &mut Vec<PathBuf>) {
     let Some(ref channel) = config.channel else { return };
     let toml = format!("[toolchain]\nchannel = \"{channel}\"");
-    fs::write(config.output_path().join("rust-toolchain.toml"), &toml).unwrap();
+    write_if_changed(config.output_path().join("rust-toolchain.toml"), &toml, files);
 }
 
-fn create_lib(mods: &[Module], config: &Config) {
-    let mods: Vec<Ident> = mods.iter().map(|x| format_ident!("{}", x.name)).collect();
+fn create_lib(mods: &[Module], config: &Config, files: &mut Vec<PathBuf>) {
+    // nested modules are declared in their parent module, see `compile`.
+    let mods: Vec<Ident> = mods.iter()
+                               .filter(|x| x.parent().is_none())
+                               .map(|x| format_ident!("{}", x.name))
+                               .collect();
 
     let attrs = parse_str::<syn::File>(&config.attrs.join("\n")).unwrap();
 
@@ -90,25 +143,137 @@ fn create_lib(mods: &[Module], config: &Config) {
     };
     let code = parse_str::<syn::File>(&code.to_string()).unwrap();
     let code = prettyplease::unparse(&code);
-    fs::write(config.output_path().join("src").join("lib.rs"), &code).unwrap();
+    write_if_changed(config.output_path().join("src").join("lib.rs"), &code, files);
 }
 
-fn compile(mods: Vec<Module>, config: &Config) {
-    // argmatch needs to be before typerec, as argmatch generates new match blocks!
-    let mods = argmatch::argmatch(mods);
-    let mods = typerec::typerec(mods);
+fn compile(mods: Vec<Module>, config: &Config, diag: &mut Diagnostics, files: &mut Vec<PathBuf>) -> Result<(), Aborted> {
+    // writes the code of each module after the pass given by `--dump-after`.
+    let mut dumps = Vec::new();
+    let dump = |pass: &Pass, mods: &[Module]| {
+        if config.dump_after.as_deref() != Some(pass.name) { return; }
+        for m in mods {
+            let path = config.output_path().join(DUMP_DIR).join(pass.name).join(m.file());
+            dumps.push((path, prettyplease::unparse(&m.ast)));
+        }
+    };
 
-    for m in mods.into_iter() {
-        // apply all other compilation stages.
-        let ast = merge_impls::merge(m.ast);
-        let ast = auto_derive::auto_derive(ast);
-        let ast = index::index(ast);
-        let ast = auto_obj_bound::auto_obj_bound(ast);
+    let enabled: Vec<&str> = config.passes.iter().map(String::as_str).collect();
+    let krate = specr_transpile::transpile_with(mods, &enabled, dump);
+    for (path, code) in dumps {
+        write_if_changed(path, &code, files);
+    }
+
+    let krate = match krate {
+        Ok(krate) => krate,
+        Err(errors) => {
+            diag.append(errors);
+            return diag.abort_if_errors();
+        },
+    };
+    diag.append(krate.warnings);
+    diag.abort_if_errors()?;
+
+    // optional passes may generate further modules, so lib.rs is created from the transpiled modules.
+    create_lib(&krate.modules, config, files);
+
+    let mut source_map = toml::Table::new();
+
+    for m in krate.modules {
+        let filename = m.file();
+
+        for ex in m.examples {
+            let filename = format!("examples/{}.rs", ex.name);
+            let ast = compile_example(ex.ast, config);
+            let (code, item_locs) = unparse_with_source(&ast, &ex.source_map);
+            write_if_changed(config.output_path().join(&filename), &code, files);
+            source_map.insert(filename, item_locs_table(item_locs).into());
+        }
 
         // write AST back to Rust file.
-        let code = prettyplease::unparse(&ast);
-        let filename = format!("{}.rs", m.name);
-        let p: PathBuf = config.output_path().join("src").join(filename);
-        fs::write(&p, &code).unwrap();
+        let (code, item_locs) = unparse_with_source(&m.ast, &m.source_map);
+        let p: PathBuf = config.output_path().join("src").join(&filename);
+        write_if_changed(p, &code, files);
+
+        source_map.insert(format!("src/{filename}"), item_locs_table(item_locs).into());
+    }
+
+    write_if_changed(config.output_path().join(check::SOURCE_MAP_FILE), &source_map.to_string(), files);
+
+    Ok(())
+}
+
+fn item_locs_table(item_locs: Vec<(usize, source_map::SourceLoc)>) -> toml::Table {
+    item_locs.into_iter()
+             .map(|(line, loc)| (line.to_string(), loc.to_string().into()))
+             .collect()
+}
+
+/// Turns a ```rust,example code block into the code of an example of the generated crate.
+///
+/// Examples are separate crates, so `crate::` paths are replaced by the name of the generated crate.
+fn compile_example(mut ast: syn::File, config: &Config) -> syn::File {
+    struct CrateVisitor(Ident);
+
+    impl VisitMut for CrateVisitor {
+        fn visit_path_mut(&mut self, p: &mut syn::Path) {
+            if p.leading_colon.is_none() && p.segments[0].ident == "crate" {
+                p.segments[0].ident = self.0.clone();
+            }
+            visit_path_mut(self, p);
+        }
+
+        fn visit_use_tree_mut(&mut self, t: &mut UseTree) {
+            if let UseTree::Path(p) = t {
+                if p.ident == "crate" {
+                    p.ident = self.0.clone();
+                }
+            }
+        }
+    }
+
+    CrateVisitor(format_ident!("{}", config.name.replace('-', "_"))).visit_file_mut(&mut ast);
+    ast.items.insert(0, parse_quote! {
+        #[allow(unused_imports)]
+        #[macro_use] extern crate libspecr;
+    });
+
+    ast
+}
+
+/// Converts the AST back to Rust code.
+/// Each item is preceded by a `// source: <file>:<line>` comment pointing to the Markdown code it originates from.
+///
+/// Also returns the line of each item, method and statement in the generated code together with its Markdown location.
+fn unparse_with_source(ast: &syn::File, source_map: &source_map::SourceMap) -> (String, Vec<(usize, source_map::SourceLoc)>) {
+    let header = syn::File {
+        shebang: ast.shebang.clone(),
+        attrs: ast.attrs.clone(),
+        items: Vec::new(),
+    };
+    let mut code = prettyplease::unparse(&header);
+    let mut item_locs = Vec::new();
+
+    for item in &ast.items {
+        if let Some(loc) = source_map.lookup_item(item) {
+            code.push_str(&format!("// source: {loc}\n"));
+            let line = code.lines().count() + 1;
+            item_locs.push((line, loc));
+        }
+
+        let file = syn::File {
+            shebang: None,
+            attrs: Vec::new(),
+            items: vec![item.clone()],
+        };
+        let item_code = prettyplease::unparse(&file);
+
+        // methods and statements get their own locations, so that errors within large items are mapped to the right line.
+        let offset = code.lines().count();
+        for (line, loc) in source_map.lookup_nested(item, &item_code) {
+            item_locs.push((offset + line, loc));
+        }
+        code.push_str(&item_code);
     }
+
+    (code, item_locs)
 }
@@ -28,10 +28,12 @@ pub fn merge(mut ast: syn::File) -> syn::File {
     ast
 }
 
+// impls belong together if they have the same header.
+// Their `#[cfg]`s have to match as well, like those of ```rust,cfg(feature = "x") code blocks.
 fn belong_together(ii1: &ItemImpl, ii2: &ItemImpl) -> bool {
     let to_str = |ii: &ItemImpl| {
         let mut ii = ii.clone();
-        ii.attrs.clear();
+        ii.attrs.retain(|a| a.path().is_ident("cfg"));
         ii.items.clear();
 
         ii.to_token_stream()
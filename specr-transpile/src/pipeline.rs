@@ -0,0 +1,161 @@
+use crate::prelude::*;
+use crate::{lint, argmatch, typerec, merge_impls, auto_derive, index, auto_obj_bound, gc_root, visit, display};
+
+use std::result::Result;
+
+/// A transformation of the specr code.
+pub struct Pass {
+    /// the name of the pass, as used by `--dump-after=<name>`.
+    pub name: &'static str,
+
+    /// the passes which need to run before this one.
+    pub after: &'static [&'static str],
+
+    /// optional passes only run if they are enabled, like by `passes = [...]` in the config file.
+    pub optional: bool,
+
+    pub kind: PassKind,
+}
+
+pub enum PassKind {
+    /// transforms all modules at once, as it needs to see the whole crate.
+    Crate(fn(Vec<Module>, &mut Diagnostics) -> Vec<Module>),
+
+    /// transforms each module on its own.
+    /// If `examples` is set, the examples of each module are transformed as well.
+    Module { run: fn(syn::File) -> syn::File, examples: bool },
+}
+
+/// All passes. Passes without dependencies between them run in the order they are listed here.
+pub static PASSES: &[Pass] = &[
+    Pass {
+        name: "lint",
+        after: &[],
+        optional: false,
+        kind: PassKind::Crate(lint::lint),
+    },
+    Pass {
+        name: "argmatch",
+        // lint checks the code as written, before any code is generated.
+        after: &["lint"],
+        optional: false,
+        kind: PassKind::Crate(argmatch::argmatch),
+    },
+    Pass {
+        name: "display",
+        after: &["lint"],
+        optional: false,
+        kind: PassKind::Crate(display::display),
+    },
+    Pass {
+        name: "typerec",
+        // argmatch and display generate new match blocks, which need to be fixed by typerec.
+        after: &["argmatch", "display"],
+        optional: false,
+        kind: PassKind::Crate(typerec::typerec),
+    },
+    Pass {
+        name: "merge_impls",
+        // argmatch needs to see the impl blocks as written.
+        after: &["argmatch"],
+        optional: false,
+        kind: PassKind::Module { run: merge_impls::merge, examples: false },
+    },
+    Pass {
+        name: "auto_derive",
+        after: &[],
+        optional: false,
+        kind: PassKind::Crate(auto_derive::auto_derive),
+    },
+    Pass {
+        name: "index",
+        // typerec needs to see indexing expressions as written, to find out what they refer to.
+        after: &["typerec"],
+        optional: false,
+        kind: PassKind::Module { run: index::index, examples: true },
+    },
+    Pass {
+        name: "auto_obj_bound",
+        // this way, the bounds are added once per merged impl.
+        after: &["merge_impls"],
+        optional: false,
+        kind: PassKind::Module { run: auto_obj_bound::auto_obj_bound, examples: false },
+    },
+    Pass {
+        name: "gc_root",
+        // argmatch generates the bodies of argmatch functions, which may be safepoints as well.
+        after: &["argmatch"],
+        optional: false,
+        kind: PassKind::Crate(gc_root::gc_root),
+    },
+    Pass {
+        name: "visit",
+        // the visitors need to see the final types, including the `GcCow`s added by typerec.
+        after: &["typerec", "auto_derive"],
+        optional: true,
+        kind: PassKind::Crate(visit::visit),
+    },
+];
+
+/// Returns all passes in the order they run in.
+///
+/// Panics if the dependencies refer to unknown passes, or are cyclic.
+pub fn passes() -> Vec<&'static Pass> {
+    let mut out: Vec<&'static Pass> = Vec::new();
+    while out.len() < PASSES.len() {
+        let done = |name: &&str| out.iter().any(|p| p.name == *name);
+        let next = PASSES.iter()
+                         .find(|p| !done(&p.name) && p.after.iter().all(done))
+                         .unwrap_or_else(|| panic!("the dependencies of the passes are unknown or cyclic"));
+        out.push(next);
+    }
+
+    out
+}
+
+/// Looks up a pass by its name.
+pub fn pass(name: &str) -> Option<&'static Pass> {
+    PASSES.iter().find(|p| p.name == name)
+}
+
+/// Runs all passes on `mods`, optional passes only if they are contained in `enabled`.
+///
+/// `after_pass` is called with the modules after each pass, this is used by `--dump-after`.
+/// Errors and warnings are reported to `diag`. If a pass reports errors, the remaining passes are skipped.
+pub fn run(mut mods: Vec<Module>, enabled: &[&str], diag: &mut Diagnostics, mut after_pass: impl FnMut(&Pass, &[Module])) -> Result<Vec<Module>, Aborted> {
+    for p in passes() {
+        if p.optional && !enabled.contains(&p.name) { continue; }
+
+        mods = match &p.kind {
+            PassKind::Crate(f) => f(mods, diag),
+            PassKind::Module { run, examples } => {
+                for m in &mut mods {
+                    m.ast = run(std::mem::replace(&mut m.ast, parse_quote!()));
+                    if !examples { continue; }
+
+                    for ex in &mut m.examples {
+                        ex.ast = run(std::mem::replace(&mut ex.ast, parse_quote!()));
+                    }
+                }
+                mods
+            },
+        };
+        if diag.has_errors() { return Err(Aborted); }
+
+        after_pass(p, &mods);
+    }
+
+    Ok(mods)
+}
+
+#[test]
+fn test_passes_order() {
+    let passes = passes();
+    assert_eq!(passes.len(), PASSES.len());
+    for (i, p) in passes.iter().enumerate() {
+        for dep in p.after {
+            assert!(pass(dep).is_some());
+            assert!(passes[..i].iter().any(|q| q.name == *dep));
+        }
+    }
+}
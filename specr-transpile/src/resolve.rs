@@ -0,0 +1,344 @@
+use crate::prelude::*;
+
+use std::collections::HashMap;
+use std::result::Result;
+use std::fmt::{self, Display};
+
+/// Resolves the names visible in each module of the generated crate.
+///
+/// The generated crate consists of the crate root, which declares all top-level modules,
+/// and one module per `Module`, which declares its nested modules. The names visible in a module are its own items,
+/// and everything imported by its top-level `use` declarations.
+/// `use` declarations may import from other modules (also through globs and re-exports),
+/// and the variants of enums.
+///
+/// Names which are not defined in the specr code (like `Some`, or anything from `libspecr`)
+/// are not resolved.
+pub struct Resolver {
+    /// the names visible in each module.
+    scopes: HashMap<String, Scope>,
+
+    /// the variants of each enum.
+    enums: HashMap<ItemPath, Vec<String>>,
+}
+
+/// An item, identified by its module and name.
+#[derive(Clone, PartialEq, Eq, Hash, Debug, PartialOrd, Ord)]
+pub struct ItemPath {
+    pub module: String,
+    pub name: String,
+}
+
+/// What a name refers to.
+#[derive(Clone, PartialEq, Eq, Hash, Debug, PartialOrd, Ord)]
+pub enum Def {
+    /// The root of the generated crate.
+    Crate,
+    Module(String),
+    Enum(ItemPath),
+    /// A variant of an enum.
+    Variant(ItemPath, String),
+    /// Any other item, like structs, functions or traits.
+    Item(ItemPath),
+}
+
+/// Why a path could not be resolved.
+#[derive(Debug)]
+pub enum ResolveError {
+    /// The path does not refer to something defined in the specr code.
+    Unresolved,
+    /// The path is imported by multiple globs with different meanings.
+    Ambiguous(Vec<Def>),
+}
+
+type Scope = HashMap<String, Binding>;
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+struct Binding {
+    /// contains multiple defs, if multiple globs import this name.
+    defs: Vec<Def>,
+
+    /// whether this binding can be imported from other modules.
+    public: bool,
+
+    /// bindings from globs are shadowed by items and explicit imports.
+    glob: bool,
+}
+
+// A single import of a `use` declaration.
+struct Import {
+    /// the path to import from, like `crate::lang` for `use crate::lang::Expr`.
+    path: Vec<String>,
+    kind: ImportKind,
+    public: bool,
+}
+
+enum ImportKind {
+    /// `use path::name as rename`.
+    Name { name: String, rename: String },
+    /// `use path::*`.
+    Glob,
+}
+
+impl Display for ItemPath {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}::{}", self.module, self.name)
+    }
+}
+
+impl Display for Def {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Def::Crate => write!(f, "crate"),
+            Def::Module(m) => write!(f, "{m}"),
+            Def::Enum(p) | Def::Item(p) => write!(f, "{p}"),
+            Def::Variant(p, v) => write!(f, "{p}::{v}"),
+        }
+    }
+}
+
+impl Resolver {
+    pub fn new(mods: &[Module]) -> Resolver {
+        let mut enums = HashMap::new();
+        let mut items = HashMap::new();
+        let mut imports = HashMap::new();
+
+        for m in mods {
+            let mut scope = Scope::new();
+            let mut mod_imports = Vec::new();
+
+            for item in &m.ast.items {
+                if let Item::Use(u) = item {
+                    let public = is_public(&u.vis);
+                    collect_imports(&u.tree, Vec::new(), public, &mut mod_imports);
+                    continue;
+                }
+
+                let Some((ident, vis)) = item_name(item) else { continue };
+                let public = is_public(vis);
+                let path = ItemPath { module: m.name.clone(), name: ident.to_string() };
+                let def = match item {
+                    Item::Enum(e) => {
+                        let variants = e.variants.iter().map(|v| v.ident.to_string()).collect();
+                        enums.insert(path.clone(), variants);
+                        Def::Enum(path)
+                    },
+                    _ => Def::Item(path),
+                };
+                scope.insert(ident.to_string(), Binding { defs: vec![def], public, glob: false });
+            }
+
+            items.insert(m.name.clone(), scope);
+            imports.insert(m.name.clone(), mod_imports);
+        }
+
+        // nested modules are items of their parent module.
+        for m in mods {
+            let Some(parent) = m.parent() else { continue };
+            let Some(scope) = items.get_mut(parent) else { continue };
+            let def = Def::Module(m.name.clone());
+            scope.insert(m.ident().to_string(), Binding { defs: vec![def], public: true, glob: false });
+        }
+
+        let mut resolver = Resolver { scopes: items.clone(), enums };
+
+        // imports can depend on other imports, so we resolve them until nothing changes anymore.
+        loop {
+            let mut scopes = items.clone();
+            for (module, scope) in scopes.iter_mut() {
+                for import in &imports[module] {
+                    resolver.apply_import(module, import, scope);
+                }
+            }
+
+            if scopes == resolver.scopes { break; }
+            resolver.scopes = scopes;
+        }
+
+        resolver
+    }
+
+    /// Resolves `path`, as written in `module`.
+    /// `self_ty` is what `Self` refers to, if `path` is used within an `impl` block.
+    pub fn resolve(&self, module: &str, self_ty: Option<&Def>, path: &Path) -> Result<Def, ResolveError> {
+        if path.leading_colon.is_some() {
+            return Err(ResolveError::Unresolved);
+        }
+
+        let segments: Vec<String> = path.segments.iter().map(|s| s.ident.to_string()).collect();
+        self.resolve_segments(module, self_ty, &segments)
+    }
+
+    /// Resolves the path of a type, like the self type of an `impl` block.
+    pub fn resolve_type(&self, module: &str, ty: &Type) -> Option<Def> {
+        let Type::Path(p) = ty else { return None };
+        if p.qself.is_some() { return None; }
+
+        self.resolve(module, None, &p.path).ok()
+    }
+
+    /// The variants of the enum `e`.
+    pub fn variants(&self, e: &ItemPath) -> &[String] {
+        &self.enums[e]
+    }
+
+    /// Like `resolve`, for a path given as its segments.
+    pub fn resolve_segments(&self, module: &str, self_ty: Option<&Def>, segments: &[String]) -> Result<Def, ResolveError> {
+        let Some((first, rest)) = segments.split_first() else { return Err(ResolveError::Unresolved) };
+
+        let mut def = match &**first {
+            "crate" => Def::Crate,
+            "self" => Def::Module(module.to_string()),
+            "super" => self.lookup(&Def::Module(module.to_string()), first)?,
+            "Self" => self_ty.cloned().ok_or(ResolveError::Unresolved)?,
+            _ => self.lookup(&Def::Module(module.to_string()), first)?,
+        };
+
+        for s in rest {
+            def = self.lookup(&def, s)?;
+        }
+
+        Ok(def)
+    }
+
+    // looks up the name `name` within `def`.
+    fn lookup(&self, def: &Def, name: &str) -> Result<Def, ResolveError> {
+        match def {
+            Def::Crate if self.scopes.contains_key(name) => Ok(Def::Module(name.to_string())),
+            Def::Module(m) if name == "super" => match m.rsplit_once("::") {
+                Some((parent, _)) => Ok(Def::Module(parent.to_string())),
+                None => Ok(Def::Crate),
+            },
+            Def::Module(m) => {
+                let binding = self.scopes.get(m).and_then(|s| s.get(name)).ok_or(ResolveError::Unresolved)?;
+                match &binding.defs[..] {
+                    [def] => Ok(def.clone()),
+                    defs => Err(ResolveError::Ambiguous(defs.to_vec())),
+                }
+            },
+            Def::Enum(e) if self.enums[e].iter().any(|v| v == name) => Ok(Def::Variant(e.clone(), name.to_string())),
+            _ => Err(ResolveError::Unresolved),
+        }
+    }
+
+    // adds the names imported by `import` to `scope`, using the scopes of the last iteration.
+    fn apply_import(&self, module: &str, import: &Import, scope: &mut Scope) {
+        let Ok(def) = self.resolve_segments(module, None, &import.path) else { return };
+
+        match &import.kind {
+            ImportKind::Name { name, rename } => {
+                let Ok(def) = self.lookup(&def, name) else { return };
+                let binding = Binding { defs: vec![def], public: import.public, glob: false };
+                match scope.get(rename) {
+                    Some(b) if !b.glob => {}, // the name is already defined.
+                    _ => { scope.insert(rename.clone(), binding); },
+                }
+            },
+            ImportKind::Glob => {
+                let names: Vec<(String, Def)> = match &def {
+                    Def::Module(m) => {
+                        let Some(s) = self.scopes.get(m) else { return };
+                        s.iter()
+                         .filter(|(_, b)| b.public)
+                         .flat_map(|(name, b)| b.defs.iter().map(move |d| (name.clone(), d.clone())))
+                         .collect()
+                    },
+                    Def::Enum(e) => {
+                        self.enums[e].iter()
+                                     .map(|v| (v.clone(), Def::Variant(e.clone(), v.clone())))
+                                     .collect()
+                    },
+                    _ => return,
+                };
+
+                for (name, def) in names {
+                    let b = scope.entry(name).or_insert_with(|| Binding { defs: Vec::new(), public: import.public, glob: true });
+                    if b.glob && !b.defs.contains(&def) {
+                        b.defs.push(def);
+                        b.defs.sort();
+                    }
+                }
+            },
+        }
+    }
+}
+
+fn collect_imports(tree: &UseTree, mut path: Vec<String>, public: bool, out: &mut Vec<Import>) {
+    match tree {
+        UseTree::Path(p) => {
+            path.push(p.ident.to_string());
+            collect_imports(&p.tree, path, public, out);
+        },
+        UseTree::Name(n) => {
+            let name = n.ident.to_string();
+            let rename = name.clone();
+            out.push(Import { path, kind: ImportKind::Name { name, rename }, public });
+        },
+        UseTree::Rename(r) => {
+            let name = r.ident.to_string();
+            let rename = r.rename.to_string();
+            out.push(Import { path, kind: ImportKind::Name { name, rename }, public });
+        },
+        UseTree::Glob(_) => {
+            out.push(Import { path, kind: ImportKind::Glob, public });
+        },
+        UseTree::Group(g) => {
+            for t in &g.items {
+                collect_imports(t, path.clone(), public, out);
+            }
+        },
+    }
+}
+
+// all modules are in the same crate, so `pub(crate)` and alike are public for our purposes.
+fn is_public(vis: &Visibility) -> bool {
+    !matches!(vis, Visibility::Inherited)
+}
+
+fn item_name(item: &Item) -> Option<(&Ident, &Visibility)> {
+    match item {
+        Item::Const(x) => Some((&x.ident, &x.vis)),
+        Item::Enum(x) => Some((&x.ident, &x.vis)),
+        Item::Fn(x) => Some((&x.sig.ident, &x.vis)),
+        Item::Mod(x) => Some((&x.ident, &x.vis)),
+        Item::Static(x) => Some((&x.ident, &x.vis)),
+        Item::Struct(x) => Some((&x.ident, &x.vis)),
+        Item::Trait(x) => Some((&x.ident, &x.vis)),
+        Item::Type(x) => Some((&x.ident, &x.vis)),
+        Item::Union(x) => Some((&x.ident, &x.vis)),
+        // `macro_rules!` macros are not part of the module scope.
+        _ => None,
+    }
+}
+
+#[test]
+fn test_ambiguous() {
+    let a = Module::new("a", parse_quote! {
+        pub struct Cons(u32, #[specr::indirection] Option<Cons>);
+    });
+    let b = Module::new("b", parse_quote! {
+        pub enum Foo { Cons(u32, #[specr::indirection] Foo), Nil }
+        pub use Foo::*;
+    });
+    let c = Module::new("c", parse_quote! {
+        use crate::a::*;
+        use crate::b::*;
+
+        pub fn f() -> u32 {
+            let Cons(x, _) = g();
+            x
+        }
+    });
+    let mods = vec![a, b, c];
+
+    let resolver = Resolver::new(&mods);
+    let Err(ResolveError::Ambiguous(defs)) = resolver.resolve_segments("c", None, &["Cons".to_string()]) else { panic!("`Cons` is not ambiguous") };
+    assert_eq!(defs, [
+        Def::Variant(ItemPath { module: "b".to_string(), name: "Foo".to_string() }, "Cons".to_string()),
+        Def::Item(ItemPath { module: "a".to_string(), name: "Cons".to_string() }),
+    ]);
+
+    let mut diag = Diagnostics::default();
+    crate::typerec::typerec(mods, &mut diag);
+    assert_eq!(diag.messages(), ["cannot resolve `Cons`, it could refer to `a::Cons` or `b::Foo::Cons`"]);
+}
@@ -1,95 +1,594 @@
 /// This module gets the source code of MiniRust.
 
 use std::fs;
-use std::path::Path;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+
+use crate::source_map::{SourceMap, SourceLoc};
+use crate::auto_derive::ModuleDerives;
+use crate::prelude::*;
 
 pub struct Module {
+    /// the full name of the module, like `lang::step` for the directory `lang/step`.
     pub name: String,
     pub ast: syn::File,
+
+    /// maps the spans of `ast` back to the Markdown files.
+    pub source_map: SourceMap,
+
+    /// the ```rust,example code blocks of this module.
+    pub examples: Vec<Example>,
+
+    /// the cargo features used by ```rust,cfg(feature = "x") code blocks.
+    pub features: Vec<String>,
+
+    /// the traits to derive for the structs & enums of this module, in addition to the default ones.
+    pub derives: ModuleDerives,
 }
 
-/// looks for subdirs in the directory `folder`, and converts them to a module.
-pub fn fetch(folder: &Path) -> Vec<Module> {
-    let mut mods = Vec::new();
+/// A ```rust,example code block, which becomes an example of the generated crate.
+pub struct Example {
+    /// the name of the example, like `lang_step` for an example in `lang/step.md`.
+    pub name: String,
+    pub ast: syn::File,
+    pub source_map: SourceMap,
+}
 
-    for d in fs::read_dir(folder).unwrap() {
-        let d = d.unwrap();
-        let ty = d.file_type().unwrap();
-        if ty.is_dir() {
-            let name = d.file_name().into_string().unwrap();
+impl Module {
+    /// creates a module from code which doesn't originate from Markdown files, like the input of the `specr!` macro.
+    pub fn new(name: impl Into<String>, ast: syn::File) -> Module {
+        Module {
+            name: name.into(),
+            ast,
+            source_map: SourceMap::default(),
+            examples: Vec::new(),
+            features: Vec::new(),
+            derives: ModuleDerives::default(),
+        }
+    }
+
+    /// the name of the module within its parent, like `step` for `lang::step`.
+    pub fn ident(&self) -> &str {
+        self.name.rsplit("::").next().unwrap()
+    }
+
+    /// the full name of the parent module, `None` for top-level modules.
+    pub fn parent(&self) -> Option<&str> {
+        self.name.rsplit_once("::").map(|(p, _)| p)
+    }
+
+    /// the generated file, relative to the `src` directory of the generated crate.
+    pub fn file(&self) -> String {
+        format!("{}.rs", self.name.replace("::", "/"))
+    }
+}
 
-            // exclude ".git" from the module candidates.
-            if name == ".git" { continue; }
+/// looks for subdirs in the input directory, and converts them to modules.
+/// Nested subdirs become nested modules.
+///
+/// Modules which fail to parse are reported to `diag` and skipped.
+/// Modules not included by the config are skipped as well, unless they contain included modules.
+/// Modules and items of the config which are not found are reported as warnings.
+///
+/// Parents always come before their children.
+/// If the config contains an explicit module list, the modules are ordered accordingly.
+/// Otherwise, they are ordered by name, except that the prelude comes first.
+pub fn fetch(config: &Config, diag: &mut Diagnostics) -> Vec<Module> {
+    let mut mods = Vec::new();
+    fetch_dir(config, &config.input_path(), None, diag, &mut mods);
 
-            if let Some(m) = mk_mod(folder.to_string_lossy().as_ref(), &*name) {
-                mods.push(m);
+    for (key, names) in [("include", &config.include), ("list", &config.list)] {
+        for x in names.iter().flatten() {
+            if !mods.iter().any(|m| &m.name == x) {
+                diag.warning_in(&config.path, format!("module `{x}` of `modules.{key}` not found, or it contains no code"));
             }
         }
     }
 
+    // the warnings are sorted, as the tables are no ordered maps.
+    let mut modules: Vec<&String> = config.derive.modules.keys().collect();
+    modules.sort();
+    for x in modules {
+        if !mods.iter().any(|m| &m.name == x) {
+            diag.warning_in(&config.path, format!("module `{x}` of `derive.modules` not found, or it contains no code"));
+        }
+    }
+    let mut items: Vec<&String> = config.derive.items.keys().collect();
+    items.sort();
+    for x in items {
+        let found = x.rsplit_once("::").map_or(false, |(module, item)| {
+            mods.iter().any(|m| m.name == module && has_type(&m.ast, item))
+        });
+        if !found {
+            diag.warning_in(&config.path, format!("item `{x}` of `derive.items` is no struct or enum of the generated modules"));
+        }
+    }
+
+    // a module comes as early as the earliest listed module within it, this keeps parents before their children.
+    // Note that `sort_by_key` is stable.
+    let position = |m: &Module| -> usize {
+        let Some(list) = &config.list else { return 0 };
+        let within = |x: &String| x == &m.name || x.starts_with(&format!("{}::", m.name));
+        list.iter().position(within).unwrap_or(list.len())
+    };
+    mods.sort_by_key(position);
+
     // move prelude to the beginning to get macros to work.
-    let i = mods.iter().position(|x| x.name == "prelude").unwrap();
-    mods.swap(0, i);
+    if config.list.is_none() {
+        mods.sort_by_key(|m| m.name != "prelude" && !m.name.starts_with("prelude::"));
+    }
 
     mods
 }
 
-// returns None if the module doesn't contain any source code.
-// TODO use Rusts Path API for this.
-fn mk_mod(basename: &str, modname: &str) -> Option<Module> {
-    let mut code = String::new();
-    let dirname = format!("{basename}/{modname}");
+// whether `ast` defines a struct or enum `name`.
+fn has_type(ast: &syn::File, name: &str) -> bool {
+    ast.items.iter().any(|i| match i {
+        Item::Struct(s) => s.ident == name,
+        Item::Enum(e) => e.ident == name,
+        _ => false,
+    })
+}
+
+// converts the subdirs of `dir` to modules, and adds them to `mods`.
+// `parent` is the module of `dir`, or `None` for the input directory.
+fn fetch_dir(config: &Config, dir: &Path, parent: Option<&str>, diag: &mut Diagnostics, mods: &mut Vec<Module>) {
+    let Some(entries) = read_dir(dir, diag) else { return };
+    let mut subdirs: Vec<PathBuf> = entries.into_iter().filter(|d| d.is_dir()).collect();
+    subdirs.sort();
+
+    for d in subdirs {
+        let dirname = d.file_name().unwrap().to_string_lossy().into_owned();
 
-    for f in fs::read_dir(&dirname).unwrap() {
-        let f = f.unwrap();
-        let ty = f.file_type().unwrap();
-        if !ty.is_file() { continue; }
+        // exclude hidden dirs like ".git" from the module candidates.
+        if dirname.starts_with('.') { continue; }
 
-        let name = f.file_name().into_string().unwrap();
-        if !name.ends_with(".md") { continue; }
+        let name = match parent {
+            Some(p) => format!("{p}::{dirname}"),
+            None => dirname,
+        };
 
-        let name = format!("{dirname}/{name}");
+        if config.is_excluded(&name) { continue; }
 
-        let fcode = fs::read_to_string(name).unwrap();
-        let fcode = filter_specr_lang(&*fcode);
-        code.push_str(&*fcode);
+        let mut children = Vec::new();
+        fetch_dir(config, &d, Some(&name), diag, &mut children);
+
+        // a module without code is still required to contain its children.
+        let m = mk_mod(&d, &name, diag);
+        if (m.is_some() && config.is_included(&name)) || !children.is_empty() {
+            let mut m = m.unwrap_or_else(|| Module::new(name.clone(), parse_quote!()));
+            m.derives = config.derive.for_module(&name);
+            mods.push(m);
+        }
+        mods.extend(children);
     }
+}
+
+/// The name of the `#[cfg(test)]` module containing the ```rust,test code blocks of a module.
+pub const TEST_MOD: &str = "spec_tests";
 
-    if code.is_empty() { return None; }
+// returns None if the module doesn't contain any source code, or if it doesn't parse.
+// The .md files are concatenated in the order of their names.
+// Files which cannot be read, like files that are not UTF-8, are reported to `diag`.
+fn mk_mod(dir: &Path, modname: &str, diag: &mut Diagnostics) -> Option<Module> {
+    let mut files: Vec<PathBuf> = read_dir(dir, diag)?.into_iter()
+                                                      .filter(|f| f.is_file() && f.extension().map_or(false, |x| x == "md"))
+                                                      .collect();
+    files.sort();
 
-    let ast = syn::parse_str::<syn::File>(&*code).unwrap_or_else(|e| {
-        eprintln!("parse error:");
-        let start = e.span().start().line;
-        let start = start.checked_sub(2).unwrap_or(0);
-        let end = e.span().end().line + 2;
-        for x in code.lines().skip(start).take(end-start) {
-            eprintln!("{}", x);
+    let mut contents = Vec::new();
+    for f in files {
+        match fs::read_to_string(&f) {
+            Ok(content) => contents.push((f.file_name().unwrap().to_string_lossy().into_owned(), content)),
+            Err(e) => diag.error_in(&f, format!("cannot read this file: {e}")),
         }
-        panic!("{}", &e)
+    }
+
+    from_markdown(modname, &contents, diag)
+}
+
+// returns the entries of `dir`. Errors are reported to `diag`,
+// as files may vanish while they are read, e.g. if an editor saves a file by replacing it.
+fn read_dir(dir: &Path, diag: &mut Diagnostics) -> Option<Vec<PathBuf>> {
+    let entries = fs::read_dir(dir).and_then(|entries| {
+        entries.map(|e| e.map(|e| e.path())).collect::<std::io::Result<Vec<PathBuf>>>()
     });
+
+    match entries {
+        Ok(entries) => Some(entries),
+        Err(e) => {
+            diag.error_in(dir, format!("cannot read this directory: {e}"));
+            None
+        },
+    }
+}
+
+/// Converts the Markdown files `files`, given as (file name, content), to the module `modname`.
+/// The files are concatenated in the given order.
+///
+/// Returns `None` if the files don't contain any source code, or if it doesn't parse.
+pub fn from_markdown(modname: &str, files: &[(String, String)], diag: &mut Diagnostics) -> Option<Module> {
+    let mut code = CodeBuilder::new();
+    let mut tests = Vec::new();
+    let mut examples = Vec::new();
+    let mut cfgs = Vec::new();
+
+    for (name, fcode) in files {
+        let file = format!("{}/{name}", modname.replace("::", "/"));
+        let (blocks, unclosed) = filter_specr_lang(fcode);
+        if let Some(line) = unclosed {
+            diag.error_at(SourceLoc { file: file.clone(), line }, "unclosed code block");
+        }
+
+        for block in blocks {
+            let loc = SourceLoc { file: file.clone(), line: block.md_line - 1 };
+            match block.fence.kind {
+                BlockKind::Code => {
+                    let lines = code.push(&file, &block);
+                    if let Some(cfg) = &block.fence.cfg {
+                        cfgs.push((lines, cfg.clone(), loc));
+                    }
+                },
+                BlockKind::Test => tests.push((file.clone(), block, loc)),
+                BlockKind::Example if block.fence.cfg.is_some() => {
+                    diag.error_at(loc, "`example` code blocks cannot have a `cfg`");
+                },
+                BlockKind::Example => {
+                    let stem = name.strip_suffix(".md").unwrap_or(name);
+                    let name = format!("{}_{stem}", modname.replace("::", "_"));
+                    examples.push((name, file.clone(), block));
+                },
+            }
+        }
+    }
+
+    // test code goes into a `#[cfg(test)]` module at the end.
+    if !tests.is_empty() {
+        code.code.push_str(&format!("#[cfg(test)]\nmod {TEST_MOD} {{\nuse super::*;\n\n"));
+        for (file, block, loc) in tests {
+            let lines = code.push(&file, &block);
+            if let Some(cfg) = &block.fence.cfg {
+                cfgs.push((lines, cfg.clone(), loc));
+            }
+        }
+        code.code.push_str("}\n");
+    }
+
+    let examples = mk_examples(examples, diag);
+
+    if code.code.trim().is_empty() && examples.is_empty() { return None; }
+
+    let (mut ast, source_map) = code.parse(diag)?;
+
+    let mut features = Vec::new();
+    for (lines, cfg, loc) in cfgs {
+        let Ok(cfg) = parse_str::<TokenStream>(&cfg) else {
+            diag.error_at(loc, format!("cannot parse `cfg({cfg})`"));
+            continue;
+        };
+        add_cfg(&mut ast.items, &lines, &cfg);
+        collect_features(cfg, &mut features);
+    }
+    features.sort();
+    features.dedup();
+
     Some(Module {
         name: modname.to_string(),
-        ast
+        ast,
+        source_map,
+        examples,
+        features,
+        derives: ModuleDerives::default(),
     })
 }
 
+// collects the code of a module, and remembers where it came from.
+struct CodeBuilder {
+    code: String,
+    source_map: SourceMap,
+}
+
+impl CodeBuilder {
+    fn new() -> CodeBuilder {
+        CodeBuilder {
+            // generated tokens have `Span::call_site()`, which is located on the first line.
+            // Hence we leave the first line empty, so that the `SourceMap` doesn't map generated tokens to any Markdown code.
+            code: String::from("\n"),
+            source_map: SourceMap::default(),
+        }
+    }
+
+    // appends a code block, and returns the lines it occupies in the concatenated code.
+    fn push(&mut self, file: &str, block: &CodeBlock) -> Range<usize> {
+        let code_line = self.code.lines().count() + 1;
+        let len = block.code.lines().count();
+        self.source_map.add_block(file.to_string(), code_line, block.md_line, len);
+
+        self.code.push_str(&block.code);
+        self.code.push_str("\n\n");
+
+        code_line..code_line+len
+    }
+
+    // parses the concatenated code, parse errors are reported to `diag`.
+    fn parse(self, diag: &mut Diagnostics) -> Option<(syn::File, SourceMap)> {
+        let CodeBuilder { code, mut source_map } = self;
+        let ast = code.parse::<TokenStream>()
+                      .map_err(Error::from)
+                      .and_then(|tokens| {
+                          source_map.set_source(&tokens);
+                          parse2::<syn::File>(tokens)
+                      });
+
+        match ast {
+            Ok(ast) => Some((ast, source_map)),
+            Err(e) => {
+                diag.syn_error(&source_map, e);
+                None
+            },
+        }
+    }
+}
+
+// parses each ```rust,example code block on its own.
+fn mk_examples(blocks: Vec<(String, String, CodeBlock)>, diag: &mut Diagnostics) -> Vec<Example> {
+    let mut examples: Vec<Example> = Vec::new();
+
+    for (name, file, block) in blocks {
+        let mut code = CodeBuilder::new();
+        code.push(&file, &block);
+        let Some((ast, source_map)) = code.parse(diag) else { continue };
+
+        // multiple examples in the same file are numbered.
+        let n = examples.iter().filter(|e| e.name == name || e.name.starts_with(&format!("{name}_"))).count();
+        let name = match n {
+            0 => name,
+            n => format!("{name}_{}", n + 1),
+        };
+
+        examples.push(Example { name, ast, source_map });
+    }
+
+    examples
+}
+
+// adds `#[cfg(<cfg>)]` to the items within `lines` of the concatenated code.
+// Items of the test module are considered as well.
+fn add_cfg(items: &mut [Item], lines: &Range<usize>, cfg: &TokenStream) {
+    for item in items {
+        if lines.contains(&item.span().start().line) {
+            let attrs = match item {
+                Item::Const(x) => &mut x.attrs,
+                Item::Enum(x) => &mut x.attrs,
+                Item::Fn(x) => &mut x.attrs,
+                Item::Impl(x) => &mut x.attrs,
+                Item::Macro(x) => &mut x.attrs,
+                Item::Mod(x) => &mut x.attrs,
+                Item::Static(x) => &mut x.attrs,
+                Item::Struct(x) => &mut x.attrs,
+                Item::Trait(x) => &mut x.attrs,
+                Item::Type(x) => &mut x.attrs,
+                Item::Union(x) => &mut x.attrs,
+                Item::Use(x) => &mut x.attrs,
+                _ => continue,
+            };
+            attrs.insert(0, parse_quote!(#[cfg(#cfg)]));
+        } else if let Item::Mod(ItemMod { content: Some((_, items)), .. }) = item {
+            add_cfg(items, lines, cfg);
+        }
+    }
+}
+
+// collects the `x` of each `feature = "x"` in a `cfg` predicate.
+fn collect_features(cfg: TokenStream, features: &mut Vec<String>) {
+    let tokens: Vec<TokenTree> = cfg.into_iter().collect();
+    for (i, t) in tokens.iter().enumerate() {
+        match t {
+            TokenTree::Group(g) => collect_features(g.stream(), features),
+            TokenTree::Ident(id) if id == "feature" => {
+                let Some(TokenTree::Punct(p)) = tokens.get(i+1) else { continue };
+                let Some(TokenTree::Literal(lit)) = tokens.get(i+2) else { continue };
+                if p.as_char() != '=' { continue; }
+                let Ok(Lit::Str(s)) = parse_str::<Lit>(&lit.to_string()) else { continue };
+                features.push(s.value());
+            },
+            _ => {},
+        }
+    }
+}
+
+/// What a code block is used for, given by the annotations of its fence, like ```rust,test.
+#[derive(Default)]
+struct Fence {
+    kind: BlockKind,
+
+    /// given by `cfg(<predicate>)`, the code is only compiled if `<predicate>` holds.
+    cfg: Option<String>,
+}
+
+#[derive(Default, PartialEq, Eq)]
+enum BlockKind {
+    /// a plain ```rust block.
+    #[default]
+    Code,
+    /// `` ```rust,test ``: the code goes into a `#[cfg(test)]` module.
+    Test,
+    /// `` ```rust,example ``: the code becomes an example of the generated crate.
+    Example,
+}
+
+impl Fence {
+    // parses the part of the fence after ```rust, like `,test,cfg(feature = "x")`.
+    // Returns `None` if the code block should be ignored, like for ```rust,ignore.
+    fn parse(info: &str) -> Option<Fence> {
+        let info = info.trim();
+        let mut fence = Fence::default();
+        if info.is_empty() { return Some(fence); }
+
+        for a in split_annotations(info.strip_prefix(',')?) {
+            let a = a.trim();
+            match a {
+                "test" | "example" if fence.kind != BlockKind::Code => return None,
+                "test" => fence.kind = BlockKind::Test,
+                "example" => fence.kind = BlockKind::Example,
+                _ if a.starts_with("cfg(") && a.ends_with(')') && fence.cfg.is_none() => {
+                    fence.cfg = Some(a["cfg(".len()..a.len()-1].to_string());
+                },
+                _ => return None,
+            }
+        }
+
+        Some(fence)
+    }
+}
+
+// splits `s` at all commas which are not within parentheses.
+fn split_annotations(s: &str) -> Vec<&str> {
+    let mut out = Vec::new();
+    let mut depth = 0;
+    let mut start = 0;
+    for (i, c) in s.char_indices() {
+        match c {
+            '(' => depth += 1,
+            ')' => depth -= 1,
+            ',' if depth == 0 => {
+                out.push(&s[start..i]);
+                start = i + 1;
+            },
+            _ => {},
+        }
+    }
+    out.push(&s[start..]);
+
+    out
+}
+
+struct CodeBlock {
+    /// the line (1-based) the code starts at.
+    md_line: usize,
+    code: String,
+    fence: Fence,
+}
 
 // this filters out the code blocks ```rust <code> ```
-// it will ignore ```rust,ignore <code> ``` blocks
-fn filter_specr_lang(mut s: &str) -> String {
-    const OFFSET1: usize = "\n```rust\n".len();
+// it will ignore code blocks with unknown annotations, like ```rust,ignore <code> ```
+// If the last code block is not closed, the line it starts at is returned as well.
+fn filter_specr_lang(s: &str) -> (Vec<CodeBlock>, Option<usize>) {
+    const FENCE: &str = "\n```rust";
     const OFFSET2: usize = "\n```\n".len();
 
-    let mut out = String::new();
-    // note that this find(_) pattern doesn't match "```rust,ignore" due to the final newline.
-    while let Some(i) = s.find("\n```rust\n") {
-        s = &s[i+OFFSET1..];
-        if let Some(j) = s.find("\n```\n") {
-            out.push_str(&s[..j]);
-            out.push_str("\n\n");
-            s = &s[j+OFFSET2..];
-        } else { panic!("unclosed code segment!"); }
+    // the leading newline lets us find a code block at the start of the file.
+    let s = &format!("\n{s}");
+
+    let mut out = Vec::new();
+    let mut pos = 0;
+    while let Some(i) = s[pos..].find(FENCE) {
+        let info_start = pos + i + FENCE.len();
+        let Some(nl) = s[info_start..].find('\n') else { break };
+        let start = info_start + nl + 1;
+        let md_line = s[..start].lines().count();
+
+        let Some(j) = s[start..].find("\n```\n") else { return (out, Some(md_line - 1)) };
+        pos = start + j + OFFSET2;
+
+        if let Some(fence) = Fence::parse(&s[info_start..info_start+nl]) {
+            out.push(CodeBlock { md_line, code: s[start..start+j].to_string(), fence });
+        }
     }
 
-    out
+    (out, None)
+}
+
+// loads the config of a new, empty test directory, whose Markdown files are in `input`.
+#[cfg(test)]
+fn test_config(name: &str, config: &str) -> Config {
+    let dir = std::env::temp_dir().join(format!("specr-{name}-{}", std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(dir.join("input")).unwrap();
+    fs::write(dir.join("specr.toml"), format!("input = \"input\"\noutput = \"out\"\nname = \"spec\"\n{config}")).unwrap();
+
+    Config::from_file(&dir.join("specr.toml"))
+}
+
+#[test]
+fn test_unreadable() {
+    let config = test_config("unreadable", "");
+    let dir = config.input_path().join("lang");
+    fs::create_dir(&dir).unwrap();
+    fs::write(dir.join("a.md"), "```rust\nfn foo() {}\n```\n").unwrap();
+    fs::write(dir.join("b.md"), b"\xff\xfe").unwrap();
+
+    let mut diag = Diagnostics::new(&config);
+    let mods = fetch(&config, &mut diag);
+    assert_eq!(diag.messages().len(), 1);
+    assert!(diag.messages()[0].starts_with("cannot read this file: "));
+    assert_eq!(mods.len(), 1);
+
+    fs::remove_dir_all(&config.root).unwrap();
+}
+
+// adds the Markdown file `file` with the code `code` to the input directory of `config`.
+#[cfg(test)]
+fn write_md(config: &Config, file: &str, code: &str) {
+    let path = config.input_path().join(file);
+    fs::create_dir_all(path.parent().unwrap()).unwrap();
+    fs::write(path, format!("```rust\n{code}\n```\n")).unwrap();
+}
+
+#[test]
+fn test_fetch() {
+    let config = test_config("fetch", "");
+    write_md(&config, "b/b.md", "fn b() {}");
+    write_md(&config, "a/a.md", "fn a() {}");
+    write_md(&config, "a/x/x.md", "fn x() {}");
+    write_md(&config, "prelude/prelude.md", "pub use std::fmt;");
+    write_md(&config, ".git/git.md", "fn git() {}");
+    // `c` contains no code, but its child does.
+    write_md(&config, "c/d/d.md", "fn d() {}");
+    fs::write(config.input_path().join("c/README.md"), "no code").unwrap();
+
+    let mut diag = Diagnostics::new(&config);
+    let mods = fetch(&config, &mut diag);
+    assert!(diag.messages().is_empty());
+    let names: Vec<&str> = mods.iter().map(|m| &*m.name).collect();
+    assert_eq!(names, ["prelude", "a", "a::x", "b", "c", "c::d"]);
+    assert_eq!(mods[2].ident(), "x");
+    assert_eq!(mods[2].parent(), Some("a"));
+    assert_eq!(mods[5].file(), "c/d.rs");
+
+    fs::remove_dir_all(&config.root).unwrap();
+}
+
+#[test]
+fn test_fetch_list() {
+    let config = test_config("fetch-list", "[modules]\nlist = [\"b\", \"a::x\", \"a\", \"missing\"]\n\n[derive.items.\"a::Foo\"]\nadd = [\"Hash\"]\n");
+    write_md(&config, "a/a.md", "fn a() {}");
+    write_md(&config, "a/x/x.md", "fn x() {}");
+    write_md(&config, "b/b.md", "fn b() {}");
+    write_md(&config, "c/c.md", "fn c() {}");
+
+    let mut diag = Diagnostics::new(&config);
+    let mods = fetch(&config, &mut diag);
+    assert!(!diag.has_errors());
+    assert_eq!(diag.messages(), ["module `missing` of `modules.list` not found, or it contains no code",
+                                 "item `a::Foo` of `derive.items` is no struct or enum of the generated modules"]);
+
+    // parents come before their children, even if the list names them later.
+    let names: Vec<&str> = mods.iter().map(|m| &*m.name).collect();
+    assert_eq!(names, ["b", "a", "a::x"]);
+
+    fs::remove_dir_all(&config.root).unwrap();
+}
+
+#[test]
+fn test_fetch_without_prelude() {
+    let config = test_config("fetch-without-prelude", "");
+    write_md(&config, "b/b.md", "fn b() {}");
+    write_md(&config, "a/a.md", "fn a() {}");
+
+    let mut diag = Diagnostics::new(&config);
+    let names: Vec<String> = fetch(&config, &mut diag).into_iter().map(|m| m.name).collect();
+    assert_eq!(names, ["a", "b"]);
+
+    fs::remove_dir_all(&config.root).unwrap();
 }
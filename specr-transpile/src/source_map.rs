@@ -0,0 +1,171 @@
+use crate::prelude::*;
+
+use std::fmt::{self, Display};
+
+/// Maps lines of the concatenated code of a `Module` back to the Markdown files they originate from.
+///
+/// Code can be moved between modules (like submatches, by argmatch), so the code blocks remember
+/// which parsed string they belong to, and spans are only looked up in the code blocks of their own string.
+#[derive(Clone, Default)]
+pub struct SourceMap {
+    blocks: Vec<CodeBlock>,
+}
+
+/// A ```rust code block of some Markdown file.
+#[derive(Clone, PartialEq, Eq)]
+struct CodeBlock {
+    /// The parsed string containing the code block, as given by `Span::file`.
+    /// `None` until the code is parsed, such a code block matches spans of any string.
+    source: Option<String>,
+    /// The Markdown file, relative to the input directory.
+    file: String,
+    /// The first line of the code block in the concatenated code (1-based).
+    code_line: usize,
+    /// The first line of the code block in the Markdown file (1-based).
+    md_line: usize,
+    /// The number of lines of the code block.
+    len: usize,
+}
+
+/// A location in a Markdown file.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct SourceLoc {
+    pub file: String,
+    pub line: usize,
+}
+
+impl Display for SourceLoc {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}", self.file, self.line)
+    }
+}
+
+impl SourceMap {
+    /// registers that the code of `file` starting at `md_line` was placed at `code_line` of the concatenated code.
+    pub fn add_block(&mut self, file: String, code_line: usize, md_line: usize, len: usize) {
+        self.blocks.push(CodeBlock { source: None, file, code_line, md_line, len });
+    }
+
+    /// registers that the code blocks added so far were parsed into `tokens`.
+    pub fn set_source(&mut self, tokens: &TokenStream) {
+        let Some(tt) = tokens.clone().into_iter().next() else { return };
+        let source = tt.span().file();
+        for b in &mut self.blocks {
+            b.source.get_or_insert_with(|| source.clone());
+        }
+    }
+
+    /// adds the code blocks of `other`, so that code moved from its module can be looked up in `self`.
+    pub fn extend(&mut self, other: &SourceMap) {
+        for b in &other.blocks {
+            if !self.blocks.contains(b) {
+                self.blocks.push(b.clone());
+            }
+        }
+    }
+
+    // looks up the Markdown location of `line` in the concatenated code parsed into `source`.
+    fn lookup(&self, source: &str, line: usize) -> Option<SourceLoc> {
+        let b = self.blocks.iter().find(|b| {
+            b.source.as_ref().map_or(true, |s| s == source) && b.code_line <= line && line < b.code_line + b.len
+        })?;
+        Some(SourceLoc {
+            file: b.file.clone(),
+            line: b.md_line + (line - b.code_line),
+        })
+    }
+
+    /// looks up the Markdown location of a span.
+    /// Returns `None` for spans that were generated by the transpiler itself.
+    pub fn lookup_span(&self, span: Span) -> Option<SourceLoc> {
+        // generated tokens have `Span::call_site()`, whose line is not covered by any code block, see `mk_mod`.
+        self.lookup(&span.file(), span.start().line)
+    }
+
+    /// looks up the Markdown location of an item.
+    pub fn lookup_item(&self, item: &Item) -> Option<SourceLoc> {
+        self.lookup_span(item_span(item))
+    }
+
+    /// looks up the Markdown locations of the methods and statements within an item.
+    /// `code` is the generated code of the item, and each location is returned with the line of `code` (1-based) where its node begins.
+    ///
+    /// The nodes are found by parsing `code` again, and walking both syntax trees in the same order.
+    pub fn lookup_nested(&self, item: &Item, code: &str) -> Vec<(usize, SourceLoc)> {
+        let Ok(file) = parse_file(code) else { return Vec::new() };
+        let [generated] = &file.items[..] else { return Vec::new() };
+
+        let mut orig = NestedSpans(Vec::new());
+        orig.visit_item(item);
+        let mut gen = NestedSpans(Vec::new());
+        gen.visit_item(generated);
+        if orig.0.len() != gen.0.len() { return Vec::new(); }
+
+        orig.0.into_iter()
+              .zip(gen.0)
+              .filter_map(|(o, g)| Some((g.start().line, self.lookup_span(o)?)))
+              .collect()
+    }
+}
+
+// collects the spans of the methods and statements within an item, in the order of the syntax tree.
+struct NestedSpans(Vec<Span>);
+
+impl<'ast> Visit<'ast> for NestedSpans {
+    fn visit_impl_item_fn(&mut self, f: &'ast ImplItemFn) {
+        self.0.push(f.sig.fn_token.span);
+        visit_impl_item_fn(self, f);
+    }
+
+    fn visit_trait_item_fn(&mut self, f: &'ast TraitItemFn) {
+        self.0.push(f.sig.fn_token.span);
+        visit_trait_item_fn(self, f);
+    }
+
+    fn visit_stmt(&mut self, s: &'ast Stmt) {
+        self.0.push(s.span());
+        visit_stmt(self, s);
+    }
+}
+
+// returns the span of the keyword of an item.
+// We don't use `item.span()`, as its first token might be a generated attribute.
+fn item_span(item: &Item) -> Span {
+    match item {
+        Item::Const(x) => x.const_token.span,
+        Item::Enum(x) => x.enum_token.span,
+        Item::Fn(x) => x.sig.fn_token.span,
+        Item::Impl(x) => x.impl_token.span,
+        Item::Macro(x) => x.mac.path.span(),
+        Item::Mod(x) => x.mod_token.span,
+        Item::Static(x) => x.static_token.span,
+        Item::Struct(x) => x.struct_token.span,
+        Item::Trait(x) => x.trait_token.span,
+        Item::Type(x) => x.type_token.span,
+        Item::Union(x) => x.union_token.span,
+        Item::Use(x) => x.use_token.span,
+        x => x.span(),
+    }
+}
+
+#[test]
+fn test_lookup_nested() {
+    let md = "# Machine\n\n```rust\nimpl Machine {\n    fn step(&mut self) {\n        let x = 1;\n\n        self.run(x);\n    }\n\n    fn run(&mut self, x: u32) {}\n}\n```\n";
+    let mut diag = Diagnostics::default();
+    let m = crate::source::from_markdown("lang", &[("lang.md".to_string(), md.to_string())], &mut diag).unwrap();
+
+    let item = &m.ast.items[0];
+    let code = prettyplease::unparse(&File { shebang: None, attrs: Vec::new(), items: vec![item.clone()] });
+    let locs: Vec<(usize, String)> = m.source_map.lookup_nested(item, &code)
+                                                 .into_iter()
+                                                 .map(|(line, loc)| (line, loc.to_string()))
+                                                 .collect();
+
+    // the generated code has no empty line between the statements.
+    assert_eq!(locs, [
+        (2, "lang/lang.md:5".to_string()),
+        (3, "lang/lang.md:6".to_string()),
+        (4, "lang/lang.md:8".to_string()),
+        (6, "lang/lang.md:11".to_string()),
+    ]);
+}
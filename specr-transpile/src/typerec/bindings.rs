@@ -0,0 +1,160 @@
+use crate::typerec::*;
+
+/// How a pattern binds a variable, see the "binding modes" of the Rust reference.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub(in crate::typerec) enum BindingMode {
+    Move,
+    Ref,
+    RefMut,
+}
+
+/// A variable bound to a `GcCow<_>`-wrapped variant element.
+pub(in crate::typerec) struct Binding {
+    ident: Ident,
+    mode: BindingMode,
+    /// whether the variable was declared `mut`.
+    mutable: bool,
+}
+
+/// finds the variables within a pattern that are bound to wrapped elements.
+/// `mode` is the default binding mode of the pattern, i.e. whether the matched value is a reference.
+///
+/// Wrapped elements can only be bound to variables or ignored by `_`,
+/// other patterns are reported to `diag`.
+pub(in crate::typerec) fn pat_bindings(pat: &Pat, mode: BindingMode, lookup: &mut ElementLookup) -> Vec<Binding> {
+    let mut v = Visitor {
+        lookup,
+        mode,
+        bindings: Vec::new(),
+    };
+    v.visit_pat(pat);
+
+    v.bindings
+}
+
+/// generates the statements that unwrap the `GcCow`s bound by `bindings`.
+///
+/// - `x` becomes `let x = x.extract();`
+/// - `ref x` becomes `let x = &x.extract();`
+/// - `ref mut x` becomes a guard, which writes `x` back into the `GcCow` once it goes out of scope.
+pub(in crate::typerec) fn unwrap_bindings(bindings: &[Binding]) -> TokenStream {
+    let mut ts = TokenStream::new();
+    for b in bindings {
+        let x = &b.ident;
+        let stmts = match b.mode {
+            BindingMode::Move if b.mutable => quote! { let mut #x = #x.extract(); },
+            BindingMode::Move => quote! { let #x = #x.extract(); },
+            BindingMode::Ref => quote! { let #x = &#x.extract(); },
+            BindingMode::RefMut => {
+                let guard = format_ident!("__specr_{}_guard", x);
+                quote! {
+                    let mut #guard = #x.mutate_guard();
+                    let #x = &mut *#guard;
+                }
+            },
+        };
+        ts.extend(stmts);
+    }
+
+    ts
+}
+
+/// generates the statements that unwrap the `GcCow`s bound by `bindings` within a match guard.
+///
+/// A guard only sees shared references to the bindings, so `ref mut x` is unwrapped like `ref x`.
+pub(in crate::typerec) fn unwrap_guard_bindings(bindings: &[Binding]) -> TokenStream {
+    let mut ts = TokenStream::new();
+    for b in bindings {
+        let x = &b.ident;
+        let stmts = match b.mode {
+            BindingMode::Move => quote! { let #x = #x.extract(); },
+            BindingMode::Ref | BindingMode::RefMut => quote! { let #x = &#x.extract(); },
+        };
+        ts.extend(stmts);
+    }
+
+    ts
+}
+
+struct Visitor<'a, 'b> {
+    lookup: &'a mut ElementLookup<'b>,
+    mode: BindingMode,
+    bindings: Vec<Binding>,
+}
+
+impl Visitor<'_, '_> {
+    // `pat` matches the wrapped element `elem`.
+    fn element_pat(&mut self, pat: &Pat, elem: String) {
+        match pat {
+            Pat::Ident(id) if id.subpat.is_none() && !self.is_variant(&id.ident) => {
+                // an explicit `mut` resets the binding mode to `Move`.
+                let mode = match (&id.by_ref, &id.mutability) {
+                    (Some(_), Some(_)) => BindingMode::RefMut,
+                    (Some(_), None) => BindingMode::Ref,
+                    (None, Some(_)) => BindingMode::Move,
+                    (None, None) => self.mode,
+                };
+                let mutable = id.by_ref.is_none() && id.mutability.is_some();
+                self.bindings.push(Binding { ident: id.ident.clone(), mode, mutable });
+            },
+            Pat::Wild(_) | Pat::Rest(_) => {},
+            _ => {
+                let msg = format!("cannot match on the contents of the `#[specr::indirection]` element `{elem}`, bind it to a variable and match on that instead");
+                self.lookup.diag.error(self.lookup.source_map, pat.span(), msg);
+            },
+        }
+    }
+
+    // whether the identifier pattern `x` refers to an enum variant, instead of binding a variable.
+    fn is_variant(&self, x: &Ident) -> bool {
+        let path = Path::from(x.clone());
+        let l = &self.lookup;
+        matches!(l.resolver.resolve(l.module, l.self_ty.as_ref(), &path), Ok(Def::Variant(..)))
+    }
+}
+
+impl Visit<'_> for Visitor<'_, '_> {
+    fn visit_pat_struct(&mut self, pat: &PatStruct) {
+        let idxs: Vec<ElementIdx> = self.lookup.elements(&pat.path).into_iter().cloned().collect();
+
+        for f in &pat.fields {
+            let Member::Named(m) = &f.member else { continue };
+            if idxs.iter().any(|idx| matches!(idx, ElementIdx::Named(n) if n == m)) {
+                self.element_pat(&f.pat, m.to_token_stream().to_string());
+            } else {
+                self.visit_pat(&f.pat);
+            }
+        }
+    }
+
+    fn visit_pat_tuple_struct(&mut self, pat: &PatTupleStruct) {
+        let idxs: Vec<ElementIdx> = self.lookup.elements(&pat.path).into_iter().cloned().collect();
+
+        let variant = fix::extract_variant(&pat.path);
+
+        // after a `..`, we don't know the index of the elements anymore.
+        let rest = pat.elems.iter().position(|f| matches!(f, Pat::Rest(_)));
+        if let Some(r) = rest {
+            let after_rest = r + 1 < pat.elems.len();
+            if after_rest && idxs.iter().any(|idx| matches!(idx, ElementIdx::Unnamed(i) if *i >= r)) {
+                let msg = format!("cannot use `..` in front of an `#[specr::indirection]` element of `{variant}`, use `_` instead");
+                self.lookup.diag.error(self.lookup.source_map, pat.elems[r].span(), msg);
+            }
+        }
+
+        for (i, f) in pat.elems.iter().enumerate() {
+            if rest.map_or(true, |r| i < r) && idxs.contains(&ElementIdx::Unnamed(i)) {
+                self.element_pat(f, format!("{variant}.{i}"));
+            } else {
+                self.visit_pat(f);
+            }
+        }
+    }
+
+    // matching against a reference pattern resets the default binding mode.
+    fn visit_pat_reference(&mut self, pat: &PatReference) {
+        let outer = std::mem::replace(&mut self.mode, BindingMode::Move);
+        visit_pat_reference(self, pat);
+        self.mode = outer;
+    }
+}
@@ -1,33 +1,302 @@
 use crate::typerec::*;
+use bindings::{Binding, BindingMode, pat_bindings, unwrap_bindings, unwrap_guard_bindings};
 
-/// Fix constructing and matching of enum variants that were `GcCow<_>`-wrapped.
-pub(in crate::typerec) fn fix(mods: &mut [Module], elements: &HashSet<VariantElement>) {
+use std::collections::HashMap;
+
+// macros whose arguments are expressions separated by commas. Their arguments are fixed like ordinary code.
+pub(crate) static EXPR_MACROS: &[&str] = &["assert", "assert_eq", "assert_ne", "debug_assert", "debug_assert_eq", "debug_assert_ne",
+                                "list", "vec", "format", "print", "println", "eprint", "eprintln", "panic", "unreachable"];
+
+// methods of libspecr and the standard library which mutate their receiver.
+static MUTATING_METHODS: &[&str] = &["push", "pop", "pop_front", "set", "mutate_at", "reverse", "write_subslice_at_index", "sort_by_key",
+                                     "insert", "try_insert", "remove", "take", "replace", "get_or_insert", "get_or_insert_with", "as_mut"];
+
+/// The fields of all structs by name, see `struct_fields`.
+type Fields = HashMap<String, Vec<(ItemPath, Wrapped, Option<ItemPath>)>>;
+
+/// Fix constructing, matching and field accesses of enum variants and structs whose elements were `GcCow<_>`-wrapped.
+pub(in crate::typerec) fn fix(mods: &mut [Module], elements: &HashSet<Element>, fields: &Fields, resolver: &Resolver, diag: &mut Diagnostics) {
+    let mutating = &mutating_methods(mods);
     for m in mods {
-        Visitor { elements }.visit_file_mut(&mut m.ast);
+        let lookup = ElementLookup {
+            elements,
+            resolver,
+            diag: &mut *diag,
+            source_map: &m.source_map,
+            module: &m.name,
+            self_ty: None,
+        };
+        Visitor { lookup, fields, mutating, ref_params: HashMap::new(), local_tys: HashMap::new() }.visit_file_mut(&mut m.ast);
+
+        // examples use the crate through `crate::` paths, so they are resolved like code of their module.
+        for ex in &mut m.examples {
+            let lookup = ElementLookup {
+                elements,
+                resolver,
+                diag: &mut *diag,
+                source_map: &ex.source_map,
+                module: &m.name,
+                self_ty: None,
+            };
+            Visitor { lookup, fields, mutating, ref_params: HashMap::new(), local_tys: HashMap::new() }.visit_file_mut(&mut ex.ast);
+        }
     }
 }
 
 struct Visitor<'a> {
-    elements: &'a HashSet<VariantElement>,
+    lookup: ElementLookup<'a>,
+    fields: &'a Fields,
+
+    /// the names of the methods which mutate their receiver, see `mutating_methods`.
+    mutating: &'a HashSet<String>,
+
+    /// the parameters of the surrounding function that are references,
+    /// like `self` in `fn foo(&self)`. They determine the binding mode of `match self { .. }`.
+    ref_params: HashMap<String, BindingMode>,
+
+    /// the structs of the specr code, which the variables in scope are known to have (possibly behind references).
+    /// This is known for parameters and `let` statements with a type.
+    local_tys: HashMap<String, ItemPath>,
+}
+
+impl Visitor<'_> {
+    fn visit_fn_mut(&mut self, sig: &Signature, block: &mut Block) {
+        let ref_params = sig.inputs.iter().filter_map(|arg| {
+            let (name, ty) = match arg {
+                FnArg::Receiver(r) => ("self".to_string(), &*r.ty),
+                FnArg::Typed(pt) => {
+                    let Pat::Ident(pi) = &*pt.pat else { return None };
+                    (pi.ident.to_string(), &*pt.ty)
+                },
+            };
+            let Type::Reference(ty) = ty else { return None };
+            let mode = if ty.mutability.is_some() { BindingMode::RefMut } else { BindingMode::Ref };
+            Some((name, mode))
+        }).collect();
+
+        let local_tys = sig.inputs.iter().filter_map(|arg| {
+            let FnArg::Typed(pt) = arg else { return None };
+            let Pat::Ident(pi) = &*pt.pat else { return None };
+            Some((pi.ident.to_string(), self.struct_of(&pt.ty)?))
+        }).collect();
+
+        let outer = std::mem::replace(&mut self.ref_params, ref_params);
+        let outer_tys = std::mem::replace(&mut self.local_tys, local_tys);
+        self.visit_block_mut(block);
+        self.ref_params = outer;
+        self.local_tys = outer_tys;
+    }
+
+    // the struct of the specr code `ty` refers to, looking through references.
+    fn struct_of(&self, ty: &Type) -> Option<ItemPath> {
+        match ty {
+            Type::Reference(r) => self.struct_of(&r.elem),
+            Type::Paren(p) => self.struct_of(&p.elem),
+            Type::Path(p) if p.qself.is_none() => {
+                let l = &self.lookup;
+                match l.resolver.resolve(l.module, l.self_ty.as_ref(), &p.path) {
+                    Ok(Def::Item(s)) => Some(s),
+                    _ => None,
+                }
+            },
+            _ => None,
+        }
+    }
+
+    // the struct of the specr code `e` evaluates to, if this is syntactically visible.
+    fn struct_of_expr(&self, e: &Expr) -> Option<ItemPath> {
+        match e {
+            Expr::Paren(p) => self.struct_of_expr(&p.expr),
+            Expr::Reference(r) => self.struct_of_expr(&r.expr),
+            Expr::Unary(ExprUnary { op: UnOp::Deref(_), expr, .. }) => self.struct_of_expr(expr),
+            Expr::Path(p) if p.path.is_ident("self") => match &self.lookup.self_ty {
+                Some(Def::Item(s)) => Some(s.clone()),
+                _ => None,
+            },
+            Expr::Path(p) => self.local_tys.get(&p.path.get_ident()?.to_string()).cloned(),
+            Expr::Field(f) => {
+                let s = self.struct_of_expr(&f.base)?;
+                let candidates = self.fields.get(&member_name(&f.member))?;
+                candidates.iter().find(|(ty, ..)| *ty == s)?.2.clone()
+            },
+            _ => None,
+        }
+    }
+
+    // whether the field access `f` accesses a wrapped struct field.
+    //
+    // If the struct of the accessed value is not syntactically visible, the field is identified by its name,
+    // but only if the wrapped fields of that name have an `#[specr::indirection]`.
+    // Otherwise, or if structs have both wrapped and unwrapped fields of that name, an error is reported.
+    fn is_wrapped_field(&mut self, f: &ExprField) -> bool {
+        let name = member_name(&f.member);
+        let Some(candidates) = self.fields.get(&name) else { return false };
+
+        if let Some(s) = self.struct_of_expr(&f.base) {
+            return candidates.iter().any(|(ty, wrapped, _)| *ty == s && *wrapped != Wrapped::No);
+        }
+
+        // tuple fields are also used by tuples, so we only consider them if the struct is known.
+        if matches!(f.member, Member::Unnamed(_)) { return false; }
+
+        // inferred indirections are not visible in the code, so accessing them by name alone would be surprising.
+        if let Some((ty, ..)) = candidates.iter().find(|(_, w, _)| *w == Wrapped::Inferred) {
+            let msg = format!("`.{name}` might refer to the field of `{ty}`, which gets an indirection to break a type cycle, \
+                               give the accessed value a type, like `let x: {} = ...`, or mark the field with `#[specr::indirection]`", ty.name);
+            self.lookup.diag.error(self.lookup.source_map, f.member.span(), msg);
+            return false;
+        }
+
+        let wrapped: Vec<&ItemPath> = candidates.iter().filter(|(_, w, _)| *w == Wrapped::Explicit).map(|(ty, ..)| ty).collect();
+        if wrapped.is_empty() { return false; }
+        if wrapped.len() < candidates.len() {
+            let msg = format!("cannot tell whether `.{name}` refers to the `#[specr::indirection]` field of {}, give the accessed value a type, like `let x: {} = ...`",
+                              wrapped.iter().map(|ty| format!("`{ty}`")).collect::<Vec<_>>().join(" or "), wrapped[0].name);
+            self.lookup.diag.error(self.lookup.source_map, f.member.span(), msg);
+            return false;
+        }
+
+        true
+    }
+
+    // visits a place expression which is mutated, like the left side of an assignment.
+    // Wrapped fields within it are mutated through a guard, which writes them back into their `GcCow`.
+    fn visit_place_mut(&mut self, e: &mut Expr) {
+        match e {
+            Expr::Field(f) => {
+                let wrapped = self.is_wrapped_field(f);
+                self.visit_place_mut(&mut f.base);
+                if wrapped {
+                    *e = parse_quote! { (*#f.mutate_guard()) };
+                }
+            },
+            Expr::Index(i) => {
+                self.visit_place_mut(&mut i.expr);
+                self.visit_expr_mut(&mut i.index);
+            },
+            Expr::Paren(p) => self.visit_place_mut(&mut p.expr),
+            _ => self.visit_expr_mut(e),
+        }
+    }
+
+    // visits a scrutinee which is mutated by `ref mut` bindings.
+    // A wrapped field becomes `*x.f.mutate_guard()`, without the parentheses of `visit_place_mut`.
+    fn visit_scrutinee_mut(&mut self, e: &mut Expr) {
+        self.visit_place_mut(e);
+        if let Expr::Paren(p) = e {
+            *e = (*p.expr).clone();
+        }
+    }
+
+    // the default binding mode of patterns matching against `e`.
+    // We only know that `e` is a reference, if this is syntactically visible.
+    fn scrutinee_mode(&self, e: &Expr) -> BindingMode {
+        match e {
+            Expr::Reference(r) if r.mutability.is_some() => BindingMode::RefMut,
+            Expr::Reference(_) => BindingMode::Ref,
+            Expr::Paren(p) => self.scrutinee_mode(&p.expr),
+            Expr::Path(p) => p.path.get_ident()
+                                   .and_then(|x| self.ref_params.get(&x.to_string()))
+                                   .copied()
+                                   .unwrap_or(BindingMode::Move),
+            _ => BindingMode::Move,
+        }
+    }
+
+    // returns the statements unwrapping the wrapped elements bound by `pat`.
+    fn unwrap_pat(&mut self, pat: &Pat, scrutinee: &Expr) -> Vec<Stmt> {
+        let bindings = self.bindings(pat, scrutinee);
+        let stmts = unwrap_bindings(&bindings);
+        let block: Block = parse2(quote! {{ #stmts }}).unwrap();
+
+        block.stmts
+    }
+
+    // the binding mode of each element of a tuple scrutinee, like `(self, x)` generated by argmatch, is determined separately.
+    fn bindings(&mut self, pat: &Pat, scrutinee: &Expr) -> Vec<Binding> {
+        if let (Pat::Tuple(p), Expr::Tuple(e)) = (pat, scrutinee) {
+            let has_rest = p.elems.iter().any(|x| matches!(x, Pat::Rest(_)));
+            if !has_rest && p.elems.len() == e.elems.len() {
+                return p.elems.iter()
+                              .zip(&e.elems)
+                              .flat_map(|(p, e)| self.bindings(p, e))
+                              .collect();
+            }
+        }
+
+        let mode = self.scrutinee_mode(scrutinee);
+        pat_bindings(pat, mode, &mut self.lookup)
+    }
 }
 
 impl VisitMut for Visitor<'_> {
+    // fixup field accesses of wrapped struct fields:
+    // `x.f` ==> `x.f.extract()`
+    // `x.f = e` ==> `x.f = libspecr::hidden::GcCow::new(e)`
+    // `x.f += e` ==> `(*x.f.mutate_guard()) += e`
+    // `x.f.push(e)` ==> `(*x.f.mutate_guard()).push(e)`
+    // `if let Some(ref mut y) = x.f` ==> `if let Some(ref mut y) = *x.f.mutate_guard()`
+    fn visit_expr_mut(&mut self, e: &mut Expr) {
+        match e {
+            Expr::Assign(a) => {
+                match &mut *a.left {
+                    Expr::Field(f) if self.is_wrapped_field(f) => {
+                        self.visit_place_mut(&mut f.base);
+                        self.visit_expr_mut(&mut a.right);
+                        wrap_expr(&mut a.right);
+                    },
+                    left => {
+                        self.visit_place_mut(left);
+                        self.visit_expr_mut(&mut a.right);
+                    },
+                }
+            },
+            Expr::Binary(b) if is_compound_assign(&b.op) => {
+                self.visit_place_mut(&mut b.left);
+                self.visit_expr_mut(&mut b.right);
+            },
+            Expr::Reference(r) if r.mutability.is_some() => self.visit_place_mut(&mut r.expr),
+            Expr::MethodCall(m) if self.mutating.contains(&m.method.to_string()) => {
+                self.visit_place_mut(&mut m.receiver);
+                for arg in &mut m.args {
+                    self.visit_expr_mut(arg);
+                }
+            },
+            Expr::Let(l) if binds_ref_mut(&l.pat) => self.visit_scrutinee_mut(&mut l.expr),
+            Expr::Field(f) => {
+                let wrapped = self.is_wrapped_field(f);
+                self.visit_expr_mut(&mut f.base);
+                if wrapped {
+                    *e = parse_quote! { #f.extract() };
+                }
+            },
+            _ => visit_expr_mut(self, e),
+        }
+    }
+
+    // `Self` refers to the self type within an impl block.
+    fn visit_item_impl_mut(&mut self, i: &mut ItemImpl) {
+        let self_ty = self.lookup.resolver.resolve_type(self.lookup.module, &i.self_ty);
+        let outer = std::mem::replace(&mut self.lookup.self_ty, self_ty);
+
+        visit_item_impl_mut(self, i);
+
+        self.lookup.self_ty = outer;
+    }
+
     // fixup named enum variant construction:
     // `Variant { x: 2 }` ==> `Variant { x: libspecr::hidden::GcCow::new(2) }`
     fn visit_expr_struct_mut(&mut self, i: &mut ExprStruct) {
-        for e in self.elements {
-            let ElementIdx::Named(name) = &e.idx else { continue };
-
-            let p = extract_variant(&i.path);
-            if p == e.variant {
-                for f in &mut i.fields {
-                    let Member::Named(m) = &f.member else { continue };
-                    if m == name {
-                        // this solves the case `Variant { x }`.
-                        f.colon_token = Some(Default::default());
-
-                        wrap_expr(&mut f.expr);
-                    }
+        for idx in self.lookup.elements(&i.path) {
+            let ElementIdx::Named(name) = idx else { continue };
+
+            for f in &mut i.fields {
+                let Member::Named(m) = &f.member else { continue };
+                if m == name {
+                    // this solves the case `Variant { x }`.
+                    f.colon_token = Some(Default::default());
+
+                    wrap_expr(&mut f.expr);
                 }
             }
         }
@@ -38,12 +307,9 @@ impl VisitMut for Visitor<'_> {
     // fixup unnamed enum variant construction:
     // `Some(2)` ==> `Some(libspecr::hidden::GcCow::new(2))`
     fn visit_expr_call_mut(&mut self, i: &mut ExprCall) {
-        for e in self.elements {
-            let ElementIdx::Unnamed(idx) = &e.idx else { continue };
-            let Expr::Path(p) = &*i.func else { continue };
-            let var = extract_variant(&p.path);
-
-            if e.variant == var {
+        if let Expr::Path(p) = &*i.func {
+            for idx in self.lookup.elements(&p.path) {
+                let ElementIdx::Unnamed(idx) = idx else { continue };
                 let Some(arg_ref) = i.args.iter_mut().nth(*idx) else { continue };
                 wrap_expr(arg_ref);
             }
@@ -52,20 +318,183 @@ impl VisitMut for Visitor<'_> {
         visit_expr_call_mut(self, i);
     }
 
+    // fixup the arguments of macros like `assert!(..)` or `list![..]`.
+    fn visit_macro_mut(&mut self, i: &mut Macro) {
+        let Some(name) = i.path.segments.last() else { return };
+        if !EXPR_MACROS.contains(&&*name.ident.to_string()) { return; }
+        let Ok(mut args) = i.parse_body_with(Punctuated::<Expr, Token![,]>::parse_terminated) else { return };
+
+        for arg in args.iter_mut() {
+            self.visit_expr_mut(arg);
+        }
+        i.tokens = args.to_token_stream();
+    }
+
+    fn visit_item_fn_mut(&mut self, i: &mut ItemFn) {
+        self.visit_fn_mut(&i.sig, &mut i.block);
+    }
+
+    fn visit_impl_item_fn_mut(&mut self, i: &mut ImplItemFn) {
+        self.visit_fn_mut(&i.sig, &mut i.block);
+    }
+
+    fn visit_trait_item_fn_mut(&mut self, i: &mut TraitItemFn) {
+        if let Some(block) = &mut i.default {
+            self.visit_fn_mut(&i.sig, block);
+        }
+    }
+
     // fixup matches:
     // `Foo { x } => { ... }` ==> `Foo { x } => { let x = x.extract(); ... }`
-    fn visit_arm_mut(&mut self, i: &mut Arm) {
-        let idents: Vec<_> = pat_idents::pat_idents(&i.pat, self.elements).into_iter().collect();
-        let body = &i.body;
-        let body = quote! {
-            {
-                #( let #idents = #idents.extract(); )*
-                #body
+    // `Foo { x } if g => ...` ==> `Foo { x } if { let x = x.extract(); g } => ...`
+    fn visit_expr_match_mut(&mut self, i: &mut ExprMatch) {
+        for arm in &mut i.arms {
+            let bindings = self.bindings(&arm.pat, &i.expr);
+            if bindings.is_empty() { continue; }
+
+            if let Some((_, guard)) = &mut arm.guard {
+                let stmts = unwrap_guard_bindings(&bindings);
+                **guard = parse_quote! {
+                    {
+                        #stmts
+                        #guard
+                    }
+                };
             }
-        };
-        i.body = Box::new(parse2(body).unwrap());
 
-        visit_arm_mut(self, i);
+            let stmts = unwrap_bindings(&bindings);
+
+            let body = &arm.body;
+            let body = quote! {
+                {
+                    #stmts
+                    #body
+                }
+            };
+            *arm.body = parse2(body).unwrap();
+        }
+
+        // `ref mut` bindings mutate the scrutinee.
+        if i.arms.iter().any(|arm| binds_ref_mut(&arm.pat)) {
+            self.visit_scrutinee_mut(&mut i.expr);
+            for arm in &mut i.arms {
+                self.visit_arm_mut(arm);
+            }
+        } else {
+            visit_expr_match_mut(self, i);
+        }
+    }
+
+    fn visit_local_mut(&mut self, i: &mut Local) {
+        match &mut i.init {
+            Some(init) if binds_ref_mut(&i.pat) => {
+                self.visit_scrutinee_mut(&mut init.expr);
+                if let Some((_, diverge)) = &mut init.diverge {
+                    self.visit_expr_mut(diverge);
+                }
+            },
+            _ => visit_local_mut(self, i),
+        }
+    }
+
+    // fixup `if let Foo { x } = e { ... }`.
+    fn visit_expr_if_mut(&mut self, i: &mut ExprIf) {
+        if let Expr::Let(l) = &*i.cond {
+            let stmts = self.unwrap_pat(&l.pat, &l.expr);
+            i.then_branch.stmts.splice(0..0, stmts);
+        }
+
+        visit_expr_if_mut(self, i);
+    }
+
+    // fixup `while let Foo { x } = e { ... }`.
+    fn visit_expr_while_mut(&mut self, i: &mut ExprWhile) {
+        if let Expr::Let(l) = &*i.cond {
+            let stmts = self.unwrap_pat(&l.pat, &l.expr);
+            i.body.stmts.splice(0..0, stmts);
+        }
+
+        visit_expr_while_mut(self, i);
+    }
+
+    // fixup `let Foo { x } = e;` by unwrapping right after the `let`.
+    fn visit_block_mut(&mut self, i: &mut Block) {
+        let mut stmts = Vec::new();
+        for mut stmt in std::mem::take(&mut i.stmts) {
+            let unwrap = match &stmt {
+                Stmt::Local(Local { pat, init: Some(init), .. }) => self.unwrap_pat(pat, &init.expr),
+                _ => Vec::new(),
+            };
+
+            self.visit_stmt_mut(&mut stmt);
+
+            // a `let` shadows the parameters it binds.
+            if let Stmt::Local(local) = &stmt {
+                let (pat, ty) = match &local.pat {
+                    Pat::Type(pt) => (&*pt.pat, self.struct_of(&pt.ty)),
+                    pat => (pat, None),
+                };
+                if let Pat::Ident(pi) = pat {
+                    let x = pi.ident.to_string();
+                    self.ref_params.remove(&x);
+                    match ty {
+                        Some(ty) => { self.local_tys.insert(x, ty); },
+                        None => { self.local_tys.remove(&x); },
+                    }
+                }
+            }
+
+            stmts.push(stmt);
+            stmts.extend(unwrap);
+        }
+        i.stmts = stmts;
+    }
+}
+
+// the names of the methods which mutate their receiver:
+// the ones of `MUTATING_METHODS`, and the methods of the specr code taking `&mut self`.
+// Calling them on a wrapped field mutates the field through a guard.
+fn mutating_methods(mods: &[Module]) -> HashSet<String> {
+    struct Methods(HashSet<String>);
+
+    impl Visit<'_> for Methods {
+        fn visit_signature(&mut self, sig: &Signature) {
+            if let Some(FnArg::Receiver(r)) = sig.inputs.first() {
+                if r.reference.is_some() && r.mutability.is_some() {
+                    self.0.insert(sig.ident.to_string());
+                }
+            }
+        }
+    }
+
+    let mut methods = Methods(MUTATING_METHODS.iter().map(|x| x.to_string()).collect());
+    for m in mods {
+        methods.visit_file(&m.ast);
+    }
+
+    methods.0
+}
+
+// whether `pat` binds a variable by `ref mut`.
+fn binds_ref_mut(pat: &Pat) -> bool {
+    struct RefMut(bool);
+
+    impl Visit<'_> for RefMut {
+        fn visit_pat_ident(&mut self, p: &PatIdent) {
+            self.0 |= p.by_ref.is_some() && p.mutability.is_some();
+            visit_pat_ident(self, p);
+        }
+    }
+
+    let mut v = RefMut(false);
+    v.visit_pat(pat);
+    v.0
+}
+
+fn member_name(m: &Member) -> String {
+    match m {
+        Member::Named(x) => x.to_string(),
+        Member::Unnamed(i) => i.index.to_string(),
     }
 }
 
@@ -77,6 +506,11 @@ fn wrap_expr(expr: &mut Expr) {
     *expr = parse2(e).unwrap();
 }
 
+pub(crate) fn is_compound_assign(op: &BinOp) -> bool {
+    matches!(op, BinOp::AddAssign(_) | BinOp::SubAssign(_) | BinOp::MulAssign(_) | BinOp::DivAssign(_) | BinOp::RemAssign(_)
+               | BinOp::BitXorAssign(_) | BinOp::BitAndAssign(_) | BinOp::BitOrAssign(_) | BinOp::ShlAssign(_) | BinOp::ShrAssign(_))
+}
+
 // extract the last segment (i.e. the enum Variant) from a path
 // `Foo::Bar` => `Bar`
 pub(in crate::typerec) fn extract_variant(p: &Path) -> Ident {
@@ -1,42 +1,160 @@
 use crate::prelude::*;
+use crate::resolve::{Resolver, ResolveError, Def, ItemPath};
+use crate::source_map::SourceMap;
+
+use std::fmt::{self, Display};
 
 mod wrap;
-use wrap::wrap_variant_elements;
+use wrap::{wrap_elements, struct_fields, Wrapped};
 
-mod pat_idents;
+mod bindings;
 mod fix;
+pub(crate) use fix::{is_compound_assign, EXPR_MACROS};
 
-/// Resolves infinite type recursion problems in enums by wrapping with `GcCow<_>`.
-pub fn typerec(mut mods: Vec<Module>) -> Vec<Module> {
-    let elements = wrap_variant_elements(&mut mods);
-    fix::fix(&mut mods, &elements);
+/// Resolves infinite type recursion problems in enums and structs by wrapping with `GcCow<_>`.
+/// Elements are wrapped if they are marked with `#[specr::indirection]`, or if they would make their type infinitely sized.
+/// Paths that could refer to multiple wrapped enum variants, and ambiguous field accesses are reported to `diag`.
+pub fn typerec(mut mods: Vec<Module>, diag: &mut Diagnostics) -> Vec<Module> {
+    let resolver = Resolver::new(&mods);
+    let (elements, explicit) = wrap_elements(&mut mods, &resolver);
+    let fields = struct_fields(&mods, &elements, &explicit, &resolver);
+    fix::fix(&mut mods, &elements, &fields, &resolver, diag);
 
     mods
 }
 
-/// Specifies an Element of some enum Variant.
-/// Note that this does not know the name of the enum.
+/// Specifies an Element of some enum Variant or struct.
 ///
 /// Example:
 /// enum Foo {
 ///   A { a: u32 },
 ///   B(u32),
 /// }
+/// struct Bar(u32);
 ///
 /// referencing Foo::A::a would be done by
-/// VariantElement { variant: "A", idx: ElementIdx::Named("a") } 
+/// Element { ty: "lang::Foo", variant: Some("A"), idx: ElementIdx::Named("a") } 
 /// while the u32-argument of Foo::B would be referenced by
-/// VariantElement { variant: "B", idx: ElementIdx::Unnamed(0) } 
+/// Element { ty: "lang::Foo", variant: Some("B"), idx: ElementIdx::Unnamed(0) } 
+/// and the field of Bar by
+/// Element { ty: "lang::Bar", variant: None, idx: ElementIdx::Unnamed(0) } 
 ///
-#[derive(Hash, PartialEq, Eq)]
-struct VariantElement {
-    variant: Ident,
+#[derive(Hash, PartialEq, Eq, Clone)]
+struct Element {
+    ty: ItemPath,
+    /// `None` for the fields of a struct.
+    variant: Option<Ident>,
     idx: ElementIdx,
 }
 
-/// Indexes an enum variant, either by-name, or by argument position.
-#[derive(Hash, PartialEq, Eq)]
+/// Indexes an enum variant or struct, either by-name, or by argument position.
+#[derive(Hash, PartialEq, Eq, Clone)]
 enum ElementIdx {
     Named(Ident),
     Unnamed(usize),
 }
+
+impl Display for ElementIdx {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ElementIdx::Named(x) => write!(f, "{x}"),
+            ElementIdx::Unnamed(i) => write!(f, "{i}"),
+        }
+    }
+}
+
+/// Finds out which wrapped elements a path to an enum variant or struct refers to.
+struct ElementLookup<'a> {
+    elements: &'a HashSet<Element>,
+    resolver: &'a Resolver,
+    diag: &'a mut Diagnostics,
+    source_map: &'a SourceMap,
+
+    /// the module containing the paths.
+    module: &'a str,
+
+    /// the self type of the surrounding `impl` block.
+    self_ty: Option<Def>,
+}
+
+impl ElementLookup<'_> {
+    /// returns the wrapped elements of the variant or struct `path` refers to.
+    ///
+    /// Paths that the name resolution doesn't understand, like variants imported within a function,
+    /// are matched by their last segment. If this is ambiguous, an error is reported.
+    /// The same holds for paths imported by multiple globs.
+    fn elements(&mut self, path: &Path) -> Vec<&ElementIdx> {
+        let var = fix::extract_variant(path);
+        let (ty, variant) = match self.resolver.resolve(self.module, self.self_ty.as_ref(), path) {
+            Ok(Def::Variant(e, v)) if var == v => (e, Some(var)),
+            Ok(Def::Item(s)) => (s, None),
+            Ok(_) => return Vec::new(),
+            Err(e) => {
+                let mut candidates: Vec<(&ItemPath, &Option<Ident>)> = self.elements.iter()
+                                                                               .filter(|e| match &e.variant {
+                                                                                   Some(v) => *v == var,
+                                                                                   None => var == e.ty.name,
+                                                                               })
+                                                                               .map(|e| (&e.ty, &e.variant))
+                                                                               .collect();
+
+                // if the path is ambiguous, we at least know which enums or structs it could refer to.
+                if let ResolveError::Ambiguous(defs) = e {
+                    candidates.retain(|(ty, v)| match v {
+                        Some(v) => defs.contains(&Def::Variant((*ty).clone(), v.to_string())),
+                        None => defs.contains(&Def::Item((*ty).clone())),
+                    });
+                }
+                candidates.sort_by_key(|(ty, _)| *ty);
+                candidates.dedup();
+
+                match candidates[..] {
+                    [] => return Vec::new(),
+                    [(ty, v)] => (ty.clone(), v.clone()),
+                    _ => {
+                        let candidates: Vec<String> = candidates.iter().map(|(ty, v)| match v {
+                            Some(v) => format!("`{ty}::{v}`"),
+                            None => format!("`{ty}`"),
+                        }).collect();
+                        let msg = format!("cannot resolve `{}`, it could refer to {}", path.to_token_stream(), candidates.join(" or "));
+                        self.diag.error(self.source_map, path.span(), msg);
+                        return Vec::new();
+                    },
+                }
+            },
+        };
+
+        self.elements.iter()
+                     .filter(|e| e.ty == ty && e.variant == variant)
+                     .map(|e| &e.idx)
+                     .collect()
+    }
+}
+
+#[test]
+fn test_field_errors() {
+    let m = Module::new("lang", parse_quote! {
+        pub struct Node { val: u32, next: Option<Node> }
+        pub struct Tree { #[specr::indirection] kids: Option<Tree> }
+        pub struct Leaf { kids: u32 }
+        pub struct List { #[specr::indirection] tail: Option<List> }
+
+        pub fn f(n: Node, t: Tree, l: List) {
+            let _ = n.next;
+            let _ = t.kids;
+            let _ = l.tail;
+            let _ = node().next;
+            let _ = tree().kids;
+            let _ = list().tail;
+        }
+    });
+
+    let mut diag = Diagnostics::default();
+    typerec(vec![m], &mut diag);
+
+    assert_eq!(diag.messages(), [
+        "`.next` might refer to the field of `lang::Node`, which gets an indirection to break a type cycle, \
+         give the accessed value a type, like `let x: Node = ...`, or mark the field with `#[specr::indirection]`",
+        "cannot tell whether `.kids` refers to the `#[specr::indirection]` field of `lang::Tree`, give the accessed value a type, like `let x: Tree = ...`",
+    ]);
+}
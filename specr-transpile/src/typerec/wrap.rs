@@ -1,20 +1,100 @@
 use crate::typerec::*;
 
-// TODO warn whenever two variant-elements could be confused.
+use std::collections::HashMap;
 
-/// Wraps enum variant elements marked with `#[specr::indirection]`.
-pub(in crate::typerec) fn wrap_variant_elements(mods: &mut [Module]) -> HashSet<VariantElement> {
-    let mut elements = HashSet::new();
+// generic types from outside of the specr code, which store their arguments behind a pointer.
+// Their arguments don't contribute to the size of a type.
+static INDIRECT_TYPES: &[&str] = &["List", "Set", "Map", "GcCow", "Box", "Rc", "Arc", "Vec", "PhantomData"];
+
+/// Wraps the enum variant elements and struct fields marked with `#[specr::indirection]`,
+/// as well as the ones which need an indirection to break an infinitely-sized type cycle.
+/// Returns all wrapped elements, and the ones of them marked with `#[specr::indirection]`.
+pub(in crate::typerec) fn wrap_elements(mods: &mut [Module], resolver: &Resolver) -> (HashSet<Element>, HashSet<Element>) {
+    let mut elements = infer_elements(mods, resolver);
+    let mut explicit_elements = HashSet::new();
 
     for m in mods {
         for item in &mut m.ast.items {
-            if let Item::Enum(it_enum) = item {
-                elements.extend(wrap_enum(it_enum));
+            let Some((ty, fields)) = item_fields(&m.name, item) else { continue };
+
+            for (variant, idx, f) in fields {
+                let element = Element { ty: ty.clone(), variant, idx };
+                let explicit = match f.attrs.iter().position(is_indirection_attr) {
+                    Some(j) => { f.attrs.remove(j); true },
+                    None => false,
+                };
+                if !explicit && !elements.contains(&element) { continue; }
+                if explicit { explicit_elements.insert(element.clone()); }
+
+                let t = &f.ty;
+                let wrapped_ty = quote! { libspecr::hidden::GcCow<#t> };
+                f.ty = parse2(wrapped_ty).unwrap();
+
+                elements.insert(element);
+            }
+        }
+    }
+
+    (elements, explicit_elements)
+}
+
+/// Whether a struct field is wrapped, see `struct_fields`.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub(in crate::typerec) enum Wrapped {
+    No,
+    /// the field has an `#[specr::indirection]`.
+    Explicit,
+    /// the field closes an infinitely-sized type cycle.
+    Inferred,
+}
+
+/// The fields of all structs, by their name (or index for tuple structs).
+/// For each struct having a field of that name, this stores whether the field is wrapped,
+/// and the struct of the specr code the field contains, if any (looking through references and the wrapping).
+pub(in crate::typerec) fn struct_fields(mods: &[Module], elements: &HashSet<Element>, explicit: &HashSet<Element>, resolver: &Resolver) -> HashMap<String, Vec<(ItemPath, Wrapped, Option<ItemPath>)>> {
+    let mut fields: HashMap<String, Vec<(ItemPath, Wrapped, Option<ItemPath>)>> = HashMap::new();
+
+    for m in mods {
+        for item in &m.ast.items {
+            let Item::Struct(s) = item else { continue };
+            let ty = ItemPath { module: m.name.clone(), name: s.ident.to_string() };
+
+            for (i, f) in s.fields.iter().enumerate() {
+                let idx = element_idx(i, f);
+                let element = Element { ty: ty.clone(), variant: None, idx: idx.clone() };
+                let wrapped = match (explicit.contains(&element), elements.contains(&element)) {
+                    (true, _) => Wrapped::Explicit,
+                    (false, true) => Wrapped::Inferred,
+                    (false, false) => Wrapped::No,
+                };
+                let value = value_struct(&m.name, &f.ty, resolver);
+                fields.entry(idx.to_string()).or_default().push((ty.clone(), wrapped, value));
             }
         }
     }
 
-    elements
+    fields
+}
+
+// the struct of the specr code, which a field of type `ty` in `module` contains.
+fn value_struct(module: &str, ty: &Type, resolver: &Resolver) -> Option<ItemPath> {
+    match ty {
+        Type::Reference(r) => value_struct(module, &r.elem, resolver),
+        Type::Paren(p) => value_struct(module, &p.elem, resolver),
+        Type::Path(p) => {
+            let last = p.path.segments.last()?;
+            if last.ident == "GcCow" {
+                let PathArguments::AngleBracketed(args) = &last.arguments else { return None };
+                let Some(GenericArgument::Type(t)) = args.args.first() else { return None };
+                return value_struct(module, t, resolver);
+            }
+            match resolver.resolve_type(module, ty) {
+                Some(Def::Item(s)) => Some(s),
+                _ => None,
+            }
+        },
+        _ => None,
+    }
 }
 
 fn is_indirection_attr(attr: &Attribute) -> bool {
@@ -22,34 +102,140 @@ fn is_indirection_attr(attr: &Attribute) -> bool {
     s == "specr::indirection"
 }
 
-fn wrap_enum(it_enum: &mut ItemEnum) -> HashSet<VariantElement> {
-    let mut elements = HashSet::new();
+fn element_idx(i: usize, f: &Field) -> ElementIdx {
+    match &f.ident {
+        Some(id) => ElementIdx::Named(id.clone()),
+        None => ElementIdx::Unnamed(i),
+    }
+}
 
-    for variant in &mut it_enum.variants {
-        let fields: Vec<&mut Field> = match &mut variant.fields {
-            Fields::Named(x) => x.named.iter_mut().collect(),
-            Fields::Unnamed(x) => x.unnamed.iter_mut().collect(),
-            Fields::Unit => Vec::new(),
-        };
+// a field, together with the enum variant it belongs to.
+type ItemField<'a> = (Option<Ident>, ElementIdx, &'a mut Field);
 
-        for (i, f) in fields.into_iter().enumerate() {
-            if let Some(j) = f.attrs.iter().position(is_indirection_attr) {
-                f.attrs.remove(j);
+// the fields of a struct, or of all variants of an enum.
+fn item_fields<'a>(module: &str, item: &'a mut Item) -> Option<(ItemPath, Vec<ItemField<'a>>)> {
+    let (ident, fields): (&Ident, Vec<(Option<Ident>, &mut Fields)>) = match item {
+        Item::Enum(e) => (&e.ident, e.variants.iter_mut().map(|v| (Some(v.ident.clone()), &mut v.fields)).collect()),
+        Item::Struct(s) => (&s.ident, vec![(None, &mut s.fields)]),
+        _ => return None,
+    };
+    let ty = ItemPath { module: module.to_string(), name: ident.to_string() };
 
-                let t = &f.ty;
-                let wrapped_ty = quote! { libspecr::hidden::GcCow<#t> };
-                f.ty = parse2(wrapped_ty).unwrap();
+    let fields = fields.into_iter()
+                       .flat_map(|(variant, fields)| {
+                           fields.iter_mut()
+                                 .enumerate()
+                                 .map(move |(i, f)| (variant.clone(), element_idx(i, f), f))
+                       })
+                       .collect();
 
-                let idx = match &f.ident {
-                    Some(id) => ElementIdx::Named(id.clone()),
-                    None => ElementIdx::Unnamed(i),
-                };
-                let variant = variant.ident.clone();
-                elements.insert(VariantElement { variant, idx });
+    Some((ty, fields))
+}
+
+// An enum or struct, and the types each of its elements contains without indirection.
+struct TypeDef {
+    elements: Vec<(Element, Vec<ItemPath>)>,
+}
+
+// finds the elements which need an indirection, as the type containing them would have infinite size otherwise.
+//
+// The types contain each other without indirection form a graph, whose cycles need to be broken.
+// We search the graph depth-first, and wrap each element which leads back to a type on the stack.
+fn infer_elements(mods: &[Module], resolver: &Resolver) -> HashSet<Element> {
+    let mut order = Vec::new();
+    let mut defs = HashMap::new();
+
+    for m in mods {
+        for item in &m.ast.items {
+            // `item_fields` needs mutable access, but we only read.
+            let mut item = item.clone();
+            let Some((ty, fields)) = item_fields(&m.name, &mut item) else { continue };
+
+            let self_ty = resolver.resolve_type(&m.name, &parse_str(&ty.name).unwrap());
+            let elements = fields.into_iter()
+                                 .filter(|(_, _, f)| !f.attrs.iter().any(is_indirection_attr))
+                                 .map(|(variant, idx, f)| {
+                                     let mut contained = Vec::new();
+                                     contained_types(&f.ty, &m.name, self_ty.as_ref(), resolver, &mut contained);
+                                     (Element { ty: ty.clone(), variant, idx }, contained)
+                                 })
+                                 .collect();
+
+            order.push(ty.clone());
+            defs.insert(ty, TypeDef { elements });
+        }
+    }
+
+    let mut search = Search { defs: &defs, stack: Vec::new(), visited: HashSet::new(), wrapped: HashSet::new() };
+    for ty in &order {
+        search.visit(ty);
+    }
+
+    search.wrapped
+}
+
+struct Search<'a> {
+    defs: &'a HashMap<ItemPath, TypeDef>,
+    stack: Vec<&'a ItemPath>,
+    visited: HashSet<&'a ItemPath>,
+    wrapped: HashSet<Element>,
+}
+
+impl<'a> Search<'a> {
+    fn visit(&mut self, ty: &'a ItemPath) {
+        if !self.visited.insert(ty) { return; }
+        let Some(def) = self.defs.get(ty) else { return };
+
+        self.stack.push(ty);
+        for (element, contained) in &def.elements {
+            // wrapping an element removes all its edges, so we don't follow them anymore.
+            if contained.iter().any(|t| self.stack.contains(&t)) {
+                self.wrapped.insert(element.clone());
+                continue;
+            }
+
+            for t in contained {
+                self.visit(t);
             }
-            
         }
+        self.stack.pop();
     }
+}
+
+// collects the enums and structs of the specr code that `ty` contains without indirection.
+// Generic types of the specr code are assumed to contain their arguments without indirection.
+fn contained_types(ty: &Type, module: &str, self_ty: Option<&Def>, resolver: &Resolver, out: &mut Vec<ItemPath>) {
+    match ty {
+        Type::Path(p) if p.qself.is_none() => {
+            let is_indirect = match resolver.resolve(module, self_ty, &p.path) {
+                Ok(Def::Enum(e) | Def::Item(e)) => {
+                    out.push(e);
+                    false
+                },
+                _ => {
+                    let last = p.path.segments.last().map(|s| s.ident.to_string());
+                    last.map_or(false, |l| INDIRECT_TYPES.contains(&&*l))
+                },
+            };
+            if is_indirect { return; }
 
-    elements
+            for s in &p.path.segments {
+                let PathArguments::AngleBracketed(args) = &s.arguments else { continue };
+                for arg in &args.args {
+                    let GenericArgument::Type(t) = arg else { continue };
+                    contained_types(t, module, self_ty, resolver, out);
+                }
+            }
+        },
+        Type::Tuple(t) => {
+            for t in &t.elems {
+                contained_types(t, module, self_ty, resolver, out);
+            }
+        },
+        Type::Array(a) => contained_types(&a.elem, module, self_ty, resolver, out),
+        Type::Paren(p) => contained_types(&p.elem, module, self_ty, resolver, out),
+        Type::Group(g) => contained_types(&g.elem, module, self_ty, resolver, out),
+        // references, pointers, slices and trait objects are indirections already.
+        _ => {},
+    }
 }
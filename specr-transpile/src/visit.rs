@@ -0,0 +1,459 @@
+use crate::prelude::*;
+use crate::resolve::{Resolver, Def, ItemPath};
+
+use std::collections::HashMap;
+
+/// The module generated for the traits.
+pub const VISIT_MOD: &str = "visit";
+
+/// The module generated within each module, containing the functions which walk its types.
+/// They are generated there, as private fields are only visible within the module of their type.
+const WALK_MOD: &str = "specr_visit";
+
+/// Generates the traits `Visit`, `VisitMut` and `Fold` for all structs & enums of the crate, like those of `syn`.
+///
+/// For each type `Foo`, the traits have the methods `visit_foo(&mut self, node: &Foo)`, `visit_foo_mut(&mut self, node: &mut Foo)`
+/// and `fold_foo(&mut self, node: Foo) -> Foo`, which by default call the functions `visit::visit_foo` etc.
+/// These call the methods of the types of all fields, which contain structs or enums of the crate.
+/// If the name of a type is not unique within the crate, the module path is prepended, like `visit_lang_foo`.
+///
+/// Fields within `GcCow`, `List`, `Set`, `Map`, `Option`, tuples and arrays are walked as well.
+/// `VisitMut` writes the mutated elements back. Types with generic parameters are not walked.
+pub fn visit(mut mods: Vec<Module>, diag: &mut Diagnostics) -> Vec<Module> {
+    if let Some(m) = mods.iter().find(|m| m.name == VISIT_MOD) {
+        diag.error(&m.source_map, Span::call_site(), format!("the `visit` pass generates the module `{VISIT_MOD}`, which already exists"));
+        return mods;
+    }
+
+    let resolver = Resolver::new(&mods);
+    let types = types(&mods, diag);
+    let aliases = aliases(&mods);
+
+    let mut methods = Vec::new();
+    let mut exports = Vec::new();
+    for m in mods.iter_mut() {
+        let mut fns = Vec::new();
+        for item in &m.ast.items {
+            let (ident, generics) = match item {
+                Item::Struct(s) => (&s.ident, &s.generics),
+                Item::Enum(e) => (&e.ident, &e.generics),
+                _ => continue,
+            };
+            if !generics.params.is_empty() { continue; }
+
+            let ty = ItemPath { module: m.name.clone(), name: ident.to_string() };
+            let walk = Walk { types: &types, aliases: &aliases, resolver: &resolver, module: &m.name };
+            let names = Names::new(&types[&ty]);
+            fns.push(walk.item(item, &names));
+            methods.push(names.methods(&ty));
+
+            let module = m.name.split("::").map(|s| format_ident!("{s}"));
+            let walk_mod = format_ident!("{WALK_MOD}");
+            let Names { visit, visit_mut, fold } = names;
+            exports.push(quote! { pub use crate::#(#module::)*#walk_mod::{#visit, #visit_mut, #fold}; });
+        }
+        if fns.is_empty() { continue; }
+
+        let walk_mod = format_ident!("{WALK_MOD}");
+        m.ast.items.push(parse_quote! {
+            #[doc(hidden)]
+            #[allow(unused_variables, unused_mut, clippy::all)]
+            pub mod #walk_mod {
+                use super::*;
+                use crate::visit::{Visit, VisitMut, Fold};
+
+                #(#fns)*
+            }
+        });
+    }
+
+    let visit: Vec<&TokenStream> = methods.iter().map(|(v, _, _)| v).collect();
+    let visit_mut: Vec<&TokenStream> = methods.iter().map(|(_, v, _)| v).collect();
+    let fold: Vec<&TokenStream> = methods.iter().map(|(_, _, f)| f).collect();
+    let ast = parse_quote! {
+        //! Traversals of the types of the crate, generated by the `visit` pass of specr-transpile.
+
+        /// Visits a value by reference. Override the methods of the types you are interested in,
+        /// and call `visit::visit_<type>` from them to visit their fields.
+        pub trait Visit {
+            #(#visit)*
+        }
+
+        /// Visits a value by mutable reference, like `Visit`.
+        pub trait VisitMut {
+            #(#visit_mut)*
+        }
+
+        /// Transforms a value, like `Visit`.
+        pub trait Fold {
+            #(#fold)*
+        }
+
+        #(#exports)*
+    };
+    mods.push(Module::new(VISIT_MOD, ast));
+
+    mods
+}
+
+// the types of all type aliases without generic parameters.
+fn aliases(mods: &[Module]) -> HashMap<ItemPath, Type> {
+    let mut aliases = HashMap::new();
+    for m in mods {
+        for item in &m.ast.items {
+            let Item::Type(t) = item else { continue };
+            if !t.generics.params.is_empty() { continue; }
+            aliases.insert(ItemPath { module: m.name.clone(), name: t.ident.to_string() }, (*t.ty).clone());
+        }
+    }
+
+    aliases
+}
+
+// the method names of each type.
+// Names which collide, like `visit_foo_mut` of `Foo` and `visit_foo_mut` of `FooMut`, get the module path prepended.
+// If that is not enough, an error is reported.
+fn types(mods: &[Module], diag: &mut Diagnostics) -> HashMap<ItemPath, String> {
+    let mut paths = Vec::new();
+    for m in mods {
+        for item in &m.ast.items {
+            match item {
+                Item::Struct(s) if s.generics.params.is_empty() => paths.push((ItemPath { module: m.name.clone(), name: s.ident.to_string() }, m, s.ident.span())),
+                Item::Enum(e) if e.generics.params.is_empty() => paths.push((ItemPath { module: m.name.clone(), name: e.ident.to_string() }, m, e.ident.span())),
+                _ => {},
+            }
+        }
+    }
+
+    let all: Vec<ItemPath> = paths.iter().map(|(p, _, _)| p.clone()).collect();
+    let mut names: HashMap<ItemPath, String> = all.iter().map(|p| (p.clone(), snake_case(&p.name))).collect();
+    let colliding_paths: Vec<&ItemPath> = all.iter().filter(|p| colliding(p, &all, &names).is_some()).collect();
+    for p in colliding_paths {
+        names.insert(p.clone(), format!("{}_{}", p.module.replace("::", "_"), snake_case(&p.name)));
+    }
+
+    // each collision is reported at the second of the two types.
+    for (i, (p, m, span)) in paths.iter().enumerate() {
+        if let Some((method, other)) = colliding(p, &all[..i], &names) {
+            diag.error(&m.source_map, *span, format!("the `visit` pass would generate the method `{method}` for both `{p}` and `{other}`, rename one of them"));
+        }
+    }
+
+    names
+}
+
+// returns a method name of `p` which one of the other types in `paths` has as well, together with that type.
+fn colliding<'a>(p: &ItemPath, paths: &'a [ItemPath], names: &HashMap<ItemPath, String>) -> Option<(Ident, &'a ItemPath)> {
+    let methods = Names::new(&names[p]).all();
+    paths.iter()
+         .filter(|q| *q != p)
+         .find_map(|q| {
+             let other = Names::new(&names[q]).all();
+             let method = methods.iter().find(|m| other.contains(m))?;
+             Some((method.clone(), q))
+         })
+}
+
+// converts a type name to snake case. Acronyms are kept together, like `UB` to `ub` and `HTTPServer` to `http_server`.
+fn snake_case(s: &str) -> String {
+    let chars: Vec<char> = s.chars().collect();
+    let mut out = String::new();
+    for (i, &c) in chars.iter().enumerate() {
+        if c.is_uppercase() && i > 0 {
+            let prev = chars[i-1];
+            let next_lower = chars.get(i+1).map_or(false, |n| n.is_lowercase());
+            if prev.is_lowercase() || prev.is_ascii_digit() || prev.is_uppercase() && next_lower {
+                out.push('_');
+            }
+        }
+        out.extend(c.to_lowercase());
+    }
+
+    out
+}
+
+// the names of the methods and functions of a type.
+struct Names {
+    visit: Ident,
+    visit_mut: Ident,
+    fold: Ident,
+}
+
+impl Names {
+    fn new(name: &str) -> Names {
+        Names {
+            visit: format_ident!("visit_{name}"),
+            visit_mut: format_ident!("visit_{name}_mut"),
+            fold: format_ident!("fold_{name}"),
+        }
+    }
+
+    fn all(self) -> [Ident; 3] {
+        [self.visit, self.visit_mut, self.fold]
+    }
+
+    // the trait methods of the type `ty`.
+    fn methods(&self, ty: &ItemPath) -> (TokenStream, TokenStream, TokenStream) {
+        let Names { visit, visit_mut, fold } = self;
+        let ty = ty_path(ty);
+        (
+            quote! { fn #visit(&mut self, node: &#ty) { #visit(self, node) } },
+            quote! { fn #visit_mut(&mut self, node: &mut #ty) { #visit_mut(self, node) } },
+            quote! { fn #fold(&mut self, node: #ty) -> #ty { #fold(self, node) } },
+        )
+    }
+}
+
+fn ty_path(ty: &ItemPath) -> TokenStream {
+    let module = ty.module.split("::").map(|s| format_ident!("{s}"));
+    let name = format_ident!("{}", ty.name);
+    quote! { crate::#(#module::)*#name }
+}
+
+// generates the code walking the types of a module.
+struct Walk<'a> {
+    types: &'a HashMap<ItemPath, String>,
+    aliases: &'a HashMap<ItemPath, Type>,
+    resolver: &'a Resolver,
+
+    /// the module of the walked type.
+    module: &'a str,
+}
+
+// the code of a walk in each of the traits, or `None` if the walked type contains no types of the crate.
+struct Code {
+    visit: TokenStream,
+    visit_mut: TokenStream,
+    fold: TokenStream,
+}
+
+impl Walk<'_> {
+    // the functions walking the fields of a struct or enum.
+    fn item(&self, item: &Item, names: &Names) -> TokenStream {
+        let (visit, visit_mut, fold) = match item {
+            Item::Struct(s) => self.walk_struct(s),
+            Item::Enum(e) => self.walk_enum(e),
+            _ => unreachable!(),
+        };
+        let ident = match item {
+            Item::Struct(s) => &s.ident,
+            Item::Enum(e) => &e.ident,
+            _ => unreachable!(),
+        };
+
+        let Names { visit: v, visit_mut: vm, fold: f } = names;
+        quote! {
+            pub fn #v<V: Visit + ?Sized>(v: &mut V, node: &#ident) { #visit }
+            pub fn #vm<V: VisitMut + ?Sized>(v: &mut V, node: &mut #ident) { #visit_mut }
+            pub fn #f<V: Fold + ?Sized>(v: &mut V, node: #ident) -> #ident { #fold }
+        }
+    }
+
+    fn walk_struct(&self, s: &ItemStruct) -> (TokenStream, TokenStream, TokenStream) {
+        let mut visit = Vec::new();
+        let mut visit_mut = Vec::new();
+        let mut fold = Vec::new();
+        for (i, f) in s.fields.iter().enumerate() {
+            let member: Member = match &f.ident {
+                Some(id) => Member::Named(id.clone()),
+                None => Member::Unnamed(i.into()),
+            };
+            let Some(code) = self.code(&f.ty, self.module, 0) else { continue };
+            let Code { visit: cv, visit_mut: cvm, fold: cf } = code;
+            visit.push(quote! { { let x0 = &node.#member; #cv } });
+            visit_mut.push(quote! { { let x0 = &mut node.#member; #cvm } });
+            fold.push(quote! { { let x0 = node.#member; node.#member = #cf; } });
+        }
+
+        (
+            quote! { #(#visit)* },
+            quote! { #(#visit_mut)* },
+            quote! { let mut node = node; #(#fold)* node },
+        )
+    }
+
+    fn walk_enum(&self, e: &ItemEnum) -> (TokenStream, TokenStream, TokenStream) {
+        let ident = &e.ident;
+        let mut visit = Vec::new();
+        let mut visit_mut = Vec::new();
+        let mut fold = Vec::new();
+        for var in &e.variants {
+            let var_ident = &var.ident;
+            let bindings: Vec<Ident> = (0..var.fields.len()).map(|i| format_ident!("f{i}")).collect();
+            let pat = match &var.fields {
+                Fields::Named(n) => {
+                    let names = n.named.iter().map(|f| f.ident.as_ref().unwrap());
+                    quote! { #ident::#var_ident { #(#names: #bindings),* } }
+                },
+                Fields::Unnamed(_) => quote! { #ident::#var_ident(#(#bindings),*) },
+                Fields::Unit => quote! { #ident::#var_ident },
+            };
+
+            let mut cv = Vec::new();
+            let mut cvm = Vec::new();
+            let mut cf = Vec::new();
+            for (f, b) in var.fields.iter().zip(&bindings) {
+                let Some(Code { visit, visit_mut, fold }) = self.code(&f.ty, self.module, 0) else { continue };
+                cv.push(quote! { { let x0 = #b; #visit } });
+                cvm.push(quote! { { let x0 = #b; #visit_mut } });
+                cf.push(quote! { let #b = { let x0 = #b; #fold }; });
+            }
+
+            visit.push(quote! { #pat => { #(#cv)* }, });
+            visit_mut.push(quote! { #pat => { #(#cvm)* }, });
+            fold.push(quote! { #pat => { #(#cf)* #pat }, });
+        }
+
+        // a reference to an enum without variants still needs to be matched.
+        if e.variants.is_empty() {
+            return (quote! { match *node {} }, quote! { match *node {} }, quote! { match node {} });
+        }
+
+        (
+            quote! { match node { #(#visit)* } },
+            quote! { match node { #(#visit_mut)* } },
+            quote! { match node { #(#fold)* } },
+        )
+    }
+
+    // the code walking `x{depth}` of type `ty`, as written in `module`.
+    // For `visit`, `x{depth}` is a `&ty`, for `visit_mut` a `&mut ty`, and for `fold` a `ty`.
+    fn code(&self, ty: &Type, module: &str, depth: usize) -> Option<Code> {
+        let x = format_ident!("x{depth}");
+        let y = format_ident!("x{}", depth + 1);
+
+        match ty {
+            Type::Path(p) if p.qself.is_none() => {
+                if let Ok(Def::Enum(path) | Def::Item(path)) = self.resolver.resolve(module, None, &p.path) {
+                    if let Some(t) = self.aliases.get(&path) {
+                        return self.code(t, &path.module, depth);
+                    }
+                    let name = self.types.get(&path)?;
+                    let Names { visit, visit_mut, fold } = Names::new(name);
+                    return Some(Code {
+                        visit: quote! { v.#visit(#x); },
+                        visit_mut: quote! { v.#visit_mut(#x); },
+                        fold: quote! { v.#fold(#x) },
+                    });
+                }
+
+                let last = p.path.segments.last()?;
+                let PathArguments::AngleBracketed(args) = &last.arguments else { return None };
+                let args: Vec<&Type> = args.args.iter().filter_map(|a| match a {
+                    GenericArgument::Type(t) => Some(t),
+                    _ => None,
+                }).collect();
+
+                match (&*last.ident.to_string(), &args[..]) {
+                    ("GcCow", [t]) => {
+                        let Code { visit, visit_mut, fold } = self.code(t, module, depth + 1)?;
+                        Some(Code {
+                            visit: quote! { let #y = &#x.extract(); #visit },
+                            visit_mut: quote! { let mut guard = #x.mutate_guard(); let #y = &mut *guard; #visit_mut },
+                            fold: quote! { libspecr::hidden::GcCow::new({ let #y = #x.extract(); #fold }) },
+                        })
+                    },
+                    ("List" | "Set", [t]) => {
+                        let Code { visit, visit_mut, fold } = self.code(t, module, depth + 1)?;
+                        Some(Code {
+                            visit: quote! { for #y in #x.iter() { let #y = &#y; #visit } },
+                            visit_mut: quote! { *#x = #x.iter().map(|mut #y| { { let #y = &mut #y; #visit_mut } #y }).collect(); },
+                            fold: quote! { #x.iter().map(|#y| #fold).collect() },
+                        })
+                    },
+                    ("Map", [k, val]) => {
+                        let k = self.code(k, module, depth + 1);
+                        let val = self.code(val, module, depth + 1);
+                        if k.is_none() && val.is_none() { return None; }
+                        let (kv, kvm, kf) = split(k, &y);
+                        let (vv, vvm, vf) = split(val, &y);
+                        Some(Code {
+                            visit: quote! { for (k, val) in #x.iter() { { let #y = &k; #kv } { let #y = &val; #vv } } },
+                            visit_mut: quote! {
+                                *#x = #x.iter().map(|(mut k, mut val)| { { let #y = &mut k; #kvm } { let #y = &mut val; #vvm } (k, val) }).collect();
+                            },
+                            fold: quote! { #x.iter().map(|(k, val)| ({ let #y = k; #kf }, { let #y = val; #vf })).collect() },
+                        })
+                    },
+                    ("Option", [t]) => {
+                        let Code { visit, visit_mut, fold } = self.code(t, module, depth + 1)?;
+                        Some(Code {
+                            visit: quote! { if let Some(#y) = #x { #visit } },
+                            visit_mut: quote! { if let Some(#y) = #x { #visit_mut } },
+                            fold: quote! { #x.map(|#y| #fold) },
+                        })
+                    },
+                    _ => None,
+                }
+            },
+            Type::Tuple(t) => {
+                let elems: Vec<Option<Code>> = t.elems.iter().map(|t| self.code(t, module, depth + 1)).collect();
+                if elems.iter().all(Option::is_none) { return None; }
+
+                let mut visit = Vec::new();
+                let mut visit_mut = Vec::new();
+                let mut fold = Vec::new();
+                for (i, c) in elems.into_iter().enumerate() {
+                    let i = Index::from(i);
+                    match c {
+                        Some(Code { visit: v, visit_mut: vm, fold: f }) => {
+                            visit.push(quote! { { let #y = &#x.#i; #v } });
+                            visit_mut.push(quote! { { let #y = &mut #x.#i; #vm } });
+                            fold.push(quote! { { let #y = #x.#i; #f }, });
+                        },
+                        None => fold.push(quote! { #x.#i, }),
+                    }
+                }
+                Some(Code {
+                    visit: quote! { #(#visit)* },
+                    visit_mut: quote! { #(#visit_mut)* },
+                    fold: quote! { ( #(#fold)* ) },
+                })
+            },
+            Type::Array(a) => {
+                let Code { visit, visit_mut, fold } = self.code(&a.elem, module, depth + 1)?;
+                Some(Code {
+                    visit: quote! { for #y in #x.iter() { #visit } },
+                    visit_mut: quote! { for #y in #x.iter_mut() { #visit_mut } },
+                    fold: quote! { #x.map(|#y| #fold) },
+                })
+            },
+            Type::Paren(p) => self.code(&p.elem, module, depth),
+            Type::Group(g) => self.code(&g.elem, module, depth),
+            _ => None,
+        }
+    }
+}
+
+// the code of an element, which leaves it unchanged if it contains no types of the crate.
+fn split(code: Option<Code>, x: &Ident) -> (TokenStream, TokenStream, TokenStream) {
+    match code {
+        Some(Code { visit, visit_mut, fold }) => (visit, visit_mut, fold),
+        None => (quote! {}, quote! {}, quote! { #x }),
+    }
+}
+
+#[test]
+fn test_names() {
+    let lang = Module::new("lang", parse_quote! {
+        pub struct Foo(u32);
+        pub enum UB { A }
+        pub struct HTTPServer(u32);
+    });
+    let mem = Module::new("mem", parse_quote! {
+        pub struct FooMut(u32);
+        pub struct Foo_Mut(u32);
+    });
+
+    let mut diag = Diagnostics::default();
+    let types = types(&[lang, mem], &mut diag);
+
+    let name = |module: &str, name: &str| &types[&ItemPath { module: module.to_string(), name: name.to_string() }];
+    assert_eq!(name("lang", "Foo"), "lang_foo");
+    assert_eq!(name("lang", "UB"), "ub");
+    assert_eq!(name("lang", "HTTPServer"), "http_server");
+    assert_eq!(name("mem", "FooMut"), "mem_foo_mut");
+    assert_eq!(diag.messages(), [
+        "the `visit` pass would generate the method `visit_mem_foo_mut` for both `mem::Foo_Mut` and `mem::FooMut`, rename one of them",
+    ]);
+}
@@ -0,0 +1,26 @@
+use crate::prelude::*;
+
+use std::thread::sleep;
+use std::time::Duration;
+
+// how often we look for changes of the input files.
+const POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Transpiles again whenever the Markdown files in the input directory change.
+/// Changes of the config file require a restart.
+pub fn watch(config: &Config) -> ! {
+    let mut last_hash = None;
+
+    loop {
+        let hash = crate::cache::input_hash(config);
+        if last_hash != Some(hash) {
+            last_hash = Some(hash);
+
+            // errors have already been reported, we just wait for them to be fixed.
+            let _ = crate::run(config);
+            eprintln!("Watching `{}` for changes...", config.input);
+        }
+
+        sleep(POLL_INTERVAL);
+    }
+}
@@ -0,0 +1,83 @@
+//! Golden tests for the passes.
+//!
+//! Each directory in `tests/golden` contains an `input.md`, which is transpiled as a module named like the directory.
+//! For each `<pass>.rs` in the directory, the code of the module after this pass has to match the content of the file.
+//!
+//! Run with `SPECR_BLESS=1` to update the `<pass>.rs` files to the current output.
+
+use specr_transpile::prelude::*;
+use specr_transpile::{source, pipeline};
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+fn cases() -> Vec<PathBuf> {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/golden");
+    let mut cases: Vec<PathBuf> = fs::read_dir(dir).unwrap()
+                                                   .map(|f| f.unwrap().path())
+                                                   .filter(|f| f.is_dir())
+                                                   .collect();
+    cases.sort();
+
+    cases
+}
+
+// returns the code of the module after each pass.
+fn run_case(case: &Path) -> HashMap<&'static str, String> {
+    let name = case.file_name().unwrap().to_string_lossy();
+    let input = fs::read_to_string(case.join("input.md")).unwrap();
+
+    let mut diag = Diagnostics::default();
+    let m = source::from_markdown(&name, &[("input.md".to_string(), input)], &mut diag);
+    assert!(!diag.has_errors(), "`{name}` doesn't parse");
+    let m = m.unwrap_or_else(|| panic!("`{name}` contains no code"));
+
+    // the optional passes run as well, so that they can be tested.
+    let optional: Vec<&str> = pipeline::PASSES.iter().filter(|p| p.optional).map(|p| p.name).collect();
+
+    let mut out = HashMap::new();
+    let res = pipeline::run(vec![m], &optional, &mut diag, |pass, mods| {
+        // modules generated by a pass follow the module of the case.
+        let mut code = prettyplease::unparse(&mods[0].ast);
+        for m in &mods[1..] {
+            code.push_str(&format!("\n// mod {}\n{}", m.name, prettyplease::unparse(&m.ast)));
+        }
+        out.insert(pass.name, code);
+    });
+    assert!(res.is_ok(), "`{name}` fails to transpile");
+
+    out
+}
+
+#[test]
+fn golden() {
+    let bless = std::env::var_os("SPECR_BLESS").is_some();
+    let mut failed = Vec::new();
+
+    for case in cases() {
+        let out = run_case(&case);
+        for p in pipeline::passes() {
+            let path = case.join(format!("{}.rs", p.name));
+            if !path.exists() { continue; }
+
+            let actual = &out[p.name];
+            if bless {
+                fs::write(&path, actual).unwrap();
+            } else if fs::read_to_string(&path).unwrap() != *actual {
+                eprintln!("{} differs, the actual output is:\n{actual}", path.display());
+                failed.push(path);
+            }
+        }
+    }
+
+    assert!(failed.is_empty(), "golden files differ: {failed:?}, run with `SPECR_BLESS=1` to update them");
+}
+
+#[test]
+fn every_pass_is_tested() {
+    for p in pipeline::passes() {
+        let tested = cases().iter().any(|c| c.join(format!("{}.rs", p.name)).exists());
+        assert!(tested, "no golden test for the pass `{}`", p.name);
+    }
+}
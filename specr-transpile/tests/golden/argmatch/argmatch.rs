@@ -0,0 +1,9 @@
+pub struct Foo;
+impl Foo {
+    fn foo(&self, x: Option<i32>) -> i32 {
+        match x {
+            Some(a) => a,
+            None => 0,
+        }
+    }
+}
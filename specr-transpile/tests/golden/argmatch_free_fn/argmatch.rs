@@ -0,0 +1,23 @@
+pub enum Op {
+    Add,
+    Sub,
+    Neg,
+}
+pub trait Eval {
+    fn eval(self, a: u32, b: u32) -> u32;
+}
+pub fn commutative(op: Op) -> bool {
+    match op {
+        Op::Add => true,
+        _ => false,
+    }
+}
+impl Eval for Op {
+    fn eval(self, a: u32, b: u32) -> u32 {
+        match self {
+            Op::Add => a + b,
+            Op::Sub => a - b,
+            Op::Neg => 0 - a,
+        }
+    }
+}
@@ -0,0 +1,18 @@
+pub enum BinOp {
+    Add,
+    Sub,
+}
+pub enum Ty {
+    Int,
+    Bool,
+}
+pub struct Machine;
+impl Machine {
+    fn eval(&self, op: BinOp, ty: Ty, a: u32, b: u32) -> u32 {
+        match (op, ty) {
+            (BinOp::Add, Ty::Int) => a + b,
+            (BinOp::Sub, Ty::Int) if a >= b => a - b,
+            (_, _) => 0,
+        }
+    }
+}
@@ -0,0 +1,41 @@
+#[derive(GcCompat)]
+#[derive(Debug)]
+#[derive(libspecr::Encode)]
+#[derive(Clone)]
+#[derive(Copy)]
+#[derive(PartialEq)]
+#[derive(Eq)]
+#[derive(Hash)]
+pub struct Point {
+    x: u32,
+    y: u32,
+}
+#[derive(Clone)]
+#[derive(GcCompat)]
+#[derive(Debug)]
+#[derive(libspecr::Encode)]
+#[derive(Copy)]
+#[derive(PartialEq)]
+#[derive(Eq)]
+#[derive(Hash)]
+pub enum Dir {
+    Up,
+    Down,
+}
+#[derive(GcCompat)]
+#[derive(Debug)]
+#[derive(libspecr::Encode)]
+pub struct Machine {
+    points: List<Point>,
+}
+#[derive(PartialOrd, Ord)]
+#[derive(GcCompat)]
+#[derive(libspecr::Encode)]
+#[derive(Clone)]
+#[derive(Copy)]
+#[derive(PartialEq)]
+#[derive(Eq)]
+pub enum Level {
+    Low,
+    High,
+}
@@ -0,0 +1,19 @@
+#[derive(GcCompat)]
+#[derive(Debug)]
+#[derive(libspecr::Encode)]
+#[derive(Clone)]
+#[derive(Copy)]
+#[derive(PartialEq)]
+#[derive(Eq)]
+#[derive(Hash)]
+pub struct Wrapper<T: libspecr::hidden::Obj> {
+    inner: T,
+}
+impl<T: libspecr::hidden::Obj> Wrapper<T> {
+    pub fn get(self) -> T {
+        self.inner
+    }
+}
+pub trait Get<T: libspecr::hidden::Obj>: libspecr::hidden::Obj {
+    fn get(self) -> T;
+}
@@ -0,0 +1,58 @@
+pub enum Expr {
+    Lit(Int),
+    Neg(libspecr::hidden::GcCow<Expr>),
+    BinOp {
+        op: BinOp,
+        lhs: libspecr::hidden::GcCow<Expr>,
+        rhs: libspecr::hidden::GcCow<Expr>,
+    },
+    Tuple(Int, List<Expr>),
+}
+pub enum BinOp {
+    Add,
+    Mul,
+}
+pub struct Let {
+    name: Int,
+    init: libspecr::hidden::GcCow<Expr>,
+    mutable: bool,
+}
+pub struct Wrap<T>(T);
+impl std::fmt::Display for Expr {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Expr::Lit(_0) => write!(f, "{_0}"),
+            Expr::Neg(_0) => {
+                let _0 = &_0.extract();
+                write!(f, "-{_0}")
+            }
+            Expr::BinOp { lhs, op, rhs, .. } => {
+                let lhs = &lhs.extract();
+                let rhs = &rhs.extract();
+                write!(f, "({lhs} {op} {rhs})")
+            }
+            Expr::Tuple(_, _1) => write!(f, "{{ {_1:?} }}"),
+        }
+    }
+}
+impl std::fmt::Display for BinOp {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            BinOp::Add => write!(f, "+"),
+            BinOp::Mul => write!(f, "*"),
+        }
+    }
+}
+impl std::fmt::Display for Let {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = &self.name;
+        let init = &self.init.extract();
+        write!(f, "let {name} = {init};")
+    }
+}
+impl<T: std::fmt::Display> std::fmt::Display for Wrap<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let _0 = &self.0;
+        write!(f, "{_0}")
+    }
+}
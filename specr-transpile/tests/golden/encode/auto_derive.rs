@@ -0,0 +1,38 @@
+#[derive(GcCompat)]
+#[derive(Debug)]
+#[derive(libspecr::Encode)]
+#[derive(Clone)]
+#[derive(Copy)]
+#[derive(PartialEq)]
+#[derive(Eq)]
+#[derive(Hash)]
+pub enum Value {
+    Int(Int),
+    Tuple(List<Value>),
+}
+#[derive(GcCompat)]
+#[derive(Debug)]
+#[derive(libspecr::Encode)]
+#[derive(Clone)]
+#[derive(Copy)]
+#[derive(PartialEq)]
+#[derive(Eq)]
+#[derive(Hash)]
+pub struct Frame {
+    locals: Map<Name, Value>,
+}
+#[derive(GcCompat)]
+#[derive(Debug)]
+pub struct Machine {
+    stdout: DynWrite,
+}
+use libspecr::Encode;
+#[derive(Encode)]
+#[derive(GcCompat)]
+#[derive(Debug)]
+#[derive(Clone)]
+#[derive(Copy)]
+#[derive(PartialEq)]
+#[derive(Eq)]
+#[derive(Hash)]
+pub struct Ptr(Int);
@@ -0,0 +1,52 @@
+#[derive(GcCompat)]
+#[derive(Debug)]
+#[derive(libspecr::Encode)]
+#[derive(Clone)]
+#[derive(Copy)]
+#[derive(PartialEq)]
+#[derive(Eq)]
+#[derive(Hash)]
+pub struct Program {
+    start: u32,
+}
+#[derive(GcCompat)]
+#[derive(Debug)]
+#[derive(libspecr::Encode)]
+#[derive(Clone)]
+#[derive(Copy)]
+#[derive(PartialEq)]
+#[derive(Eq)]
+#[derive(Hash)]
+pub struct Machine {
+    pc: u32,
+}
+impl Machine {
+    pub fn step(&mut self, prog: &Program, n: u32) -> Option<u32> {
+        let __specr_out = (|| -> Option<u32> {
+            if self.pc == prog.start {
+                return None;
+            }
+            self.pc += n;
+            Some(self.pc)
+        })();
+        Self::step_gc_roots(&*self, &*prog, &__specr_out);
+        __specr_out
+    }
+}
+pub fn reset(m: &mut Machine, prog: &Program, pc: u32, reason: &str) {
+    let __specr_out = (|| -> () {
+        m.pc = pc;
+    })();
+    reset_gc_roots(&*m, &*prog, &__specr_out);
+    __specr_out
+}
+impl Machine {
+    /// Collects every object which is not reachable from the roots of `step`.
+    pub fn step_gc_roots(&self, prog: &Program, __specr_out: &Option<u32>) {
+        libspecr::hidden::mark_and_sweep_roots(&[&*self, &*prog, __specr_out]);
+    }
+}
+/// Collects every object which is not reachable from the roots of `reset`.
+pub fn reset_gc_roots(m: &Machine, prog: &Program, __specr_out: &()) {
+    libspecr::hidden::mark_and_sweep_roots(&[&*m, &*prog, __specr_out]);
+}
@@ -0,0 +1,3 @@
+pub fn second(l: List<u32>) -> u32 {
+    (l).index_at(Int::from(1))
+}
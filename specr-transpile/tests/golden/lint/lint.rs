@@ -0,0 +1,10 @@
+pub struct Names {
+    names: Vec<String>,
+}
+pub fn squares(n: Int) -> List<Int> {
+    let mut l = List::new();
+    for i in Int::ZERO..n {
+        l.push(i * i);
+    }
+    l
+}
@@ -0,0 +1,18 @@
+trait Foo {
+    fn foo1(&self);
+    fn foo2(&self);
+}
+impl Foo for () {
+    fn foo1(&self) {}
+    fn foo2(&self) {}
+}
+pub struct Bar;
+impl Bar {
+    pub fn bar1() {}
+    pub fn bar2() {}
+}
+#[cfg(feature = "extra")]
+impl Bar {
+    pub fn extra1() {}
+    pub fn extra2() {}
+}
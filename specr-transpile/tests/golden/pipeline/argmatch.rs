@@ -0,0 +1,15 @@
+pub enum Expr<T> {
+    Const(T),
+    Neg(#[specr::indirection] Expr<T>),
+}
+impl<T> Expr<T> {
+    pub fn depth(self) -> u32 {
+        match self {
+            Expr::Const(_) => 0,
+            Expr::Neg(e) => 1 + e.depth(),
+        }
+    }
+}
+pub fn first(l: List<u32>) -> u32 {
+    l[Int::from(0)]
+}
@@ -0,0 +1,26 @@
+#[derive(GcCompat)]
+#[derive(Debug)]
+#[derive(libspecr::Encode)]
+#[derive(Clone)]
+#[derive(Copy)]
+#[derive(PartialEq)]
+#[derive(Eq)]
+#[derive(Hash)]
+pub enum Expr<T: libspecr::hidden::Obj> {
+    Const(T),
+    Neg(libspecr::hidden::GcCow<Expr<T>>),
+}
+impl<T: libspecr::hidden::Obj> Expr<T> {
+    pub fn depth(self) -> u32 {
+        match self {
+            Expr::Const(_) => 0,
+            Expr::Neg(e) => {
+                let e = e.extract();
+                { 1 + e.depth() }
+            }
+        }
+    }
+}
+pub fn first(l: List<u32>) -> u32 {
+    (l).index_at(Int::from(0))
+}
@@ -0,0 +1,18 @@
+pub enum Expr<T> {
+    Const(T),
+    Neg(libspecr::hidden::GcCow<Expr<T>>),
+}
+impl<T> Expr<T> {
+    pub fn depth(self) -> u32 {
+        match self {
+            Expr::Const(_) => 0,
+            Expr::Neg(e) => {
+                let e = e.extract();
+                { 1 + e.depth() }
+            }
+        }
+    }
+}
+pub fn first(l: List<u32>) -> u32 {
+    l[Int::from(0)]
+}
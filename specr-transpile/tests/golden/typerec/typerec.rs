@@ -0,0 +1,33 @@
+pub enum List {
+    Cons(u32, libspecr::hidden::GcCow<List>),
+    Nil,
+}
+impl List {
+    pub fn len(&self) -> u32 {
+        match self {
+            List::Cons(_, tail) => {
+                let tail = &tail.extract();
+                1 + tail.len()
+            }
+            List::Nil => 0,
+        }
+    }
+    pub fn is_single(&self) -> bool {
+        match self {
+            List::Cons(
+                _,
+                tail,
+            ) if {
+                let tail = &tail.extract();
+                tail.len() == 0
+            } => {
+                let tail = &tail.extract();
+                true
+            }
+            _ => false,
+        }
+    }
+    pub fn push(self, x: u32) -> List {
+        List::Cons(x, libspecr::hidden::GcCow::new(self))
+    }
+}
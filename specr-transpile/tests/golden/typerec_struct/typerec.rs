@@ -0,0 +1,51 @@
+pub struct Node {
+    val: u32,
+    next: libspecr::hidden::GcCow<Option<Node>>,
+}
+pub struct Tree(u32, libspecr::hidden::GcCow<Option<(Tree, Tree)>>);
+pub struct A {
+    n: u32,
+    b: Option<B>,
+}
+pub struct B {
+    a: libspecr::hidden::GcCow<A>,
+}
+impl Node {
+    pub fn push(self, val: u32) -> Node {
+        Node {
+            val,
+            next: libspecr::hidden::GcCow::new(Some(self)),
+        }
+    }
+    pub fn second(&self) -> Option<u32> {
+        Some(self.next.extract()?.val)
+    }
+    pub fn set_next(&mut self, next: Option<Node>) {
+        self.next = libspecr::hidden::GcCow::new(next);
+    }
+}
+pub fn sum(t: Tree) -> u32 {
+    let Tree(x, children) = t;
+    let children = children.extract();
+    match children {
+        Some((l, r)) => x + sum(l) + sum(r),
+        None => x,
+    }
+}
+pub fn inc(b: &mut B) {
+    (*b.a.mutate_guard()).n += 1;
+    assert!(b.a.extract().n > 0);
+}
+pub struct M {
+    n: Node,
+}
+impl M {
+    pub fn inc_second(&mut self) {
+        if let Some(ref mut x) = *self.n.next.mutate_guard() {
+            x.val += 1;
+        }
+    }
+    pub fn truncate(&mut self) -> Option<Node> {
+        (*self.n.next.mutate_guard()).take()
+    }
+}
@@ -0,0 +1,26 @@
+pub enum Foo {
+    Cons(u32, libspecr::hidden::GcCow<Foo>),
+    Nil,
+}
+pub struct Cons(u32, Foo);
+impl Foo {
+    pub fn push(self, x: u32) -> Foo {
+        Foo::Cons(x, libspecr::hidden::GcCow::new(self))
+    }
+    pub fn to_cons(self, x: u32) -> Cons {
+        Cons(x, self)
+    }
+    pub fn len(self) -> u32 {
+        match self {
+            Foo::Cons(_, tail) => {
+                let tail = tail.extract();
+                1 + tail.len()
+            }
+            Foo::Nil => 0,
+        }
+    }
+}
+pub fn tail(c: Cons) -> Foo {
+    let Cons(_, tail) = c;
+    tail
+}
@@ -0,0 +1,269 @@
+pub type Locals = Map<LocalName, Value>;
+#[derive(GcCompat)]
+#[derive(Debug)]
+#[derive(libspecr::Encode)]
+#[derive(Clone)]
+#[derive(Copy)]
+#[derive(PartialEq)]
+#[derive(Eq)]
+#[derive(Hash)]
+pub struct LocalName(Int);
+#[derive(GcCompat)]
+#[derive(Debug)]
+#[derive(libspecr::Encode)]
+#[derive(Clone)]
+#[derive(Copy)]
+#[derive(PartialEq)]
+#[derive(Eq)]
+#[derive(Hash)]
+pub enum Value {
+    Int(Int),
+    Tuple(List<Value>),
+    Ptr { addr: Int, meta: libspecr::hidden::GcCow<Option<Value>> },
+}
+#[derive(GcCompat)]
+#[derive(Debug)]
+#[derive(libspecr::Encode)]
+#[derive(Clone)]
+#[derive(Copy)]
+#[derive(PartialEq)]
+#[derive(Eq)]
+#[derive(Hash)]
+pub struct Frame {
+    locals: Locals,
+    ret: (Value, bool),
+    depth: Int,
+}
+#[derive(GcCompat)]
+#[derive(Debug)]
+#[derive(libspecr::Encode)]
+#[derive(Clone)]
+#[derive(Copy)]
+#[derive(PartialEq)]
+#[derive(Eq)]
+#[derive(Hash)]
+pub enum Never {}
+#[doc(hidden)]
+#[allow(unused_variables, unused_mut, clippy::all)]
+pub mod specr_visit {
+    use super::*;
+    use crate::visit::{Visit, VisitMut, Fold};
+    pub fn visit_local_name<V: Visit + ?Sized>(v: &mut V, node: &LocalName) {}
+    pub fn visit_local_name_mut<V: VisitMut + ?Sized>(v: &mut V, node: &mut LocalName) {}
+    pub fn fold_local_name<V: Fold + ?Sized>(v: &mut V, node: LocalName) -> LocalName {
+        let mut node = node;
+        node
+    }
+    pub fn visit_value<V: Visit + ?Sized>(v: &mut V, node: &Value) {
+        match node {
+            Value::Int(f0) => {}
+            Value::Tuple(f0) => {
+                let x0 = f0;
+                for x1 in x0.iter() {
+                    let x1 = &x1;
+                    v.visit_value(x1);
+                }
+            }
+            Value::Ptr { addr: f0, meta: f1 } => {
+                let x0 = f1;
+                let x1 = &x0.extract();
+                if let Some(x2) = x1 {
+                    v.visit_value(x2);
+                }
+            }
+        }
+    }
+    pub fn visit_value_mut<V: VisitMut + ?Sized>(v: &mut V, node: &mut Value) {
+        match node {
+            Value::Int(f0) => {}
+            Value::Tuple(f0) => {
+                let x0 = f0;
+                *x0 = x0
+                    .iter()
+                    .map(|mut x1| {
+                        {
+                            let x1 = &mut x1;
+                            v.visit_value_mut(x1);
+                        }
+                        x1
+                    })
+                    .collect();
+            }
+            Value::Ptr { addr: f0, meta: f1 } => {
+                let x0 = f1;
+                let mut guard = x0.mutate_guard();
+                let x1 = &mut *guard;
+                if let Some(x2) = x1 {
+                    v.visit_value_mut(x2);
+                }
+            }
+        }
+    }
+    pub fn fold_value<V: Fold + ?Sized>(v: &mut V, node: Value) -> Value {
+        match node {
+            Value::Int(f0) => Value::Int(f0),
+            Value::Tuple(f0) => {
+                let f0 = {
+                    let x0 = f0;
+                    x0.iter().map(|x1| v.fold_value(x1)).collect()
+                };
+                Value::Tuple(f0)
+            }
+            Value::Ptr { addr: f0, meta: f1 } => {
+                let f1 = {
+                    let x0 = f1;
+                    libspecr::hidden::GcCow::new({
+                        let x1 = x0.extract();
+                        x1.map(|x2| v.fold_value(x2))
+                    })
+                };
+                Value::Ptr { addr: f0, meta: f1 }
+            }
+        }
+    }
+    pub fn visit_frame<V: Visit + ?Sized>(v: &mut V, node: &Frame) {
+        {
+            let x0 = &node.locals;
+            for (k, val) in x0.iter() {
+                {
+                    let x1 = &k;
+                    v.visit_local_name(x1);
+                }
+                {
+                    let x1 = &val;
+                    v.visit_value(x1);
+                }
+            }
+        }
+        {
+            let x0 = &node.ret;
+            {
+                let x1 = &x0.0;
+                v.visit_value(x1);
+            }
+        }
+    }
+    pub fn visit_frame_mut<V: VisitMut + ?Sized>(v: &mut V, node: &mut Frame) {
+        {
+            let x0 = &mut node.locals;
+            *x0 = x0
+                .iter()
+                .map(|(mut k, mut val)| {
+                    {
+                        let x1 = &mut k;
+                        v.visit_local_name_mut(x1);
+                    }
+                    {
+                        let x1 = &mut val;
+                        v.visit_value_mut(x1);
+                    }
+                    (k, val)
+                })
+                .collect();
+        }
+        {
+            let x0 = &mut node.ret;
+            {
+                let x1 = &mut x0.0;
+                v.visit_value_mut(x1);
+            }
+        }
+    }
+    pub fn fold_frame<V: Fold + ?Sized>(v: &mut V, node: Frame) -> Frame {
+        let mut node = node;
+        {
+            let x0 = node.locals;
+            node.locals = x0
+                .iter()
+                .map(|(k, val)| (
+                    {
+                        let x1 = k;
+                        v.fold_local_name(x1)
+                    },
+                    {
+                        let x1 = val;
+                        v.fold_value(x1)
+                    },
+                ))
+                .collect();
+        }
+        {
+            let x0 = node.ret;
+            node.ret = (
+                {
+                    let x1 = x0.0;
+                    v.fold_value(x1)
+                },
+                x0.1,
+            );
+        }
+        node
+    }
+    pub fn visit_never<V: Visit + ?Sized>(v: &mut V, node: &Never) {
+        match *node {}
+    }
+    pub fn visit_never_mut<V: VisitMut + ?Sized>(v: &mut V, node: &mut Never) {
+        match *node {}
+    }
+    pub fn fold_never<V: Fold + ?Sized>(v: &mut V, node: Never) -> Never {
+        match node {}
+    }
+}
+
+// mod visit
+//! Traversals of the types of the crate, generated by the `visit` pass of specr-transpile.
+/// Visits a value by reference. Override the methods of the types you are interested in,
+/// and call `visit::visit_<type>` from them to visit their fields.
+pub trait Visit {
+    fn visit_local_name(&mut self, node: &crate::visitor::LocalName) {
+        visit_local_name(self, node)
+    }
+    fn visit_value(&mut self, node: &crate::visitor::Value) {
+        visit_value(self, node)
+    }
+    fn visit_frame(&mut self, node: &crate::visitor::Frame) {
+        visit_frame(self, node)
+    }
+    fn visit_never(&mut self, node: &crate::visitor::Never) {
+        visit_never(self, node)
+    }
+}
+/// Visits a value by mutable reference, like `Visit`.
+pub trait VisitMut {
+    fn visit_local_name_mut(&mut self, node: &mut crate::visitor::LocalName) {
+        visit_local_name_mut(self, node)
+    }
+    fn visit_value_mut(&mut self, node: &mut crate::visitor::Value) {
+        visit_value_mut(self, node)
+    }
+    fn visit_frame_mut(&mut self, node: &mut crate::visitor::Frame) {
+        visit_frame_mut(self, node)
+    }
+    fn visit_never_mut(&mut self, node: &mut crate::visitor::Never) {
+        visit_never_mut(self, node)
+    }
+}
+/// Transforms a value, like `Visit`.
+pub trait Fold {
+    fn fold_local_name(
+        &mut self,
+        node: crate::visitor::LocalName,
+    ) -> crate::visitor::LocalName {
+        fold_local_name(self, node)
+    }
+    fn fold_value(&mut self, node: crate::visitor::Value) -> crate::visitor::Value {
+        fold_value(self, node)
+    }
+    fn fold_frame(&mut self, node: crate::visitor::Frame) -> crate::visitor::Frame {
+        fold_frame(self, node)
+    }
+    fn fold_never(&mut self, node: crate::visitor::Never) -> crate::visitor::Never {
+        fold_never(self, node)
+    }
+}
+pub use crate::visitor::specr_visit::{
+    visit_local_name, visit_local_name_mut, fold_local_name,
+};
+pub use crate::visitor::specr_visit::{visit_value, visit_value_mut, fold_value};
+pub use crate::visitor::specr_visit::{visit_frame, visit_frame_mut, fold_frame};
+pub use crate::visitor::specr_visit::{visit_never, visit_never_mut, fold_never};